@@ -0,0 +1,415 @@
+use bevy::prelude::*;
+use bevy_spicy_aseprite::{AsepriteAnimation, AsepriteAnimationState, AsepriteTag};
+use serde::{Deserialize, Serialize};
+
+use crate::{persist::PersistAppExt, sprites, time_scale::TimeScale};
+
+/// How long a cow keeps eating whatever it was fed.
+const COW_EAT_SECS: f32 = 2.;
+/// How long a cow shows the in-between facing when turning around.
+const COW_TURN_SECS: f32 = 0.12;
+/// Share of its speed a cow keeps while eating, e.g. shuffling along a trough.
+const COW_EAT_SPEED: f32 = 0.25;
+/// How long the player stands still petting an NPC.
+const PLAYER_PET_SECS: f32 = 0.5;
+
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum AnimState {
+    Idle,
+    Walk,
+    Sleep,
+    Attack,
+    Eat,
+    Pet,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum Facing {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Facing {
+    /// Facing for the dominant axis of `direction`.
+    pub fn from_direction(direction: Vec2) -> Self {
+        match direction.x.abs() > direction.y.abs() {
+            true if direction.x < 0. => Facing::West,
+            true => Facing::East,
+            false if direction.y < 0. => Facing::South,
+            false => Facing::North,
+        }
+    }
+
+    pub fn direction(self) -> Vec2 {
+        match self {
+            Facing::North => Vec2::Y,
+            Facing::East => Vec2::X,
+            Facing::South => -Vec2::Y,
+            Facing::West => -Vec2::X,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Facing::North => Facing::South,
+            Facing::East => Facing::West,
+            Facing::South => Facing::North,
+            Facing::West => Facing::East,
+        }
+    }
+
+    pub fn clockwise(self) -> Self {
+        match self {
+            Facing::North => Facing::East,
+            Facing::East => Facing::South,
+            Facing::South => Facing::West,
+            Facing::West => Facing::North,
+        }
+    }
+}
+
+/// How an entity may move while an animation state plays, so locomotion
+/// can't run ahead of the animation, e.g. sliding along mid-attack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovementLimits {
+    /// Multiplier on the velocity the entity is given, 0 to stand still.
+    pub speed: f32,
+    /// Whether the facing can change.
+    pub turn: bool,
+}
+
+impl Default for MovementLimits {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            turn: true,
+        }
+    }
+}
+
+/// Drives an entity's `AsepriteAnimation` from a logical state and facing.
+///
+/// Systems only pick the state and facing, `sync_animations` looks up the
+/// matching tag and swaps it in.
+///
+/// The state, facing and time in the state are saved, so loading doesn't cut
+/// a sleep or meal short. The rest is set up where the entity is spawned.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct AnimStateMachine {
+    state: AnimState,
+    facing: Facing,
+    /// Seconds since `state` was entered.
+    elapsed: f32,
+    /// Without north/south tags, vertical movement keeps the facing.
+    #[reflect(ignore)]
+    four_way: bool,
+    #[reflect(ignore)]
+    tags: Vec<(AnimState, Facing, AsepriteTag)>,
+    /// States that can't be left before playing for the given seconds.
+    #[reflect(ignore)]
+    min_durations: Vec<(AnimState, f32)>,
+    /// States limiting movement, see `MovementLimits`.
+    #[reflect(ignore)]
+    movement_limits: Vec<(AnimState, MovementLimits)>,
+    /// Checked before `tags`, e.g. for terrain specific walk cycles.
+    #[reflect(ignore)]
+    overrides: Vec<(AnimState, Facing, AsepriteTag)>,
+    /// Seconds turn-arounds show the in-between facing for, 0 to turn at once.
+    #[reflect(ignore)]
+    turn_secs: f32,
+    /// In-between facing shown for the turn-around in progress, and the
+    /// seconds left of it.
+    #[reflect(ignore)]
+    turn: Option<(Facing, f32)>,
+    /// Facing west shows the east tags flipped horizontally.
+    #[reflect(ignore)]
+    flip_west: bool,
+    /// Sprite sheet frame shown as of the last `AnimFrameEvent`.
+    #[reflect(ignore)]
+    frame: Option<usize>,
+}
+
+impl Default for AnimStateMachine {
+    fn default() -> Self {
+        Self::new(AnimState::Idle, Facing::South, false)
+    }
+}
+
+impl AnimStateMachine {
+    pub fn new(state: AnimState, facing: Facing, four_way: bool) -> Self {
+        Self {
+            state,
+            facing,
+            elapsed: 0.,
+            four_way,
+            tags: Vec::new(),
+            min_durations: Vec::new(),
+            movement_limits: Vec::new(),
+            overrides: Vec::new(),
+            turn_secs: 0.,
+            turn: None,
+            flip_west: false,
+            frame: None,
+        }
+    }
+
+    pub fn with_tag(mut self, state: AnimState, facing: Facing, tag: AsepriteTag) -> Self {
+        self.tags.push((state, facing, tag));
+        self
+    }
+
+    /// Uses `tag` for `state` regardless of facing.
+    pub fn with_tag_any_facing(self, state: AnimState, tag: AsepriteTag) -> Self {
+        [Facing::North, Facing::East, Facing::South, Facing::West]
+            .iter()
+            .fold(self, |machine, facing| {
+                machine.with_tag(state, *facing, tag)
+            })
+    }
+
+    pub fn with_min_duration(mut self, state: AnimState, secs: f32) -> Self {
+        self.min_durations.push((state, secs));
+        self
+    }
+
+    /// Limits movement while `state` plays, enforced by `face` and by physics
+    /// when integrating velocities.
+    pub fn with_movement_limits(mut self, state: AnimState, limits: MovementLimits) -> Self {
+        self.movement_limits.push((state, limits));
+        self
+    }
+
+    /// Turning to the opposite facing briefly shows the facing clockwise of
+    /// the old one, e.g. south between east and west, so turning around
+    /// doesn't pop. Has no effect for states without a tag for it.
+    pub fn with_turn_secs(mut self, secs: f32) -> Self {
+        self.turn_secs = secs;
+        self
+    }
+
+    /// Shows the east tags mirrored when facing west, so only east-facing
+    /// animations need to be authored. West tags are ignored.
+    pub fn with_flipped_west(mut self) -> Self {
+        self.flip_west = true;
+        self
+    }
+
+    pub fn player() -> Self {
+        use sprites::Player::tags;
+
+        Self::new(AnimState::Idle, Facing::West, false)
+            .with_tag(AnimState::Idle, Facing::East, tags::EAST_IDLE)
+            .with_tag(AnimState::Walk, Facing::East, tags::EAST_WALK)
+            .with_flipped_west()
+            .with_min_duration(AnimState::Attack, 0.3)
+            .with_movement_limits(
+                AnimState::Attack,
+                MovementLimits {
+                    speed: 0.,
+                    turn: false,
+                },
+            )
+            // player.ase has no petting tag yet, so this only holds the idle pose
+            .with_min_duration(AnimState::Pet, PLAYER_PET_SECS)
+            .with_movement_limits(
+                AnimState::Pet,
+                MovementLimits {
+                    speed: 0.,
+                    turn: false,
+                },
+            )
+    }
+
+    pub fn cow() -> Self {
+        use sprites::Cow::tags;
+
+        Self::new(AnimState::Idle, Facing::South, true)
+            .with_tag(AnimState::Idle, Facing::North, tags::NORTH_IDLE)
+            .with_tag(AnimState::Idle, Facing::East, tags::EAST_IDLE)
+            .with_tag(AnimState::Idle, Facing::South, tags::SOUTH_IDLE)
+            .with_tag(AnimState::Idle, Facing::West, tags::WEST_IDLE)
+            .with_tag(AnimState::Walk, Facing::North, tags::NORTH_WALK)
+            .with_tag(AnimState::Walk, Facing::East, tags::EAST_WALK)
+            .with_tag(AnimState::Walk, Facing::South, tags::SOUTH_WALK)
+            .with_tag(AnimState::Walk, Facing::West, tags::WEST_WALK)
+            .with_tag_any_facing(AnimState::Sleep, tags::SLEEP)
+            // cow.ase has no eating tag yet, so this only holds the idle pose
+            .with_min_duration(AnimState::Eat, COW_EAT_SECS)
+            .with_movement_limits(
+                AnimState::Eat,
+                MovementLimits {
+                    speed: COW_EAT_SPEED,
+                    turn: false,
+                },
+            )
+            .with_movement_limits(
+                AnimState::Sleep,
+                MovementLimits {
+                    speed: 0.,
+                    turn: false,
+                },
+            )
+            .with_turn_secs(COW_TURN_SECS)
+    }
+
+    pub fn state(&self) -> AnimState {
+        self.state
+    }
+
+    pub fn facing(&self) -> Facing {
+        self.facing
+    }
+
+    /// Switches to `state` unless the current one hasn't played for its
+    /// minimum duration yet. Returns whether `state` is now the current one.
+    pub fn set_state(&mut self, state: AnimState) -> bool {
+        if state == self.state {
+            return true;
+        }
+        let min_duration = self
+            .min_durations
+            .iter()
+            .find(|(locked, _)| *locked == self.state)
+            .map_or(0., |(_, secs)| *secs);
+        if self.elapsed < min_duration {
+            return false;
+        }
+        self.state = state;
+        self.elapsed = 0.;
+        true
+    }
+
+    /// Movement allowed in the current state.
+    pub fn movement_limits(&self) -> MovementLimits {
+        self.movement_limits
+            .iter()
+            .find(|(state, _)| *state == self.state)
+            .map_or_else(MovementLimits::default, |(_, limits)| *limits)
+    }
+
+    /// Turns towards `direction`, keeping the facing for a zero direction or
+    /// while the current state doesn't allow turning.
+    pub fn face(&mut self, direction: Vec2) {
+        if direction == Vec2::ZERO || !self.movement_limits().turn {
+            return;
+        }
+        let facing = if self.four_way {
+            Facing::from_direction(direction)
+        } else if direction.x != 0. {
+            Facing::from_direction(Vec2::new(direction.x, 0.))
+        } else {
+            return;
+        };
+        if self.turn_secs > 0. && facing == self.facing.opposite() {
+            self.turn = Some((self.facing.clockwise(), self.turn_secs));
+        }
+        self.facing = facing;
+    }
+
+    pub fn set_overrides(&mut self, overrides: &[(AnimState, Facing, AsepriteTag)]) {
+        if self.overrides != overrides {
+            self.overrides = overrides.to_vec();
+        }
+    }
+
+    /// Tag for the current state and facing. States without a tag of their
+    /// own, like attacks on sprites without attack tags, fall back to idle.
+    pub fn tag(&self) -> Option<AsepriteTag> {
+        self.shown().map(|(tag, _)| tag)
+    }
+
+    /// Whether the tag is shown flipped horizontally, see `with_flipped_west`.
+    pub fn flip_x(&self) -> bool {
+        self.shown().map_or(false, |(_, facing)| {
+            self.flip_west && facing == Facing::West
+        })
+    }
+
+    /// Tag shown and the facing it's shown for.
+    fn shown(&self) -> Option<(AsepriteTag, Facing)> {
+        let lookup = |state: AnimState, facing: Facing| {
+            let tag_facing = match facing {
+                Facing::West if self.flip_west => Facing::East,
+                facing => facing,
+            };
+            self.overrides
+                .iter()
+                .chain(self.tags.iter())
+                .find(|(s, f, _)| *s == state && *f == tag_facing)
+                .map(|(_, _, tag)| (*tag, facing))
+        };
+        let turning = self.turn.and_then(|(facing, _)| lookup(self.state, facing));
+        turning
+            .or_else(|| lookup(self.state, self.facing))
+            .or_else(|| lookup(AnimState::Idle, self.facing))
+    }
+}
+
+/// Sent when an animated entity's sprite sheet frame changes, e.g. to leave
+/// a footprint with every step of a walk cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct AnimFrameEvent {
+    pub entity: Entity,
+    pub state: AnimState,
+    pub facing: Facing,
+}
+
+pub struct AnimPlugin;
+
+impl Plugin for AnimPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AnimState>()
+            .register_type::<Facing>()
+            .persist_component::<AnimStateMachine>()
+            .add_event::<AnimFrameEvent>()
+            .add_system_to_stage(CoreStage::PostUpdate, sync_animations);
+    }
+}
+
+fn sync_animations(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut frame_events: EventWriter<AnimFrameEvent>,
+    mut anim_q: Query<(
+        Entity,
+        &mut AnimStateMachine,
+        &mut AsepriteAnimation,
+        &mut AsepriteAnimationState,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+) {
+    let delta = time_scale.delta_seconds(&time);
+    for (entity, mut machine, mut anim, mut anim_state, sprite) in anim_q.iter_mut() {
+        machine.elapsed += delta;
+        if let Some((facing, secs_left)) = machine.turn {
+            machine.turn = Some((facing, secs_left - delta)).filter(|(_, secs)| *secs > 0.);
+        }
+        if let Some(tag) = machine.tag() {
+            if !anim.is_tag(tag) {
+                *anim = AsepriteAnimation::from(tag);
+            }
+        }
+        if let Some(mut sprite) = sprite {
+            let flip_x = machine.flip_x();
+            if sprite.flip_x != flip_x {
+                sprite.flip_x = flip_x;
+            }
+            if machine.frame != Some(sprite.index) {
+                machine.frame = Some(sprite.index);
+                frame_events.send(AnimFrameEvent {
+                    entity,
+                    state: machine.state,
+                    facing: machine.facing,
+                });
+            }
+        }
+        if machine.state == AnimState::Walk && anim_state.is_paused() {
+            anim_state.start();
+        }
+    }
+}
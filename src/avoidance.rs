@@ -0,0 +1,119 @@
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+
+use crate::{
+    collision::{CollisionWorld, QueryMask, Velocity},
+    phase::{Phase, PhaseExt},
+    AppState, PlayerTag,
+};
+
+/// How far ahead agents look for collisions with each other, in seconds.
+const LOOKAHEAD_SECS: f32 = 0.75;
+/// Radius assumed for movers that aren't agents, i.e. the player.
+const DEFAULT_RADIUS: f32 = 16.;
+/// How hard agents turn away from a predicted collision, relative to their
+/// speed.
+const AVOID_STRENGTH: f32 = 1.5;
+
+/// Makes a moving NPC steer around other agents and the player before
+/// running into them, instead of only being pushed apart on contact.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CrowdAgent {
+    /// Room around the agent's center. Two movers keep the sum of their
+    /// radii between their centers.
+    pub radius: f32,
+}
+
+pub struct AvoidancePlugin;
+
+impl Plugin for AvoidancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(avoid_crowds),
+        );
+    }
+}
+
+/// Velocity change steering `agent` away from `other`, if they'd come closer
+/// than `radius` within `LOOKAHEAD_SECS` at their current velocities.
+fn avoidance(agent: (Vec2, Vec2), other: (Vec2, Vec2), radius: f32) -> Vec2 {
+    let (position, velocity) = agent;
+    let offset = other.0 - position;
+    let relative = velocity - other.1;
+    let speed_sq = relative.length_squared();
+    if speed_sq == 0. {
+        return Vec2::ZERO;
+    }
+    let t = (offset.dot(relative) / speed_sq).clamp(0., LOOKAHEAD_SECS);
+    let closest = offset - relative * t;
+    let distance = closest.length();
+    if distance >= radius {
+        return Vec2::ZERO;
+    }
+    // Head-on, pass on the right
+    let away = if distance > 0. {
+        -closest / distance
+    } else {
+        -relative.perp().normalize()
+    };
+    // Sooner and closer collisions weigh more
+    away * (1. - t / LOOKAHEAD_SECS) * (1. - distance / radius)
+}
+
+/// Runs after the AI set this frame's velocities, turning them away from
+/// predicted collisions without changing their speed. Neighbors come from the
+/// collision world, so only movers with colliders are avoided.
+fn avoid_crowds(
+    collision_world: Res<CollisionWorld>,
+    mut agent_q: Query<(Entity, &GlobalTransform, &CrowdAgent, &mut Velocity)>,
+    player_q: Query<(Entity, &GlobalTransform, &Velocity), (With<PlayerTag>, Without<CrowdAgent>)>,
+) {
+    // Velocities as the AI left them, so agents react to each other's
+    // intentions rather than to whoever was steered first
+    let mut movers: HashMap<Entity, (Vec2, Vec2, Option<f32>)> = agent_q
+        .iter()
+        .map(|(entity, trans, agent, velocity)| {
+            (
+                entity,
+                (trans.translation.xy(), velocity.0, Some(agent.radius)),
+            )
+        })
+        .collect();
+    for (entity, trans, velocity) in player_q.iter() {
+        movers.insert(entity, (trans.translation.xy(), velocity.0, None));
+    }
+
+    for (entity, trans, agent, mut velocity) in agent_q.iter_mut() {
+        let speed = velocity.0.length();
+        if speed == 0. {
+            continue;
+        }
+        let position = trans.translation.xy();
+        let reach = Vec2::splat(speed * LOOKAHEAD_SECS + agent.radius + DEFAULT_RADIUS);
+        let neighbors = collision_world.query_region(
+            position - reach,
+            position + reach,
+            QueryMask::COLLIDERS.excluding(entity),
+        );
+        let mut steering = Vec2::ZERO;
+        for neighbor in neighbors {
+            let (other_position, other_velocity, other_radius) = match movers.get(&neighbor) {
+                Some(mover) => *mover,
+                None => continue,
+            };
+            let radius = agent.radius + other_radius.unwrap_or(DEFAULT_RADIUS);
+            // Other agents steer too, so each takes half the avoidance
+            let share = if other_radius.is_some() { 0.5 } else { 1. };
+            steering += avoidance(
+                (position, velocity.0),
+                (other_position, other_velocity),
+                radius,
+            ) * share;
+        }
+        if steering != Vec2::ZERO {
+            let steered = velocity.0 + steering * speed * AVOID_STRENGTH;
+            velocity.0 = steered.normalize_or_zero() * speed;
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    clock::WorldClock,
+    cooldown::{Cooldown, TimerAppExt},
+    floating_text::SpawnFloatingText,
+    localization::Localization,
+    npc::NpcState,
+    phase::{Phase, PhaseExt},
+    rng::GameRng,
+    AppState, PlayerTag,
+};
+
+/// Seconds between an NPC's barks, picked at random in this range.
+const BARK_COOLDOWN_SECS: (f32, f32) = (8., 20.);
+/// NPCs further than this from the player keep quiet, nobody would see it.
+const BARK_HEARING_RADIUS: f32 = 160.;
+const BARK_COLOR: Color = Color::rgb(0.9, 0.9, 0.8);
+
+/// A line an NPC may say now and then, see `Barks`.
+#[derive(Debug, Clone)]
+pub struct BarkLine {
+    /// Localization key of the text.
+    pub key: &'static str,
+    /// In-game hours the line fits in, see `WorldClock::is_between`.
+    pub hours: Option<(f32, f32)>,
+    /// Only said with the player at most this far away.
+    pub max_player_distance: Option<f32>,
+}
+
+impl BarkLine {
+    fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            hours: None,
+            max_player_distance: None,
+        }
+    }
+
+    fn between(self, from: f32, to: f32) -> Self {
+        Self {
+            hours: Some((from, to)),
+            ..self
+        }
+    }
+
+    fn near_player(self, distance: f32) -> Self {
+        Self {
+            max_player_distance: Some(distance),
+            ..self
+        }
+    }
+
+    fn fits(&self, clock: &WorldClock, player_distance: f32) -> bool {
+        self.hours
+            .map_or(true, |(from, to)| clock.is_between(from, to))
+            && self
+                .max_player_distance
+                .map_or(true, |distance| player_distance <= distance)
+    }
+}
+
+/// Ambient one-liners an idle NPC says as floating text while the player is
+/// around, each time picking one that fits the time of day and how close the
+/// player is.
+#[derive(Component, Debug, Clone)]
+pub struct Barks {
+    pub lines: Vec<BarkLine>,
+}
+
+impl Barks {
+    pub fn new(lines: Vec<BarkLine>) -> Self {
+        Self { lines }
+    }
+
+    pub fn cow() -> Self {
+        Self::new(vec![
+            BarkLine::new("bark.moo"),
+            BarkLine::new("bark.grass"),
+            BarkLine::new("bark.nice_weather").between(8., 18.),
+            BarkLine::new("bark.early").between(5., 8.),
+            BarkLine::new("bark.late").between(20., 5.),
+            BarkLine::new("bark.hello").near_player(48.),
+        ])
+    }
+}
+
+/// `Barks` along with the cooldown until the first bark.
+#[derive(Bundle)]
+pub struct BarksBundle {
+    pub barks: Barks,
+    pub cooldown: Cooldown<Barks>,
+}
+
+impl BarksBundle {
+    pub fn new(barks: Barks) -> Self {
+        Self {
+            barks,
+            cooldown: Cooldown::started(BARK_COOLDOWN_SECS.0),
+        }
+    }
+}
+
+pub struct BarkPlugin;
+
+impl Plugin for BarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_cooldown::<Barks>().add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Ai)
+                .with_system(bark),
+        );
+    }
+}
+
+fn bark(
+    clock: Res<WorldClock>,
+    localization: Res<Localization>,
+    mut rng: ResMut<GameRng>,
+    mut floating_texts: EventWriter<SpawnFloatingText>,
+    player_q: Query<&GlobalTransform, With<PlayerTag>>,
+    mut barker_q: Query<(
+        Entity,
+        &Barks,
+        &mut Cooldown<Barks>,
+        &GlobalTransform,
+        Option<&NpcState>,
+    )>,
+) {
+    let player_pos = match player_q.get_single() {
+        Ok(trans) => trans.translation.xy(),
+        Err(_) => return,
+    };
+    let rng = rng.stream("bark");
+    for (entity, barks, mut cooldown, trans, state) in barker_q.iter_mut() {
+        if !cooldown.is_ready() {
+            continue;
+        }
+        let (min, max) = BARK_COOLDOWN_SECS;
+        cooldown.reset_to(rng.range(min, max));
+
+        // Sleeping and fleeing NPCs have better things to do
+        if matches!(state, Some(NpcState::Sleeping) | Some(NpcState::Fleeing)) {
+            continue;
+        }
+        let player_distance = trans.translation.xy().distance(player_pos);
+        if player_distance > BARK_HEARING_RADIUS {
+            continue;
+        }
+        let fitting: Vec<&BarkLine> = barks
+            .lines
+            .iter()
+            .filter(|line| line.fits(&clock, player_distance))
+            .collect();
+        if fitting.is_empty() {
+            continue;
+        }
+        let line = fitting[rng.index(fitting.len())];
+        floating_texts.send(SpawnFloatingText {
+            anchor: entity,
+            text: localization.get(line.key),
+            color: BARK_COLOR,
+        });
+    }
+}
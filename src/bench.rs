@@ -0,0 +1,310 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    ecs::{
+        event::Events,
+        system::{BoxedSystem, System},
+    },
+    prelude::*,
+    transform::transform_propagate_system::transform_propagate_system,
+    utils::HashMap,
+};
+
+use crate::{
+    collision::{
+        apply_velocity, detect_collisions, handle_collision, updated_computed_aabbs, AabbBundle,
+        AabbKind, CollisionBehavior, CollisionMatrix, CollisionWorld, CollisionWorlds, ImpactEvent,
+        PhysicsSettings, PhysicsStats, Velocity,
+    },
+    physics_dump::PhysicsDump,
+    time_scale::TimeScale,
+};
+
+pub const DEFAULT_COLLIDER_COUNT: usize = 500;
+const FRAMES: u32 = 300;
+/// Average area per collider, in square sprite pixels. Dense enough that a
+/// good share of colliders overlap every frame.
+const AREA_PER_COLLIDER: f32 = 48. * 48.;
+const COLLIDER_SIZE: f32 = 16.;
+const MAX_SPEED: f32 = 60.;
+
+/// Timings of one physics stage system over all frames.
+struct StageTimings {
+    name: &'static str,
+    system: BoxedSystem,
+    total: Duration,
+    max: Duration,
+}
+
+impl StageTimings {
+    fn new(name: &'static str, system: BoxedSystem, world: &mut World) -> Self {
+        let mut system = system;
+        system.initialize(world);
+        Self {
+            name,
+            system,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+
+    fn run(&mut self, world: &mut World) {
+        let start = Instant::now();
+        self.system.run((), world);
+        self.system.apply_buffers(world);
+        let elapsed = start.elapsed();
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+}
+
+/// Tiny LCG so runs are repeatable without pulling in a rand dependency.
+struct Lcg(u64);
+
+impl Lcg {
+    /// Uniform in `[0, 1)`.
+    fn next(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// World with the resources the physics stage needs, to run it headlessly.
+fn physics_world() -> World {
+    let mut world = World::new();
+    world.insert_resource(Time::default());
+    world.init_resource::<CollisionWorld>();
+    world.init_resource::<CollisionWorlds>();
+    world.init_resource::<CollisionMatrix>();
+    world.init_resource::<PhysicsSettings>();
+    world.init_resource::<PhysicsStats>();
+    world.init_resource::<TimeScale>();
+    world.init_resource::<Events<ImpactEvent>>();
+    world
+}
+
+/// The physics stage systems, in order.
+fn physics_stages(world: &mut World) -> Vec<StageTimings> {
+    vec![
+        StageTimings::new(
+            "updated_computed_aabbs",
+            Box::new(updated_computed_aabbs.system()),
+            world,
+        ),
+        StageTimings::new(
+            "detect_collisions",
+            Box::new(detect_collisions.system()),
+            world,
+        ),
+        StageTimings::new(
+            "handle_collision",
+            Box::new(handle_collision.system()),
+            world,
+        ),
+        StageTimings::new("apply_velocity", Box::new(apply_velocity.system()), world),
+        StageTimings::new(
+            "transform_propagate_system",
+            Box::new(transform_propagate_system.system()),
+            world,
+        ),
+    ]
+}
+
+/// Runs one physics tick over `world`.
+fn step(world: &mut World, stages: &mut [StageTimings]) {
+    world.get_resource_mut::<Time>().unwrap().update();
+    for stage in stages.iter_mut() {
+        stage.run(world);
+    }
+    world
+        .get_resource_mut::<Events<ImpactEvent>>()
+        .unwrap()
+        .update();
+    world.clear_trackers();
+}
+
+/// Runs the physics stage headlessly over `count` moving colliders and prints
+/// per-system timings. Started with `--bench-collisions N`.
+pub fn bench_collisions(count: usize) {
+    let mut world = physics_world();
+    let half_size = (AREA_PER_COLLIDER * count as f32).sqrt() / 2.;
+    let mut rng = Lcg(0x5eed);
+    for _ in 0..count {
+        let position = Vec2::new(rng.next(), rng.next()) * 2. * half_size - half_size;
+        let velocity = Vec2::new(rng.next() - 0.5, rng.next() - 0.5) * 2. * MAX_SPEED;
+        world
+            .spawn()
+            .insert(Transform::from_translation(position.extend(0.)))
+            .insert(GlobalTransform::default())
+            .insert(Velocity(velocity))
+            .with_children(|parent| {
+                parent.spawn().insert_bundle(AabbBundle::new(
+                    Vec2::splat(COLLIDER_SIZE),
+                    AabbKind::Collider,
+                    CollisionBehavior::Npc,
+                    Color::GREEN,
+                ));
+            });
+    }
+
+    let mut stages = physics_stages(&mut world);
+
+    let mut total_pairs = 0;
+    for _ in 0..FRAMES {
+        step(&mut world, &mut stages);
+        total_pairs += world.get_resource::<CollisionWorld>().unwrap().pairs.len();
+    }
+
+    println!(
+        "{} colliders, {} frames, {:.1} overlapping pairs per frame",
+        count,
+        FRAMES,
+        total_pairs as f64 / FRAMES as f64
+    );
+    println!("{:<28} {:>10} {:>10}", "system", "mean ms", "max ms");
+    for stage in stages.iter() {
+        println!(
+            "{:<28} {:>10.3} {:>10.3}",
+            stage.name,
+            stage.total.as_secs_f64() * 1000. / FRAMES as f64,
+            stage.max.as_secs_f64() * 1000.
+        );
+    }
+}
+
+/// A `dump_physics` dump spawned into a headless physics world, to step it
+/// outside of the game, e.g. to turn a collision bug report into a test.
+///
+/// Owner components other than velocity and mass, e.g. character
+/// controllers, aren't in dumps, so their owners respond like plain bodies.
+pub struct PhysicsReplay {
+    pub dump: PhysicsDump,
+    pub world: World,
+    /// Spawned owner of each dumped owner index.
+    pub owners: HashMap<u32, Entity>,
+    stages: Vec<StageTimings>,
+}
+
+impl PhysicsReplay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let dump = PhysicsDump::load(path)?;
+        let mut world = physics_world();
+        let owners = dump.spawn(&mut world);
+        // AABBs are computed from their global transforms, which the first
+        // step would otherwise only propagate after detecting collisions
+        SystemStage::single(transform_propagate_system).run(&mut world);
+        let stages = physics_stages(&mut world);
+        Ok(Self {
+            dump,
+            world,
+            owners,
+            stages,
+        })
+    }
+
+    /// Runs one physics tick.
+    pub fn step(&mut self) {
+        step(&mut self.world, &mut self.stages);
+    }
+
+    /// Dumped index of a spawned owner.
+    pub fn dumped_id(&self, entity: Entity) -> u32 {
+        self.owners
+            .iter()
+            .find(|(_, owner)| **owner == entity)
+            .map_or(entity.id(), |(id, _)| *id)
+    }
+
+    /// Where the owner dumped as `id` is now.
+    pub fn position(&self, id: u32) -> Vec2 {
+        self.world
+            .get::<Transform>(self.owners[&id])
+            .unwrap()
+            .translation
+            .truncate()
+    }
+
+    /// Overlapping pairs found in the last tick, as dumped owner indices.
+    pub fn pairs(&self) -> Vec<(u32, u32)> {
+        self.collision_world()
+            .pairs
+            .iter()
+            .map(|pair| (self.dumped_id(pair.ent1), self.dumped_id(pair.ent2)))
+            .collect()
+    }
+
+    fn collision_world(&self) -> &CollisionWorld {
+        self.world.get_resource::<CollisionWorld>().unwrap()
+    }
+}
+
+/// Loads a `dump_physics` dump and steps it headlessly for `frames` physics
+/// ticks, printing the overlaps and where the owners end up, to reproduce
+/// collision bugs outside of the game. Started with
+/// `--replay-physics FILE [FRAMES]`.
+pub fn replay_physics(path: &str, frames: u32) {
+    let mut replay = match PhysicsReplay::load(path) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Failed to load {}: {}", path, err);
+            return;
+        }
+    };
+
+    println!(
+        "{} AABBs of {} owners in {}, dumped with {} overlapping pairs",
+        replay.dump.aabbs.len(),
+        replay.owners.len(),
+        replay.dump.world,
+        replay.dump.pairs.len()
+    );
+    for frame in 1..=frames {
+        replay.step();
+        println!("frame {}", frame);
+        for pair in replay.collision_world().pairs.iter() {
+            println!(
+                "  {:?} {} - {}, normal ({:.3}, {:.3}), depth {:.3}",
+                pair.kind,
+                replay.dumped_id(pair.ent1),
+                replay.dumped_id(pair.ent2),
+                pair.contact.normal.x,
+                pair.contact.normal.y,
+                pair.contact.depth,
+            );
+        }
+    }
+
+    let mut ids: Vec<u32> = replay.owners.keys().copied().collect();
+    ids.sort_unstable();
+    for id in ids {
+        let position = replay.position(id);
+        println!("{} at ({:.3}, {:.3})", id, position.x, position.y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump_path(name: &str) -> String {
+        format!("{}/test_dumps/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    /// Player 1 spawned 3 pixels deep into wall 2, to its right.
+    #[test]
+    fn player_is_pushed_out_of_wall() {
+        let mut replay = PhysicsReplay::load(&dump_path("player_in_wall.ron")).unwrap();
+        replay.step();
+        assert_eq!(replay.pairs(), vec![(1, 2)]);
+        assert_eq!(replay.position(1), Vec2::new(-3., 0.));
+        assert_eq!(replay.position(2), Vec2::new(13., 0.));
+
+        // Resting against the wall from then on
+        replay.step();
+        assert_eq!(replay.pairs(), vec![(1, 2)]);
+        assert_eq!(replay.position(1), Vec2::new(-3., 0.));
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{
+    phase::{Phase, PhaseExt},
+    settings::Settings,
+};
+
+/// Controls how many screen pixels one world (sprite) pixel covers.
+pub struct PixelCameraSettings {
+    pub zoom: f32,
+    /// Round the zoom to a whole number so sprite pixels stay square and crisp.
+    pub integer_scaling: bool,
+    /// Draw the camera and sprites at whole world pixels, so sprites don't
+    /// shimmer while moving at sub-pixel speeds.
+    pub snap_to_pixels: bool,
+}
+
+impl Default for PixelCameraSettings {
+    fn default() -> Self {
+        Self {
+            zoom: 4.,
+            integer_scaling: true,
+            snap_to_pixels: true,
+        }
+    }
+}
+
+impl PixelCameraSettings {
+    pub fn effective_zoom(&self) -> f32 {
+        if self.integer_scaling {
+            self.zoom.round().max(1.)
+        } else {
+            self.zoom.max(f32::EPSILON)
+        }
+    }
+}
+
+/// Marks the world camera driven by `PixelCameraSettings`.
+#[derive(Component)]
+pub struct PixelCameraTag;
+
+pub struct PixelCameraPlugin;
+
+impl Plugin for PixelCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PixelCameraSettings>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(camera_hotkeys),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(apply_camera_zoom),
+            )
+            // After the physics stages moved and propagated everything
+            .add_system_to_stage(CoreStage::Last, snap_to_pixels);
+    }
+}
+
+fn camera_hotkeys(
+    keys: Res<Input<KeyCode>>,
+    mut camera_settings: ResMut<PixelCameraSettings>,
+    mut settings: ResMut<Settings>,
+) {
+    if keys.just_pressed(KeyCode::F11) {
+        settings.window_mode = settings.window_mode.next();
+        settings.save();
+    }
+    if keys.just_pressed(KeyCode::Equals) {
+        camera_settings.zoom += 1.;
+    }
+    if keys.just_pressed(KeyCode::Minus) {
+        camera_settings.zoom = (camera_settings.zoom - 1.).max(1.);
+    }
+}
+
+fn apply_camera_zoom(
+    camera_settings: Res<PixelCameraSettings>,
+    mut projection_q: Query<&mut OrthographicProjection, With<PixelCameraTag>>,
+) {
+    if !camera_settings.is_changed() {
+        return;
+    }
+
+    for mut projection in projection_q.iter_mut() {
+        projection.scale = 1. / camera_settings.effective_zoom();
+    }
+}
+
+/// Rounds the drawn positions of the camera and sprites to whole world
+/// pixels. Only `GlobalTransform` is rounded, `Transform` keeps the sub-pixel
+/// position for movement to build on.
+fn snap_to_pixels(
+    camera_settings: Res<PixelCameraSettings>,
+    mut trans_q: Query<
+        &mut GlobalTransform,
+        Or<(With<PixelCameraTag>, With<Sprite>, With<TextureAtlasSprite>)>,
+    >,
+) {
+    if !camera_settings.snap_to_pixels {
+        return;
+    }
+    for mut trans in trans_q.iter_mut() {
+        let snapped = trans.translation.truncate().round();
+        // Leave change detection alone for what's already snapped
+        if snapped != trans.translation.truncate() {
+            trans.translation = snapped.extend(trans.translation.z);
+        }
+    }
+}
+
+/// Converts the cursor position on `window` into world coordinates as seen by
+/// the given camera, or `None` if the cursor is outside the window.
+pub fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_trans: &GlobalTransform,
+) -> Option<Vec2> {
+    let cursor = window.cursor_position()?;
+    let window_size = Vec2::new(window.width(), window.height());
+    let ndc = (cursor / window_size) * 2. - Vec2::ONE;
+    let ndc_to_world = camera_trans.compute_matrix() * camera.projection_matrix.inverse();
+    Some(ndc_to_world.project_point3(ndc.extend(-1.)).truncate())
+}
@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+
+use crate::{
+    localization::Localization,
+    notify::Notify,
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    quest::{QuestCompleted, QuestFailed, QuestLog, QuestSystem},
+    settings::Settings,
+    time_scale::TimeScale,
+    AppState,
+};
+
+/// Countdown turns red with this many seconds left.
+const WARNING_SECS: f32 = 10.;
+const TIMER_COLOR: Color = Color::WHITE;
+const WARNING_COLOR: Color = Color::rgb(1., 0.35, 0.3);
+
+/// Countdown of the active quest while `Settings::timed_mode` is on. Quests
+/// without a `time_limit` aren't timed.
+#[derive(Debug, Default)]
+pub struct QuestTimer {
+    /// Quest being timed.
+    quest: Option<String>,
+    remaining: f32,
+    /// Time taken on the quest since it was started, or last failed.
+    elapsed: f32,
+}
+
+#[derive(Component)]
+struct QuestTimerTextTag;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ChallengeSystem {
+    QuestTimer,
+}
+
+pub struct ChallengePlugin;
+
+impl Plugin for ChallengePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuestTimer>()
+            .add_startup_system(setup_timer_text)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(
+                        run_quest_timer
+                            .label(ChallengeSystem::QuestTimer)
+                            .after(QuestSystem::Progress),
+                    )
+                    .with_system(notify_quest_failed.after(ChallengeSystem::QuestTimer)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_timer_text),
+            );
+    }
+}
+
+fn setup_timer_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(56.),
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 24.,
+                    color: TIMER_COLOR,
+                },
+                Default::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(QuestTimerTextTag);
+}
+
+/// Records best times of completed timed quests, starts the countdown of the
+/// next one and fails the active quest once its time is up. A failed quest
+/// loses its progress and starts over with a fresh countdown.
+#[allow(clippy::too_many_arguments)]
+fn run_quest_timer(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    settings: Res<Settings>,
+    mut timer: ResMut<QuestTimer>,
+    mut quest_log: ResMut<QuestLog>,
+    mut stats: ResMut<PlayerStats>,
+    mut quest_completed: EventReader<QuestCompleted>,
+    mut quest_failed: EventWriter<QuestFailed>,
+) {
+    for completed in quest_completed.iter() {
+        if timer.quest.as_ref() != Some(&completed.id) {
+            continue;
+        }
+        let best = stats
+            .best_quest_times
+            .entry(completed.id.clone())
+            .or_insert(timer.elapsed);
+        *best = best.min(timer.elapsed);
+        info!(quest = %completed.id, "Completed in {:.1}s", timer.elapsed);
+    }
+
+    let limit = quest_log
+        .active_quest()
+        .and_then(|quest| Some((quest.id.clone(), quest.time_limit?)))
+        .filter(|_| settings.timed_mode);
+    let (id, limit) = match limit {
+        Some(limit) => limit,
+        None => {
+            if timer.quest.is_some() {
+                *timer = QuestTimer::default();
+            }
+            return;
+        }
+    };
+    if timer.quest.as_ref() != Some(&id) {
+        *timer = QuestTimer {
+            quest: Some(id),
+            remaining: limit,
+            elapsed: 0.,
+        };
+        return;
+    }
+
+    let delta = time_scale.delta(&time).as_secs_f32();
+    timer.remaining -= delta;
+    timer.elapsed += delta;
+    if timer.remaining <= 0. {
+        info!(quest = %id, "Quest failed, out of time");
+        quest_log.delivered = 0;
+        timer.remaining = limit;
+        timer.elapsed = 0.;
+        quest_failed.send(QuestFailed { id });
+    }
+}
+
+fn notify_quest_failed(
+    mut quest_failed: EventReader<QuestFailed>,
+    localization: Res<Localization>,
+    mut notifications: EventWriter<Notify>,
+) {
+    for _ in quest_failed.iter() {
+        notifications.send(Notify::new(localization.get("notify.quest_failed")));
+    }
+}
+
+fn update_timer_text(
+    state: Res<State<AppState>>,
+    timer: Res<QuestTimer>,
+    mut text_q: Query<(&mut Text, &mut Visibility), With<QuestTimerTextTag>>,
+) {
+    let shown = timer.quest.is_some() && *state.current() == AppState::Playing;
+    for (mut text, mut visibility) in text_q.iter_mut() {
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+        if !shown {
+            continue;
+        }
+        let secs = timer.remaining.max(0.).ceil() as u32;
+        let value = format!("{}:{:02}", secs / 60, secs % 60);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+            text.sections[0].style.color = if timer.remaining <= WARNING_SECS {
+                WARNING_COLOR
+            } else {
+                TIMER_COLOR
+            };
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+
+use crate::{
+    level::{spawn_wall, Wall, WallLayout},
+    origin::WorldOrigin,
+    phase::{Phase, PhaseExt},
+    AppState, PlayerTag,
+};
+
+/// Side length of a chunk, in world pixels.
+pub const CHUNK_SIZE: f32 = 256.;
+/// Chunks up to this many chunks away from the player's are loaded.
+const LOAD_RADIUS: i32 = 1;
+/// Loaded chunks are only unloaded this far away, so walking along a chunk
+/// border doesn't load and unload the same chunks every frame.
+const UNLOAD_RADIUS: i32 = 2;
+/// Most walls spawned per frame, so loading chunks full of walls is spread
+/// over a few frames instead of hitching.
+const SPAWN_BUDGET: usize = 32;
+
+#[derive(Debug, Default)]
+struct Chunk {
+    walls: Vec<WallLayout>,
+    /// Spawned walls, `None` while unloaded. Filled in over a few frames
+    /// after loading, see `SPAWN_BUDGET`.
+    entities: Option<Vec<Entity>>,
+}
+
+/// The level's walls bucketed by chunk, so only the ones around the player
+/// are spawned and have colliders in `CollisionWorld`.
+///
+/// Walls belong to the chunk their center is in. A wall reaching further
+/// than `CHUNK_SIZE` out of its chunk can pop in late.
+#[derive(Default)]
+pub struct WorldChunks {
+    chunks: HashMap<IVec2, Chunk>,
+    /// Walls of loaded chunks still to be spawned, oldest first.
+    pending: VecDeque<(IVec2, WallLayout)>,
+}
+
+impl WorldChunks {
+    pub fn from_walls(walls: &[WallLayout]) -> Self {
+        let mut world_chunks = Self::default();
+        for wall in walls {
+            world_chunks
+                .chunks
+                .entry(chunk_coord(wall.position.into()))
+                .or_default()
+                .walls
+                .push(wall.clone());
+        }
+        world_chunks
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.chunks
+            .values()
+            .filter(|chunk| chunk.entities.is_some())
+            .count()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queues the chunk's walls for `spawn_pending`.
+    fn load(&mut self, coord: IVec2) {
+        let chunk = match self.chunks.get_mut(&coord) {
+            Some(chunk) if chunk.entities.is_none() => chunk,
+            _ => return,
+        };
+        chunk.entities = Some(Vec::new());
+        self.pending
+            .extend(chunk.walls.iter().map(|wall| (coord, wall.clone())));
+    }
+
+    /// Spawns up to `budget` queued walls.
+    fn spawn_pending(&mut self, commands: &mut Commands, origin: &WorldOrigin, budget: usize) {
+        let count = self.pending.len().min(budget);
+        for (coord, wall) in self.pending.drain(..count) {
+            let position = origin.to_local(wall.position.into());
+            let entity = spawn_wall(commands, position, wall.size.into());
+            if let Some(entities) = self
+                .chunks
+                .get_mut(&coord)
+                .and_then(|chunk| chunk.entities.as_mut())
+            {
+                entities.push(entity);
+            }
+        }
+    }
+
+    fn unload(&mut self, commands: &mut Commands, coord: IVec2) {
+        self.pending.retain(|(pending, _)| *pending != coord);
+        let entities = self
+            .chunks
+            .get_mut(&coord)
+            .and_then(|chunk| chunk.entities.take());
+        for entity in entities.into_iter().flatten() {
+            // Evicted from `CollisionWorld` through `RemovedComponents<Aabb>`
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn chunk_coord(position: Vec2) -> IVec2 {
+    (position / CHUNK_SIZE).floor().as_ivec2()
+}
+
+pub struct ChunkPlugin;
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldChunks>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(stream_chunks),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Editor).with_system(load_all_chunks))
+            .add_system_set(SystemSet::on_exit(AppState::Editor).with_system(rechunk_walls));
+    }
+}
+
+fn stream_chunks(
+    mut commands: Commands,
+    mut world_chunks: ResMut<WorldChunks>,
+    origin: Res<WorldOrigin>,
+    player_q: Query<&GlobalTransform, With<PlayerTag>>,
+) {
+    let center = chunk_coord(origin.to_world(player_q.single().translation.xy()));
+    let coords: Vec<IVec2> = world_chunks.chunks.keys().copied().collect();
+    for coord in coords {
+        let distance = (coord - center).abs().max_element();
+        if distance <= LOAD_RADIUS {
+            world_chunks.load(coord);
+        } else if distance > UNLOAD_RADIUS {
+            world_chunks.unload(&mut commands, coord);
+        }
+    }
+    world_chunks.spawn_pending(&mut commands, &origin, SPAWN_BUDGET);
+}
+
+/// The editor works on, and exports, the spawned walls, so all of them need
+/// to be around while editing.
+fn load_all_chunks(
+    mut commands: Commands,
+    mut world_chunks: ResMut<WorldChunks>,
+    origin: Res<WorldOrigin>,
+) {
+    let coords: Vec<IVec2> = world_chunks.chunks.keys().copied().collect();
+    for coord in coords {
+        world_chunks.load(coord);
+    }
+    world_chunks.spawn_pending(&mut commands, &origin, usize::MAX);
+}
+
+/// Re-buckets the walls after editing, as they may have been placed, moved
+/// or deleted.
+fn rechunk_walls(
+    mut world_chunks: ResMut<WorldChunks>,
+    origin: Res<WorldOrigin>,
+    wall_q: Query<(Entity, &Transform, &Wall)>,
+) {
+    let mut chunks: HashMap<IVec2, Chunk> = HashMap::default();
+    for (entity, trans, wall) in wall_q.iter() {
+        let position = origin.to_world(trans.translation.xy());
+        let chunk = chunks.entry(chunk_coord(position)).or_default();
+        chunk.walls.push(WallLayout {
+            position: position.into(),
+            size: wall.size.into(),
+        });
+        chunk.entities.get_or_insert_with(Vec::new).push(entity);
+    }
+    world_chunks.chunks = chunks;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+
+    fn wall(x: f32, y: f32) -> WallLayout {
+        WallLayout {
+            position: [x, y],
+            size: [16., 16.],
+        }
+    }
+
+    #[test]
+    fn chunk_coord_rounds_down_on_negative_coordinates() {
+        assert_eq!(chunk_coord(Vec2::ZERO), IVec2::ZERO);
+        assert_eq!(chunk_coord(Vec2::new(CHUNK_SIZE - 0.5, 0.)), IVec2::ZERO);
+        assert_eq!(chunk_coord(Vec2::new(CHUNK_SIZE, 0.)), IVec2::new(1, 0));
+        assert_eq!(chunk_coord(Vec2::new(-0.5, -0.5)), IVec2::new(-1, -1));
+        assert_eq!(chunk_coord(Vec2::new(-CHUNK_SIZE, 0.)), IVec2::new(-1, 0));
+        assert_eq!(
+            chunk_coord(Vec2::new(-CHUNK_SIZE - 0.5, 0.)),
+            IVec2::new(-2, 0)
+        );
+    }
+
+    #[test]
+    fn walls_are_bucketed_by_chunk() {
+        let world_chunks = WorldChunks::from_walls(&[
+            wall(10., 10.),
+            wall(20., 20.),
+            wall(-10., 10.),
+            wall(-10., -300.),
+        ]);
+        assert_eq!(world_chunks.chunk_count(), 3);
+        assert_eq!(world_chunks.chunks[&IVec2::ZERO].walls.len(), 2);
+        assert_eq!(world_chunks.chunks[&IVec2::new(-1, 0)].walls.len(), 1);
+        assert_eq!(world_chunks.chunks[&IVec2::new(-1, -2)].walls.len(), 1);
+        assert_eq!(world_chunks.loaded_count(), 0);
+    }
+
+    #[test]
+    fn load_spawn_and_unload() {
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let origin = WorldOrigin::default();
+        let coord = IVec2::new(-1, 0);
+        let mut world_chunks = WorldChunks::from_walls(&[wall(-10., 10.), wall(-20., 10.)]);
+
+        world_chunks.load(coord);
+        // Loading again doesn't queue the walls twice
+        world_chunks.load(coord);
+        // Nor does loading a chunk without walls do anything
+        world_chunks.load(IVec2::new(5, 5));
+        assert_eq!(world_chunks.loaded_count(), 1);
+        assert_eq!(world_chunks.pending_count(), 2);
+
+        let mut commands = Commands::new(&mut queue, &world);
+        world_chunks.spawn_pending(&mut commands, &origin, 1);
+        queue.apply(&mut world);
+        assert_eq!(world_chunks.pending_count(), 1);
+        let spawned = world_chunks.chunks[&coord].entities.clone().unwrap();
+        assert_eq!(spawned.len(), 1);
+        assert!(world.get::<Wall>(spawned[0]).is_some());
+
+        // Unloading drops the walls not spawned yet along with the spawned
+        let mut commands = Commands::new(&mut queue, &world);
+        world_chunks.unload(&mut commands, coord);
+        queue.apply(&mut world);
+        assert_eq!(world_chunks.pending_count(), 0);
+        assert_eq!(world_chunks.loaded_count(), 0);
+        assert!(world.get_entity(spawned[0]).is_none());
+    }
+}
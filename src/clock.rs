@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+use crate::{
+    phase::{Phase, PhaseExt},
+    weather::Weather,
+    AppState,
+};
+
+/// Real seconds per in-game day.
+const DAY_LENGTH_SECS: f32 = 600.;
+const START_HOURS: f32 = 8.;
+
+/// In-game time of day, advancing while playing.
+pub struct WorldClock {
+    /// Hours since midnight, in `[0, 24)`.
+    pub hours: f32,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self { hours: START_HOURS }
+    }
+}
+
+impl WorldClock {
+    /// Whether the clock is within `[from, to)`, wrapping past midnight if
+    /// `to` is before `from`.
+    pub fn is_between(&self, from: f32, to: f32) -> bool {
+        if from <= to {
+            self.hours >= from && self.hours < to
+        } else {
+            self.hours >= from || self.hours < to
+        }
+    }
+}
+
+#[derive(Component)]
+struct ClockTextTag;
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldClock>()
+            .add_startup_system(setup_clock_text)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(tick_clock),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_clock_text),
+            );
+    }
+}
+
+fn tick_clock(time: Res<Time>, mut clock: ResMut<WorldClock>) {
+    clock.hours = (clock.hours + time.delta_seconds() * 24. / DAY_LENGTH_SECS) % 24.;
+}
+
+fn setup_clock_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(36.),
+                    right: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 24.,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(ClockTextTag);
+}
+
+fn update_clock_text(
+    clock: Res<WorldClock>,
+    weather: Res<Weather>,
+    mut text_q: Query<&mut Text, With<ClockTextTag>>,
+) {
+    if !clock.is_changed() && !weather.is_changed() {
+        return;
+    }
+
+    let minutes = (clock.hours * 60.) as u32;
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = format!(
+            "{:02}:{:02} {:?}",
+            minutes / 60,
+            minutes % 60,
+            weather.kind()
+        );
+    }
+}
@@ -0,0 +1,1697 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bevy::{ecs::schedule::ShouldRun, math::Vec3Swizzles, prelude::*};
+use bevy_prototype_lyon::{
+    entity::ShapeBundle,
+    prelude::{DrawMode, FillMode, GeometryBuilder, StrokeMode},
+    shapes,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anim::AnimStateMachine, mods, rollback::RollbackBuffer, terrain::OnTerrain,
+    time_scale::TimeScale, DebugRender, DebugRenderTag,
+};
+
+static COLLISION_MATRIX_FILE: &str = "collision_matrix.ron";
+/// How often the collision matrix file is checked for changes.
+const MATRIX_RELOAD_SECS: f32 = 1.;
+
+/// AABBs closer than this are considered touching.
+const CONTACT_SKIN: f32 = 0.01;
+/// AABBs whose centers are closer than this count as coincident, see
+/// `knock_away_direction`.
+const COINCIDENT_EPSILON: f32 = 0.001;
+/// Minimum speed into a surface for a new contact to count as an impact.
+const IMPACT_MIN_SPEED: f32 = 40.;
+/// Sweeps per `CharacterController::move_and_slide`, each sliding along the
+/// surface the previous one stopped at.
+const MAX_SLIDES: usize = 4;
+/// Gap characters keep to the surfaces they stop at, within `CONTACT_SKIN`
+/// so they still count as touching.
+const SLIDE_SKIN: f32 = 0.005;
+
+#[derive(Component)]
+pub struct ColliderTag;
+
+#[derive(Component)]
+pub struct SensorTag;
+
+#[derive(Component)]
+pub struct Aabb {
+    pub extents: Vec2,
+    /// Offset from the AABB entity's translation. Like the extents, it isn't
+    /// rotated or scaled along with the entity, so an owner can be made up of
+    /// several offset boxes.
+    pub offset: Vec2,
+}
+
+impl Aabb {
+    /// Half extents; `extents` holds the full size like lyon's `Rectangle`.
+    pub fn extents(&self) -> Vec2 {
+        self.extents / 2.0
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AabbKind {
+    Sensor,
+    Collider,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CollisionKind {
+    SensorSensor,
+    ColliderCollider,
+    SensorCollider,
+}
+
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionBehavior {
+    None,
+    Static,
+    Npc,
+    Player,
+    Movable,
+}
+
+/// How two overlapping colliders are pushed apart.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionResponse {
+    /// The colliders pass through each other.
+    Ignore,
+    /// The first collider of the pair is pushed out of the second.
+    PushFirst,
+    /// The second collider of the pair is pushed out of the first.
+    PushSecond,
+    /// Both colliders are pushed out, sharing the depth by inverse `Mass`:
+    /// the lighter one moves further, equal masses move half the depth each.
+    Split,
+}
+
+impl CollisionResponse {
+    fn flipped(self) -> Self {
+        match self {
+            CollisionResponse::PushFirst => CollisionResponse::PushSecond,
+            CollisionResponse::PushSecond => CollisionResponse::PushFirst,
+            response => response,
+        }
+    }
+}
+
+/// Responses of colliding behavior pairs, loaded from
+/// `assets/collision_matrix.ron`, or a mod's override of it, and reloaded
+/// whenever the file changes.
+///
+/// Each pair applies either way round. Pairs missing from the matrix pass
+/// through each other.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CollisionMatrix {
+    pub responses: Vec<(CollisionBehavior, CollisionBehavior, CollisionResponse)>,
+    /// File the matrix was loaded from, and when it was last modified.
+    #[serde(skip)]
+    loaded: Option<(PathBuf, SystemTime)>,
+}
+
+impl Default for CollisionMatrix {
+    /// Used until the matrix file is loaded, and if it's missing.
+    fn default() -> Self {
+        Self {
+            responses: vec![
+                (
+                    CollisionBehavior::Player,
+                    CollisionBehavior::Static,
+                    CollisionResponse::PushFirst,
+                ),
+                (
+                    CollisionBehavior::Player,
+                    CollisionBehavior::Npc,
+                    CollisionResponse::PushFirst,
+                ),
+                (
+                    CollisionBehavior::Npc,
+                    CollisionBehavior::Static,
+                    CollisionResponse::PushFirst,
+                ),
+                (
+                    CollisionBehavior::Npc,
+                    CollisionBehavior::Npc,
+                    CollisionResponse::Split,
+                ),
+                (
+                    CollisionBehavior::Player,
+                    CollisionBehavior::Movable,
+                    CollisionResponse::Split,
+                ),
+                (
+                    CollisionBehavior::Npc,
+                    CollisionBehavior::Movable,
+                    CollisionResponse::Split,
+                ),
+                (
+                    CollisionBehavior::Movable,
+                    CollisionBehavior::Movable,
+                    CollisionResponse::Split,
+                ),
+                (
+                    CollisionBehavior::Movable,
+                    CollisionBehavior::Static,
+                    CollisionResponse::PushFirst,
+                ),
+            ],
+            loaded: None,
+        }
+    }
+}
+
+impl CollisionMatrix {
+    pub fn response(
+        &self,
+        first: CollisionBehavior,
+        second: CollisionBehavior,
+    ) -> CollisionResponse {
+        self.responses
+            .iter()
+            .find_map(|&(a, b, response)| {
+                if (a, b) == (first, second) {
+                    Some(response)
+                } else if (b, a) == (first, second) {
+                    Some(response.flipped())
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(CollisionResponse::Ignore)
+    }
+
+    /// Reloads the matrix if its file changed since it was last loaded, or a
+    /// mod now overrides it. A malformed mod override falls back to the file
+    /// below it, see `mods::load_ron`, and if none loads the current matrix is
+    /// kept.
+    fn reload(&mut self) {
+        let path = mods::resolve(COLLISION_MATRIX_FILE);
+        let modified = match std::fs::metadata(&path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return,
+        };
+        let loaded = Some((path.clone(), modified));
+        if loaded == self.loaded {
+            return;
+        }
+        self.loaded = loaded;
+
+        match mods::load_ron::<CollisionMatrix>(COLLISION_MATRIX_FILE) {
+            Ok(matrix) => {
+                info!("Loaded {}", COLLISION_MATRIX_FILE);
+                self.responses = matrix.responses;
+            }
+            Err(err) => error!("Keeping the collision matrix, {}", err),
+        }
+    }
+}
+
+pub fn reload_collision_matrix(
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+    mut matrix: ResMut<CollisionMatrix>,
+) {
+    // Load right away on the first run, then poll for changes
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(MATRIX_RELOAD_SECS, true));
+    if matrix.loaded.is_some() && !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    matrix.reload();
+}
+
+/// How hard an entity is to push, see `CollisionResponse::Split`. Entities
+/// without one have a mass of 1, an infinite mass can't be pushed at all.
+/// Neither can a mass that isn't positive, e.g. one typed into the editor,
+/// rather than an infinite inverse spreading NaNs through the transforms.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Mass(pub f32);
+
+impl Mass {
+    pub fn inverse(&self) -> f32 {
+        if self.0 > 0. {
+            1. / self.0
+        } else {
+            0.
+        }
+    }
+}
+
+/// Turns an AABB entity off without despawning it, e.g. for open doors or
+/// i-frames. Disabled AABBs are taken out of the `CollisionWorld`, so they are
+/// neither detected, resolved nor returned by queries. AABBs without this
+/// component are enabled.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColliderEnabled(pub bool);
+
+impl Default for ColliderEnabled {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Marks the entity that moves with the AABBs below it in the hierarchy, for
+/// AABBs that aren't direct children of it, e.g. a collider under an
+/// attachment point. AABBs without a marked ancestor belong to their parent.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RigidBodyRoot;
+
+/// Entity an AABB belongs to, which collision response moves and events name:
+/// its closest `RigidBodyRoot` ancestor, or else its parent.
+fn resolve_root(
+    parent: Entity,
+    parent_q: &Query<&Parent>,
+    root_q: &Query<(), With<RigidBodyRoot>>,
+) -> Entity {
+    let mut ancestor = parent;
+    loop {
+        if root_q.get(ancestor).is_ok() {
+            return ancestor;
+        }
+        match parent_q.get(ancestor) {
+            Ok(next) => ancestor = **next,
+            Err(_) => return parent,
+        }
+    }
+}
+
+/// Lets an entity pass through NPCs until the timer runs out, e.g. while
+/// dashing. Static colliders still block it.
+#[derive(Component, Debug)]
+pub struct IFrames(pub Timer);
+
+/// Makes a character get pushed out of other characters' way instead of
+/// pushing them, e.g. for companions that shouldn't shove the player around.
+#[derive(Component, Debug)]
+pub struct Yielding;
+
+/// Kinematic movement for characters, the player and NPCs alike.
+///
+/// Their `Velocity` is set by input or AI and integrated with `move_and_slide`,
+/// which stops them at static colliders instead of tunneling through and
+/// slides them along. Overlaps with other characters and movable objects are
+/// still pushed apart by `handle_collision`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CharacterController {
+    /// Steepest angle, in radians, between the movement and a surface that is
+    /// slid along at full speed instead of losing the part going into it. Zero
+    /// for top-down, where there are no slopes to walk up.
+    pub max_slope: f32,
+    /// Highest edge, in world units, a character steps past instead of being
+    /// stopped by, measured across the movement. Zero for top-down, where
+    /// every edge is a wall.
+    pub max_step: f32,
+    /// Surfaces within this distance past the end of a move are moved up to,
+    /// so characters come to rest flush against walls.
+    pub snap_distance: f32,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            max_slope: 0.,
+            max_step: 0.,
+            snap_distance: 1.,
+        }
+    }
+}
+
+impl CharacterController {
+    /// Moves the AABB `aabb` by up to `motion` through the AABBs matching
+    /// `mask`, sliding along the surfaces it hits. Returns how far it moved.
+    ///
+    /// AABBs it already touches only block motion into them, pushing it out
+    /// of overlaps is left to `handle_collision`.
+    pub fn move_and_slide(
+        &self,
+        collision_world: &CollisionWorld,
+        aabb: &AabbComputed,
+        motion: Vec2,
+        mask: QueryMask,
+    ) -> Vec2 {
+        let half_extents = (aabb.max - aabb.min) / 2.;
+        let start = aabb.center();
+        let mut center = start;
+        let mut remaining = motion;
+        for _ in 0..MAX_SLIDES {
+            let distance = remaining.length();
+            if distance == 0. {
+                break;
+            }
+            let dir = remaining / distance;
+            let moved = AabbComputed {
+                min: center - half_extents,
+                max: center + half_extents,
+                ..*aabb
+            };
+
+            let mut closest: Option<(f32, Vec2)> = None;
+            for (entity, other) in collision_world.aabbs.values() {
+                if !mask.matches(*entity, other) {
+                    continue;
+                }
+                let hit = match moved.contact(other) {
+                    Some(contact) => (dir.dot(contact.normal) < 0.).then(|| (0., contact.normal)),
+                    None => ray_vs_box(
+                        center,
+                        dir,
+                        other.min - half_extents,
+                        other.max + half_extents,
+                    )
+                    .filter(|(hit_distance, _)| *hit_distance <= distance + self.snap_distance),
+                };
+                // Edges sticking out less than `max_step` across the movement
+                // are stepped past
+                let hit = hit.filter(|(hit_distance, normal)| {
+                    let offset = dir * *hit_distance;
+                    let overlap =
+                        (moved.max + offset).min(other.max) - (moved.min + offset).max(other.min);
+                    overlap.dot(normal.perp()).abs() >= self.max_step
+                });
+                if let Some((hit_distance, normal)) = hit {
+                    if closest.map_or(true, |(closest_distance, _)| {
+                        hit_distance < closest_distance
+                    }) {
+                        closest = Some((hit_distance, normal));
+                    }
+                }
+            }
+
+            let (hit_distance, normal) = match closest {
+                Some(hit) => hit,
+                None => {
+                    center += remaining;
+                    break;
+                }
+            };
+            let travel = (hit_distance - SLIDE_SKIN).max(0.);
+            center += dir * travel;
+            let leftover = (distance - travel).max(0.);
+            let mut slide = Velocity(dir * leftover);
+            slide.slide(normal);
+            // How steeply the movement ran into the surface
+            let angle = (-dir.dot(normal)).clamp(0., 1.).asin();
+            remaining = if angle <= self.max_slope {
+                slide.0.normalize_or_zero() * leftover
+            } else {
+                slide.0
+            };
+        }
+        center - start
+    }
+}
+
+/// Linear velocity in world units per second, integrated by the physics stage
+/// after collision resolution.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Velocity(pub Vec2);
+
+impl Velocity {
+    /// Removes the part of the velocity pushing into a surface with the given
+    /// normal, leaving only the component along the contact tangent.
+    pub fn slide(&mut self, normal: Vec2) {
+        let into = self.0.dot(normal);
+        if into < 0. {
+            self.0 -= normal * into;
+        }
+    }
+}
+
+/// Narrowphase result for an overlapping pair.
+///
+/// `normal` points away from the other AABB, i.e. it is the direction the
+/// first AABB of the pair has to move to separate, and `depth` is how far.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub normal: Vec2,
+    pub depth: f32,
+}
+
+impl Contact {
+    /// The same contact seen from the other AABB of the pair.
+    pub fn flipped(&self) -> Self {
+        Self {
+            normal: -self.normal,
+            depth: self.depth,
+        }
+    }
+
+    /// Full displacement that separates the pair along the contact normal.
+    pub fn displacement(&self) -> Vec2 {
+        self.normal * self.depth
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct AabbComputed {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub aabb_kind: AabbKind,
+    pub collision_behavior: CollisionBehavior,
+}
+
+impl AabbComputed {
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn collision_kind(&self, other: &AabbComputed) -> CollisionKind {
+        match (self.aabb_kind, other.aabb_kind) {
+            (AabbKind::Collider, AabbKind::Collider) => CollisionKind::ColliderCollider,
+            (AabbKind::Collider, AabbKind::Sensor) | (AabbKind::Sensor, AabbKind::Collider) => {
+                CollisionKind::SensorCollider
+            }
+            (AabbKind::Sensor, AabbKind::Sensor) => CollisionKind::SensorSensor,
+        }
+    }
+
+    /// Computes the contact between `self` and `other`, if they overlap or
+    /// touch within `CONTACT_SKIN`.
+    ///
+    /// The contact is resolved along the axis of least penetration. Touching
+    /// AABBs give a zero depth contact, which keeps resting contacts (e.g. while
+    /// sliding along a wall) alive from one tick to the next.
+    pub fn contact(&self, other: &AabbComputed) -> Option<Contact> {
+        let overlap = self.max.min(other.max) - self.min.max(other.min);
+        if overlap.x <= -CONTACT_SKIN || overlap.y <= -CONTACT_SKIN {
+            return None;
+        }
+        let overlap = overlap.max(Vec2::ZERO);
+
+        let delta = self.center() - other.center();
+        let contact = if overlap.x < overlap.y {
+            Contact {
+                normal: Vec2::new(if delta.x < 0. { -1. } else { 1. }, 0.),
+                depth: overlap.x,
+            }
+        } else {
+            Contact {
+                normal: Vec2::new(0., if delta.y < 0. { -1. } else { 1. }),
+                depth: overlap.y,
+            }
+        };
+        Some(contact)
+    }
+
+    /// Contact pushing `self` along the unit vector `normal` until the pair
+    /// no longer overlaps.
+    fn contact_along(&self, other: &AabbComputed, normal: Vec2) -> Contact {
+        let overlap = (self.max.min(other.max) - self.min.max(other.min)).max(Vec2::ZERO);
+        let depth = |axis: usize| {
+            if normal[axis].abs() > f32::EPSILON {
+                overlap[axis] / normal[axis].abs()
+            } else {
+                f32::INFINITY
+            }
+        };
+        Contact {
+            normal,
+            depth: depth(0).min(depth(1)),
+        }
+    }
+}
+
+/// Direction to push `ent1` away from `ent2` when their AABBs are centered on
+/// each other, e.g. after spawning at the same spot, as there is no side of
+/// least penetration then. Pseudo-random so stacks spread out, but the same
+/// every tick, so the pair keeps separating over the next few substeps, where
+/// the regular contact takes over once the centers differ.
+fn knock_away_direction(ent1: Entity, ent2: Entity) -> Vec2 {
+    let (low, high) = (ent1.min(ent2), ent1.max(ent2));
+    let hash = (low.to_bits() ^ high.to_bits().rotate_left(32))
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    let angle = (hash >> 40) as f32 / (1u64 << 24) as f32 * std::f32::consts::TAU;
+    let direction = Vec2::new(angle.cos(), angle.sin());
+    if ent1 == low {
+        direction
+    } else {
+        -direction
+    }
+}
+
+/// Color of an AABB's debug shape while it isn't touching anything, see
+/// `update_debug_shapes`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AabbDebugColor(pub Color);
+
+/// How `update_debug_shapes` draws AABBs, idle ones and ones touching
+/// something. Can be changed at any time, shapes pick it up on the next
+/// physics tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugRenderStyle {
+    /// Colliders in contact with another collider.
+    pub overlapping: Color,
+    /// Sensors with a collider inside.
+    pub sensor_active: Color,
+    /// Idle colliders, instead of their `AabbDebugColor`.
+    pub collider: Option<Color>,
+    /// Idle sensors, instead of their `AabbDebugColor`.
+    pub sensor: Option<Color>,
+    pub line_width: f32,
+    /// Line width of overlapping colliders and active sensors.
+    pub active_line_width: f32,
+}
+
+impl Default for DebugRenderStyle {
+    fn default() -> Self {
+        Self {
+            overlapping: Color::RED,
+            sensor_active: Color::YELLOW,
+            collider: None,
+            sensor: None,
+            line_width: 1.,
+            active_line_width: 2.,
+        }
+    }
+}
+
+impl DebugRenderStyle {
+    /// Colorblind friendly colors from the Okabe-Ito palette, one per state
+    /// instead of per AABB, with thicker lines.
+    pub fn high_contrast() -> Self {
+        Self {
+            overlapping: Color::rgb(0.84, 0.37, 0.),
+            sensor_active: Color::rgb(0.94, 0.89, 0.26),
+            collider: Some(Color::rgb(0.34, 0.71, 0.91)),
+            sensor: Some(Color::rgb(0.8, 0.47, 0.65)),
+            line_width: 1.5,
+            active_line_width: 3.,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct AabbBundle {
+    pub aabb: Aabb,
+    pub aabb_kind: AabbKind,
+    pub collision_behavior: CollisionBehavior,
+    #[bundle]
+    pub debug_shape: ShapeBundle,
+    pub debug_color: AabbDebugColor,
+    pub tag: DebugRenderTag,
+}
+
+impl AabbBundle {
+    pub fn new(
+        extents: Vec2,
+        aabb_kind: AabbKind,
+        collision_behavior: CollisionBehavior,
+        color: Color,
+    ) -> Self {
+        Self::with_offset(extents, Vec2::ZERO, aabb_kind, collision_behavior, color)
+    }
+
+    /// An AABB moved by `offset` from its entity, see `Aabb::offset`.
+    pub fn with_offset(
+        extents: Vec2,
+        offset: Vec2,
+        aabb_kind: AabbKind,
+        collision_behavior: CollisionBehavior,
+        color: Color,
+    ) -> Self {
+        let shape = shapes::Rectangle {
+            extents,
+            origin: bevy_prototype_lyon::prelude::RectangleOrigin::CustomCenter(offset),
+        };
+
+        let builder = GeometryBuilder::new().add(&shape);
+
+        Self {
+            aabb: Aabb { extents, offset },
+            aabb_kind,
+            collision_behavior,
+            debug_shape: builder.build(
+                DrawMode::Outlined {
+                    fill_mode: FillMode::color(Color::NONE),
+                    outline_mode: StrokeMode::color(color),
+                },
+                Transform::default(),
+            ),
+            debug_color: AabbDebugColor(color),
+            tag: DebugRenderTag,
+        }
+    }
+}
+
+/// An overlapping pair found by the narrowphase this frame.
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionPair {
+    pub ent1: Entity,
+    pub ent2: Entity,
+    /// The AABB entities (children of `ent1`/`ent2`) that overlap.
+    pub aabb1: Entity,
+    pub aabb2: Entity,
+    pub kind: CollisionKind,
+    /// Contact as seen from `ent1`.
+    pub contact: Contact,
+}
+
+/// Sent when a collider of `other` starts or stops overlapping a sensor
+/// belonging to `sensor`.
+#[derive(Debug, Clone, Copy)]
+pub enum SensorEvent {
+    Entered { sensor: Entity, other: Entity },
+    Exited { sensor: Entity, other: Entity },
+}
+
+/// Entities with a collider overlapping one of this entity's sensors, as of
+/// the last physics tick, sorted. Added to every entity with a sensor.
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Overlapping(pub Vec<Entity>);
+
+impl Overlapping {
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.0.binary_search(&entity).is_ok()
+    }
+}
+
+/// Sent when a moving collider runs into a static one.
+#[derive(Debug, Clone, Copy)]
+pub struct ImpactEvent {
+    pub entity: Entity,
+    pub position: Vec2,
+    /// Surface normal at the impact, pointing towards `entity`.
+    pub normal: Vec2,
+    pub speed: f32,
+}
+
+#[derive(Default)]
+pub struct CollisionWorld {
+    /// Keyed by the AABB entity. Ordered containers keep iteration, and thus
+    /// collision resolution, deterministic for the same spawn order and inputs.
+    pub aabbs: BTreeMap<Entity, (Entity, AabbComputed)>,
+    /// AABBs taken out of detection and queries by `set_enabled`, still kept
+    /// up to date so they can be put back as they are.
+    pub disabled: BTreeMap<Entity, (Entity, AabbComputed)>,
+    pub pairs: Vec<CollisionPair>,
+    /// (sensor, other) entity pairs overlapping as of the last physics tick.
+    pub sensor_overlaps: BTreeSet<(Entity, Entity)>,
+    /// Collider AABB pairs in contact as of the last physics tick, to tell new
+    /// contacts (impacts) from resting ones.
+    pub contacts: BTreeSet<(Entity, Entity)>,
+}
+
+/// Lets gameplay veto single collision pairs, for one-off exceptions that
+/// don't warrant a new `CollisionBehavior`, e.g. a thrown item passing through
+/// its thrower. Register filters with `ContactFilterAppExt`.
+pub trait ContactFilter: Send + Sync + 'static {
+    /// Whether the owners of `pair` keep overlapping. Vetoed pairs are dropped
+    /// right after the narrowphase, so they are neither resolved nor reported
+    /// as sensor overlaps, contacts or impacts.
+    fn allow(&self, world: &World, pair: &CollisionPair) -> bool;
+}
+
+impl<F> ContactFilter for F
+where
+    F: Fn(&World, &CollisionPair) -> bool + Send + Sync + 'static,
+{
+    fn allow(&self, world: &World, pair: &CollisionPair) -> bool {
+        self(world, pair)
+    }
+}
+
+#[derive(Default)]
+pub struct ContactFilters {
+    filters: Vec<Box<dyn ContactFilter>>,
+    /// Indices into `CollisionWorld::pairs` vetoed in this substep, from
+    /// `filter_contacts`, which only has read access, to `drop_vetoed_contacts`.
+    vetoed: Mutex<Vec<usize>>,
+}
+
+pub trait ContactFilterAppExt {
+    fn add_contact_filter(&mut self, filter: impl ContactFilter) -> &mut Self;
+}
+
+impl ContactFilterAppExt for App {
+    fn add_contact_filter(&mut self, filter: impl ContactFilter) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ContactFilters::default)
+            .filters
+            .push(Box::new(filter));
+        self
+    }
+}
+
+/// World the AABBs of entities without `InCollisionWorld` are in.
+pub const DEFAULT_COLLISION_WORLD: &str = "overworld";
+
+/// Puts the AABBs of an entity in the named collision world instead of
+/// `DEFAULT_COLLISION_WORLD`. Read when the AABBs are updated, so it should
+/// be inserted along with them.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct InCollisionWorld(pub String);
+
+/// Named collision worlds, e.g. an interior and the overworld outside it.
+///
+/// The active world is the `CollisionWorld` resource, which the physics stage
+/// and queries run on. The others are kept here, their AABBs still updated but
+/// not colliding, so switching worlds is a swap rather than a rebuild.
+pub struct CollisionWorlds {
+    active: String,
+    requested: Option<String>,
+    inactive: BTreeMap<String, CollisionWorld>,
+}
+
+impl Default for CollisionWorlds {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_COLLISION_WORLD.to_string(),
+            requested: None,
+            inactive: BTreeMap::new(),
+        }
+    }
+}
+
+impl CollisionWorlds {
+    pub fn active(&self) -> &str {
+        &self.active
+    }
+
+    /// Makes `name` the active world at the start of the next physics stage.
+    pub fn activate(&mut self, name: impl Into<String>) {
+        self.requested = Some(name.into());
+    }
+
+    /// Shifts the inactive worlds, see `CollisionWorld::shift`.
+    pub fn shift_inactive(&mut self, offset: Vec2) {
+        for world in self.inactive.values_mut() {
+            world.shift(offset);
+        }
+    }
+
+    /// Names of all worlds that have had AABBs in them, in order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.inactive.keys().map(String::as_str).collect();
+        names.push(&self.active);
+        names.sort_unstable();
+        names
+    }
+}
+
+/// Copy of a `CollisionWorld`'s state, see `CollisionWorld::snapshot`.
+#[derive(Debug, Clone)]
+pub struct CollisionSnapshot {
+    aabbs: BTreeMap<Entity, (Entity, AabbComputed)>,
+    disabled: BTreeMap<Entity, (Entity, AabbComputed)>,
+    pairs: Vec<CollisionPair>,
+    sensor_overlaps: BTreeSet<(Entity, Entity)>,
+    contacts: BTreeSet<(Entity, Entity)>,
+}
+
+/// Tuning of the physics stage.
+#[derive(Debug, Clone)]
+pub struct PhysicsSettings {
+    /// Times per frame AABBs are updated, collisions are detected and resolved
+    /// and velocities integrated, each over an equal share of the frame time.
+    /// More substeps keep fast movers from tunneling and settle crowds faster.
+    pub substeps: u32,
+    /// Most a contact's depth is corrected in one substep, so deep overlaps
+    /// (e.g. something spawned inside a wall) are pushed out over a few
+    /// substeps instead of popping out at once.
+    pub max_penetration_correct: f32,
+    /// Share of a contact's depth corrected in one substep, Baumgarte style.
+    /// Below 1 overlaps are resolved over a few substeps, which softens the
+    /// push-out from walls and crowds instead of snapping.
+    pub penetration_correction: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            substeps: 1,
+            max_penetration_correct: f32::INFINITY,
+            penetration_correction: 1.,
+        }
+    }
+}
+
+/// Run criteria looping the physics substep stage `PhysicsSettings::substeps`
+/// times, at least once.
+pub fn run_substeps(settings: Res<PhysicsSettings>, mut substep: Local<u32>) -> ShouldRun {
+    if *substep < settings.substeps.max(1) {
+        *substep += 1;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        *substep = 0;
+        ShouldRun::No
+    }
+}
+
+/// Per-tick counters filled in by the physics stage systems.
+#[derive(Default, Debug)]
+pub struct PhysicsStats {
+    /// AABB pairs run through the narrowphase.
+    pub pairs_tested: usize,
+    /// Pairs that overlapped or touched.
+    pub pairs_collided: usize,
+    /// Wall time of the whole physics stage.
+    pub stage_time: Duration,
+    stage_start: Option<Instant>,
+}
+
+/// Selects which AABBs a `CollisionWorld` query considers.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryMask {
+    pub colliders: bool,
+    pub sensors: bool,
+    /// Only consider AABBs with this behavior.
+    pub behavior: Option<CollisionBehavior>,
+    /// Ignore AABBs owned by this entity, usually the one doing the query.
+    pub exclude: Option<Entity>,
+}
+
+impl QueryMask {
+    pub const COLLIDERS: Self = Self {
+        colliders: true,
+        sensors: false,
+        behavior: None,
+        exclude: None,
+    };
+    pub const SENSORS: Self = Self {
+        colliders: false,
+        sensors: true,
+        behavior: None,
+        exclude: None,
+    };
+    pub const ALL: Self = Self {
+        colliders: true,
+        sensors: true,
+        behavior: None,
+        exclude: None,
+    };
+
+    pub fn with_behavior(self, behavior: CollisionBehavior) -> Self {
+        Self {
+            behavior: Some(behavior),
+            ..self
+        }
+    }
+
+    pub fn excluding(self, entity: Entity) -> Self {
+        Self {
+            exclude: Some(entity),
+            ..self
+        }
+    }
+
+    pub fn matches(&self, entity: Entity, aabb: &AabbComputed) -> bool {
+        let kind_matches = match aabb.aabb_kind {
+            AabbKind::Collider => self.colliders,
+            AabbKind::Sensor => self.sensors,
+        };
+        kind_matches
+            && self.behavior.map_or(true, |b| b == aabb.collision_behavior)
+            && self.exclude != Some(entity)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    /// Entity owning the AABB that was hit.
+    pub entity: Entity,
+    pub aabb: Entity,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+/// Slab test of the ray `origin + t * dir` against the box `min..max`.
+/// Returns the entry distance and the normal of the face hit. A ray starting
+/// inside the box hits at distance 0 with a normal facing back along the ray.
+fn ray_vs_box(origin: Vec2, dir: Vec2, min: Vec2, max: Vec2) -> Option<(f32, Vec2)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = -dir;
+    for axis in 0..2 {
+        if dir[axis] == 0. {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin[axis]) / dir[axis];
+        let t2 = (max[axis] - origin[axis]) / dir[axis];
+        let (t_near, t_far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+        if t_near > t_enter {
+            t_enter = t_near;
+            normal = Vec2::ZERO;
+            normal[axis] = -dir[axis].signum();
+        }
+        t_exit = t_exit.min(t_far);
+    }
+
+    if t_enter > t_exit || t_exit < 0. {
+        None
+    } else if t_enter < 0. {
+        Some((0., -dir))
+    } else {
+        Some((t_enter, normal))
+    }
+}
+
+impl CollisionWorld {
+    /// Takes the AABB entity `aabb` out of collision detection and queries,
+    /// or puts it back, without despawning it.
+    ///
+    /// Has no effect on AABBs that haven't been through the physics stage yet.
+    pub fn set_enabled(&mut self, aabb: Entity, enabled: bool) {
+        if enabled {
+            if let Some(entry) = self.disabled.remove(&aabb) {
+                self.aabbs.insert(aabb, entry);
+            }
+        } else if let Some(entry) = self.aabbs.remove(&aabb) {
+            self.disabled.insert(aabb, entry);
+        }
+    }
+
+    /// Moves every AABB by `-offset`, along with the world origin, see
+    /// `origin::shift_origin`.
+    pub fn shift(&mut self, offset: Vec2) {
+        for (_, aabb) in self.aabbs.values_mut().chain(self.disabled.values_mut()) {
+            aabb.min -= offset;
+            aabb.max -= offset;
+        }
+    }
+
+    /// Drops every AABB owned by `owner` along with the pairs and sensor
+    /// overlaps involving it, for despawning it without waiting on the next
+    /// physics tick to catch up.
+    pub fn remove_owner(&mut self, owner: Entity) {
+        let owned: BTreeSet<Entity> = self
+            .aabbs
+            .iter()
+            .chain(self.disabled.iter())
+            .filter(|(_, (entity, _))| *entity == owner)
+            .map(|(aabb, _)| *aabb)
+            .collect();
+        self.contacts
+            .retain(|(aabb1, aabb2)| !owned.contains(aabb1) && !owned.contains(aabb2));
+        self.aabbs.retain(|_, (entity, _)| *entity != owner);
+        self.disabled.retain(|_, (entity, _)| *entity != owner);
+        self.pairs
+            .retain(|pair| pair.ent1 != owner && pair.ent2 != owner);
+        self.sensor_overlaps
+            .retain(|(sensor, other)| *sensor != owner && *other != owner);
+    }
+
+    /// Copies the state the physics stage carries between ticks, for
+    /// `restore` to roll back to. Transforms and velocities aren't part of the
+    /// world, see `rollback::PhysicsSnapshot`.
+    pub fn snapshot(&self) -> CollisionSnapshot {
+        CollisionSnapshot {
+            aabbs: self.aabbs.clone(),
+            disabled: self.disabled.clone(),
+            pairs: self.pairs.clone(),
+            sensor_overlaps: self.sensor_overlaps.clone(),
+            contacts: self.contacts.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: CollisionSnapshot) {
+        self.aabbs = snapshot.aabbs;
+        self.disabled = snapshot.disabled;
+        self.pairs = snapshot.pairs;
+        self.sensor_overlaps = snapshot.sensor_overlaps;
+        self.contacts = snapshot.contacts;
+    }
+
+    /// Entities with an AABB matching `mask` that contains `point`.
+    pub fn query_point(&self, point: Vec2, mask: QueryMask) -> Vec<Entity> {
+        self.query_region(point, point, mask)
+    }
+
+    /// Entities with an AABB matching `mask` that overlaps the region
+    /// `min..max`, each listed once and in a stable order.
+    pub fn query_region(&self, min: Vec2, max: Vec2, mask: QueryMask) -> Vec<Entity> {
+        let entities: BTreeSet<Entity> = self
+            .aabbs
+            .values()
+            .filter(|(entity, aabb)| {
+                mask.matches(*entity, aabb)
+                    && aabb.min.x <= max.x
+                    && aabb.max.x >= min.x
+                    && aabb.min.y <= max.y
+                    && aabb.max.y >= min.y
+            })
+            .map(|(entity, _)| *entity)
+            .collect();
+        entities.into_iter().collect()
+    }
+
+    /// Casts a ray and returns the closest AABB matching `mask` within
+    /// `max_dist`. `dir` doesn't need to be normalized.
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        mask: QueryMask,
+    ) -> Option<RayHit> {
+        self.aabb_cast(origin, Vec2::ZERO, dir, max_dist, mask)
+    }
+
+    /// Sweeps a box with the given half extents centered at `origin` along
+    /// `dir` and returns the first AABB matching `mask` it touches.
+    pub fn aabb_cast(
+        &self,
+        origin: Vec2,
+        half_extents: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        mask: QueryMask,
+    ) -> Option<RayHit> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec2::ZERO {
+            return None;
+        }
+
+        let mut closest: Option<RayHit> = None;
+        for (aabb_ent, (entity, aabb)) in self.aabbs.iter() {
+            if !mask.matches(*entity, aabb) {
+                continue;
+            }
+            // Casting a box is casting a ray against the box grown by its extents
+            let hit = ray_vs_box(
+                origin,
+                dir,
+                aabb.min - half_extents,
+                aabb.max + half_extents,
+            );
+            if let Some((distance, normal)) = hit {
+                let closer = closest.map_or(true, |hit| distance < hit.distance);
+                if distance <= max_dist && closer {
+                    closest = Some(RayHit {
+                        entity: *entity,
+                        aabb: *aabb_ent,
+                        point: origin + dir * distance,
+                        normal,
+                        distance,
+                    });
+                }
+            }
+        }
+        closest
+    }
+}
+
+/// Swaps in the world requested with `CollisionWorlds::activate`.
+///
+/// Sensor overlaps stay with their world, so entering it again doesn't repeat
+/// `SensorEvent`s. Rollback snapshots are of the previous world and dropped.
+pub fn swap_collision_worlds(
+    mut worlds: ResMut<CollisionWorlds>,
+    mut collision_world: ResMut<CollisionWorld>,
+    mut rollback: ResMut<RollbackBuffer>,
+) {
+    let name = match worlds.requested.take() {
+        Some(name) if name != worlds.active => name,
+        _ => return,
+    };
+    let next = worlds.inactive.remove(&name).unwrap_or_default();
+    let previous = std::mem::replace(&mut *collision_world, next);
+    let active = std::mem::replace(&mut worlds.active, name.clone());
+    info!("Switched collision world from {} to {}", active, name);
+    worlds.inactive.insert(active, previous);
+    rollback.clear();
+}
+
+pub fn updated_computed_aabbs(
+    mut collision_world: ResMut<CollisionWorld>,
+    mut worlds: ResMut<CollisionWorlds>,
+    world_q: Query<&InCollisionWorld>,
+    aabb_query: Query<
+        (
+            Entity,
+            &Parent,
+            &Aabb,
+            &AabbKind,
+            &CollisionBehavior,
+            &GlobalTransform,
+        ),
+        Or<(
+            Changed<GlobalTransform>,
+            Changed<CollisionBehavior>,
+            Changed<Aabb>,
+        )>,
+    >,
+    enabled_q: Query<(Entity, &ColliderEnabled), Changed<ColliderEnabled>>,
+    removed_aabbs: RemovedComponents<Aabb>,
+    parent_q: Query<&Parent>,
+    root_q: Query<(), With<RigidBodyRoot>>,
+) {
+    for entity in removed_aabbs.iter() {
+        for world in std::iter::once(&mut *collision_world).chain(worlds.inactive.values_mut()) {
+            world.aabbs.remove(&entity);
+            world.disabled.remove(&entity);
+        }
+    }
+    for (entity, parent, aabb, aabb_kind, collision_behavior, g_trans) in aabb_query.iter() {
+        let center = g_trans.translation.xy() + aabb.offset;
+        let aabb_computed = AabbComputed {
+            min: center - aabb.extents(),
+            max: center + aabb.extents(),
+            aabb_kind: *aabb_kind,
+            collision_behavior: *collision_behavior,
+        };
+        let root = resolve_root(**parent, &parent_q, &root_q);
+        let entry = (root, aabb_computed);
+        let name = world_q
+            .get(root)
+            .map_or(DEFAULT_COLLISION_WORLD, |world| world.0.as_str());
+        let collision_world = if name == worlds.active {
+            &mut *collision_world
+        } else {
+            worlds.inactive.entry(name.to_string()).or_default()
+        };
+        match collision_world.disabled.get_mut(&entity) {
+            Some(disabled) => *disabled = entry,
+            None => {
+                collision_world.aabbs.insert(entity, entry);
+            }
+        }
+    }
+    // After the update above, so AABBs spawned disabled are evicted right away
+    for (entity, enabled) in enabled_q.iter() {
+        for world in std::iter::once(&mut *collision_world).chain(worlds.inactive.values_mut()) {
+            world.set_enabled(entity, enabled.0);
+        }
+    }
+}
+
+pub fn begin_physics_stats(mut stats: ResMut<PhysicsStats>) {
+    stats.stage_start = Some(Instant::now());
+}
+
+pub fn end_physics_stats(mut stats: ResMut<PhysicsStats>) {
+    if let Some(start) = stats.stage_start.take() {
+        stats.stage_time = start.elapsed();
+    }
+}
+
+pub fn detect_collisions(
+    mut collision_world: ResMut<CollisionWorld>,
+    mut stats: ResMut<PhysicsStats>,
+) {
+    let mut pairs = Vec::new();
+    let mut pairs_tested = 0;
+    // Unordered pairs: each overlap is reported once, and the response decides
+    // which of the two entities gets moved.
+    for (i, (aabb_ent1, (ent1, aabb1))) in collision_world.aabbs.iter().enumerate() {
+        for (aabb_ent2, (ent2, aabb2)) in collision_world.aabbs.iter().skip(i + 1) {
+            if ent1 == ent2 {
+                continue;
+            }
+            pairs_tested += 1;
+            if let Some(mut contact) = aabb1.contact(aabb2) {
+                if aabb1.center().distance(aabb2.center()) < COINCIDENT_EPSILON {
+                    contact = aabb1.contact_along(aabb2, knock_away_direction(*ent1, *ent2));
+                }
+                pairs.push(CollisionPair {
+                    ent1: *ent1,
+                    ent2: *ent2,
+                    aabb1: *aabb_ent1,
+                    aabb2: *aabb_ent2,
+                    kind: aabb1.collision_kind(aabb2),
+                    contact,
+                });
+            }
+        }
+    }
+    stats.pairs_tested = pairs_tested;
+    stats.pairs_collided = pairs.len();
+    collision_world.pairs = pairs;
+}
+
+/// Asks the `ContactFilters` about every pair the narrowphase found. Takes the
+/// whole world, so filters can look at any component of the owners.
+pub fn filter_contacts(world: &World) {
+    let filters = match world.get_resource::<ContactFilters>() {
+        Some(filters) if !filters.filters.is_empty() => filters,
+        _ => return,
+    };
+    let collision_world = world.get_resource::<CollisionWorld>().unwrap();
+    let vetoed = collision_world
+        .pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, pair)| {
+            !filters
+                .filters
+                .iter()
+                .all(|filter| filter.allow(world, pair))
+        })
+        .map(|(i, _)| i)
+        .collect();
+    *filters.vetoed.lock().unwrap() = vetoed;
+}
+
+pub fn drop_vetoed_contacts(
+    mut collision_world: ResMut<CollisionWorld>,
+    mut stats: ResMut<PhysicsStats>,
+    filters: Option<Res<ContactFilters>>,
+) {
+    let vetoed = match filters {
+        Some(filters) => std::mem::take(&mut *filters.vetoed.lock().unwrap()),
+        None => return,
+    };
+    if vetoed.is_empty() {
+        return;
+    }
+    let mut i = 0;
+    collision_world.pairs.retain(|_| {
+        i += 1;
+        vetoed.binary_search(&(i - 1)).is_err()
+    });
+    stats.pairs_collided = collision_world.pairs.len();
+}
+
+pub fn emit_sensor_events(
+    mut collision_world: ResMut<CollisionWorld>,
+    mut sensor_events: EventWriter<SensorEvent>,
+) {
+    let mut overlaps = BTreeSet::new();
+    for pair in collision_world.pairs.iter() {
+        let (_, aabb1) = collision_world.aabbs[&pair.aabb1];
+        let (_, aabb2) = collision_world.aabbs[&pair.aabb2];
+        match (aabb1.aabb_kind, aabb2.aabb_kind) {
+            (AabbKind::Sensor, AabbKind::Collider) => {
+                overlaps.insert((pair.ent1, pair.ent2));
+            }
+            (AabbKind::Collider, AabbKind::Sensor) => {
+                overlaps.insert((pair.ent2, pair.ent1));
+            }
+            _ => {}
+        }
+    }
+
+    for &(sensor, other) in overlaps.difference(&collision_world.sensor_overlaps) {
+        sensor_events.send(SensorEvent::Entered { sensor, other });
+    }
+    for &(sensor, other) in collision_world.sensor_overlaps.difference(&overlaps) {
+        sensor_events.send(SensorEvent::Exited { sensor, other });
+    }
+    collision_world.sensor_overlaps = overlaps;
+}
+
+pub fn update_overlapping(
+    mut commands: Commands,
+    collision_world: Res<CollisionWorld>,
+    mut overlapping_q: Query<(Entity, &mut Overlapping)>,
+) {
+    let mut overlaps: BTreeMap<Entity, Vec<Entity>> = collision_world
+        .aabbs
+        .values()
+        .filter(|(_, aabb)| aabb.aabb_kind == AabbKind::Sensor)
+        .map(|(owner, _)| (*owner, Vec::new()))
+        .collect();
+    for &(sensor, other) in collision_world.sensor_overlaps.iter() {
+        overlaps.entry(sensor).or_default().push(other);
+    }
+
+    // Sensors that got disabled or despawned leave their owner's list empty
+    for (owner, mut overlapping) in overlapping_q.iter_mut() {
+        let others = overlaps.remove(&owner).unwrap_or_default();
+        // Only touch lists that changed, for `Changed<Overlapping>`
+        if overlapping.0 != others {
+            overlapping.0 = others;
+        }
+    }
+    for (owner, others) in overlaps {
+        commands.entity(owner).insert(Overlapping(others));
+    }
+}
+
+/// Colors debug shapes by what their AABB touched in the last substep: its
+/// `AabbDebugColor` while idle, otherwise as set by `DebugRenderStyle`.
+pub fn update_debug_shapes(
+    collision_world: Res<CollisionWorld>,
+    style: Res<DebugRenderStyle>,
+    debug_render: Res<DebugRender>,
+    mut shape_q: Query<(Entity, &AabbKind, &AabbDebugColor, &mut DrawMode)>,
+) {
+    if !debug_render.0 {
+        return;
+    }
+    let mut overlapping = BTreeSet::new();
+    let mut active_sensors = BTreeSet::new();
+    for pair in collision_world.pairs.iter() {
+        match pair.kind {
+            CollisionKind::ColliderCollider => {
+                overlapping.insert(pair.aabb1);
+                overlapping.insert(pair.aabb2);
+            }
+            CollisionKind::SensorCollider => {
+                let is_sensor = |aabb| {
+                    collision_world
+                        .aabbs
+                        .get(aabb)
+                        .map_or(false, |(_, computed)| {
+                            computed.aabb_kind == AabbKind::Sensor
+                        })
+                };
+                if is_sensor(&pair.aabb1) {
+                    active_sensors.insert(pair.aabb1);
+                } else if is_sensor(&pair.aabb2) {
+                    active_sensors.insert(pair.aabb2);
+                }
+            }
+            CollisionKind::SensorSensor => {}
+        }
+    }
+
+    for (entity, aabb_kind, debug_color, mut draw_mode) in shape_q.iter_mut() {
+        let (color, line_width) = if overlapping.contains(&entity) {
+            (style.overlapping, style.active_line_width)
+        } else if active_sensors.contains(&entity) {
+            (style.sensor_active, style.active_line_width)
+        } else {
+            let idle = match aabb_kind {
+                AabbKind::Collider => style.collider,
+                AabbKind::Sensor => style.sensor,
+            };
+            (idle.unwrap_or(debug_color.0), style.line_width)
+        };
+        // Changing the draw mode tessellates the shape again, so leave
+        // unchanged ones alone
+        if let DrawMode::Outlined { outline_mode, .. } = &*draw_mode {
+            if outline_mode.color == color && outline_mode.options.line_width == line_width {
+                continue;
+            }
+        }
+        *draw_mode = DrawMode::Outlined {
+            fill_mode: FillMode::color(Color::NONE),
+            outline_mode: StrokeMode::new(color, line_width),
+        };
+    }
+}
+
+fn displace(
+    ent: Entity,
+    displacement: Vec2,
+    transform_q: &mut Query<&mut Transform>,
+    gtransform_q: &mut Query<&mut GlobalTransform>,
+) {
+    transform_q.get_mut(ent).unwrap().translation += displacement.extend(0.0);
+    gtransform_q.get_mut(ent).unwrap().translation += displacement.extend(0.0);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_collision(
+    mut collision_world: ResMut<CollisionWorld>,
+    matrix: Res<CollisionMatrix>,
+    settings: Res<PhysicsSettings>,
+    mut transform_q: Query<&mut Transform>,
+    mut gtransform_q: Query<&mut GlobalTransform>,
+    mut velocity_q: Query<&mut Velocity>,
+    mut impacts: EventWriter<ImpactEvent>,
+    enabled_q: Query<&ColliderEnabled>,
+    iframes_q: Query<(), With<IFrames>>,
+    character_q: Query<(), With<CharacterController>>,
+    yielding_q: Query<(), With<Yielding>>,
+    mass_q: Query<&Mass>,
+) {
+    let _span = info_span!("physics").entered();
+    let is_disabled = |aabb| enabled_q.get(aabb).map_or(false, |enabled| !enabled.0);
+    let mut contacts = BTreeSet::new();
+    for pair in collision_world.pairs.iter() {
+        // Pairs are found before the rest of the stage runs, skip any AABB
+        // that got disabled since
+        if is_disabled(pair.aabb1) || is_disabled(pair.aabb2) {
+            continue;
+        }
+        let (ent1, ent2) = (pair.ent1, pair.ent2);
+        let (_, aabb1) = collision_world.aabbs[&pair.aabb1];
+        let (_, aabb2) = collision_world.aabbs[&pair.aabb2];
+        match pair.kind {
+            CollisionKind::ColliderCollider => {
+                let involves_npc = aabb1.collision_behavior == CollisionBehavior::Npc
+                    || aabb2.collision_behavior == CollisionBehavior::Npc;
+                if involves_npc && (iframes_q.get(ent1).is_ok() || iframes_q.get(ent2).is_ok()) {
+                    continue;
+                }
+                contacts.insert((pair.aabb1, pair.aabb2));
+                let is_new = !collision_world.contacts.contains(&(pair.aabb1, pair.aabb2));
+
+                // Yielding characters are pushed out of other characters'
+                // way, whatever the matrix says
+                let both_characters =
+                    character_q.get(ent1).is_ok() && character_q.get(ent2).is_ok();
+                let response = match (yielding_q.get(ent1).is_ok(), yielding_q.get(ent2).is_ok()) {
+                    (true, false) if both_characters => CollisionResponse::PushFirst,
+                    (false, true) if both_characters => CollisionResponse::PushSecond,
+                    _ => matrix.response(aabb1.collision_behavior, aabb2.collision_behavior),
+                };
+                // The entities pushed out of the pair, their AABBs and the
+                // contact as seen from them
+                let responses = match response {
+                    CollisionResponse::Ignore => [None, None],
+                    CollisionResponse::PushFirst => [Some((ent1, aabb1, pair.contact)), None],
+                    CollisionResponse::PushSecond => {
+                        [Some((ent2, aabb2, pair.contact.flipped())), None]
+                    }
+                    CollisionResponse::Split => {
+                        let inverse_mass = |ent| mass_q.get(ent).map_or(1., Mass::inverse);
+                        let (inverse1, inverse2) = (inverse_mass(ent1), inverse_mass(ent2));
+                        let total = inverse1 + inverse2;
+                        // Two immovable entities stay stuck in each other
+                        if total > 0. {
+                            let share = |inverse: f32| Contact {
+                                normal: pair.contact.normal,
+                                depth: pair.contact.depth * inverse / total,
+                            };
+                            [
+                                Some((ent1, aabb1, share(inverse1))),
+                                Some((ent2, aabb2, share(inverse2).flipped())),
+                            ]
+                        } else {
+                            [None, None]
+                        }
+                    }
+                };
+
+                for (ent, aabb, contact) in responses.into_iter().flatten() {
+                    let displacement = Contact {
+                        normal: contact.normal,
+                        depth: (contact.depth * settings.penetration_correction)
+                            .min(settings.max_penetration_correct),
+                    }
+                    .displacement();
+                    trace!(?displacement, ?ent1, ?ent2, "Collision response");
+                    displace(ent, displacement, &mut transform_q, &mut gtransform_q);
+                    if let Ok(mut velocity) = velocity_q.get_mut(ent) {
+                        let speed = -velocity.0.dot(contact.normal);
+                        if is_new && speed > IMPACT_MIN_SPEED {
+                            let half_extents = (aabb.max - aabb.min) / 2.;
+                            impacts.send(ImpactEvent {
+                                entity: ent,
+                                position: aabb.center() - contact.normal * half_extents,
+                                normal: contact.normal,
+                                speed,
+                            });
+                        }
+                        velocity.slide(contact.normal);
+                    }
+                }
+            }
+            CollisionKind::SensorCollider => {}
+            CollisionKind::SensorSensor => {}
+        }
+    }
+    collision_world.contacts = contacts;
+}
+
+pub fn tick_iframes(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut iframes_q: Query<(Entity, &mut IFrames)>,
+) {
+    for (entity, mut iframes) in iframes_q.iter_mut() {
+        iframes.0.tick(time_scale.delta(&time));
+        if iframes.0.finished() {
+            commands.entity(entity).remove::<IFrames>();
+        }
+    }
+}
+
+/// The terrain and animation state of a mover, which scale its velocity.
+type SpeedScaling<'a> = (Option<&'a OnTerrain>, Option<&'a AnimStateMachine>);
+
+/// What an entity's velocity is scaled by when integrating it: the terrain it
+/// is on and the `MovementLimits` of its animation. Applied here rather than
+/// to the `Velocity` itself, which may be set once and kept.
+fn speed_scale((on_terrain, machine): SpeedScaling) -> f32 {
+    on_terrain.map_or(1., |on_terrain| on_terrain.speed_multiplier)
+        * machine.map_or(1., |machine| machine.movement_limits().speed)
+}
+
+/// Integrates velocities once collisions have been resolved, so motion into a
+/// wall has already been projected onto the wall and the entity slides along it.
+pub fn apply_velocity(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    settings: Res<PhysicsSettings>,
+    mut query: Query<(&mut Transform, &Velocity, SpeedScaling), Without<CharacterController>>,
+) {
+    let delta = time_scale.delta_seconds(&time) / settings.substeps.max(1) as f32;
+    for (mut transform, velocity, scaling) in query.iter_mut() {
+        transform.translation += (velocity.0 * speed_scale(scaling) * delta).extend(0.0);
+    }
+}
+
+/// Integrates the velocities of characters, see `CharacterController`. They
+/// are stopped by the static colliders the matrix doesn't let them through.
+pub fn move_characters(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    settings: Res<PhysicsSettings>,
+    collision_world: Res<CollisionWorld>,
+    matrix: Res<CollisionMatrix>,
+    mut character_q: Query<(
+        Entity,
+        &CharacterController,
+        &mut Transform,
+        &GlobalTransform,
+        &Velocity,
+        &Children,
+        SpeedScaling,
+    )>,
+    aabb_q: Query<(&Aabb, &AabbKind, &CollisionBehavior)>,
+) {
+    let delta = time_scale.delta_seconds(&time) / settings.substeps.max(1) as f32;
+    for (entity, controller, mut transform, g_trans, velocity, children, scaling) in
+        character_q.iter_mut()
+    {
+        let motion = velocity.0 * speed_scale(scaling) * delta;
+        // Disabled colliders aren't in the world and don't stop the character
+        let collider = children
+            .iter()
+            .filter(|child| collision_world.aabbs.contains_key(*child))
+            .filter_map(|child| aabb_q.get(*child).ok())
+            .find(|(_, kind, _)| **kind == AabbKind::Collider);
+        let (aabb, _, behavior) = match collider {
+            Some(collider) => collider,
+            None => {
+                transform.translation += motion.extend(0.);
+                continue;
+            }
+        };
+        if matrix.response(*behavior, CollisionBehavior::Static) == CollisionResponse::Ignore {
+            transform.translation += motion.extend(0.);
+            continue;
+        }
+        // From the transforms rather than the world, `handle_collision` may
+        // have moved the character since its AABB was computed
+        let center = g_trans.translation.xy() + aabb.offset;
+        let computed = AabbComputed {
+            min: center - aabb.extents(),
+            max: center + aabb.extents(),
+            aabb_kind: AabbKind::Collider,
+            collision_behavior: *behavior,
+        };
+        let moved = controller.move_and_slide(
+            &collision_world,
+            &computed,
+            motion,
+            QueryMask::COLLIDERS
+                .with_behavior(CollisionBehavior::Static)
+                .excluding(entity),
+        );
+        transform.translation += moved.extend(0.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aabb(center: Vec2, size: Vec2, collision_behavior: CollisionBehavior) -> AabbComputed {
+        AabbComputed {
+            min: center - size / 2.,
+            max: center + size / 2.,
+            aabb_kind: AabbKind::Collider,
+            collision_behavior,
+        }
+    }
+
+    /// A player overlapping a static wall to its right by `depth`.
+    fn player_and_wall(depth: f32) -> (AabbComputed, AabbComputed) {
+        let size = Vec2::splat(16.);
+        let player = aabb(Vec2::ZERO, size, CollisionBehavior::Player);
+        let wall = aabb(Vec2::new(16. - depth, 0.), size, CollisionBehavior::Static);
+        (player, wall)
+    }
+
+    /// Runs `detect_collisions` over the two AABBs, spawning the AABB
+    /// entities in the given order, and returns the pairs with the player's
+    /// owner.
+    fn detect(first: AabbComputed, second: AabbComputed) -> (Vec<CollisionPair>, Entity) {
+        let mut world = World::new();
+        world.init_resource::<PhysicsStats>();
+        let mut collision_world = CollisionWorld::default();
+        let mut player = None;
+        for computed in [first, second] {
+            let owner = world.spawn().id();
+            let aabb_entity = world.spawn().id();
+            if computed.collision_behavior == CollisionBehavior::Player {
+                player = Some(owner);
+            }
+            collision_world.aabbs.insert(aabb_entity, (owner, computed));
+        }
+        world.insert_resource(collision_world);
+        SystemStage::single(detect_collisions).run(&mut world);
+        let pairs = world
+            .get_resource::<CollisionWorld>()
+            .unwrap()
+            .pairs
+            .clone();
+        (pairs, player.unwrap())
+    }
+
+    #[test]
+    fn contact_displacement_is_penetration_depth() {
+        let depth = 3.;
+        let (player, wall) = player_and_wall(depth);
+        let contact = player.contact(&wall).unwrap();
+        assert_eq!(contact.normal, Vec2::new(-1., 0.));
+        assert_eq!(contact.depth, depth);
+        assert_eq!(contact.displacement(), Vec2::new(-depth, 0.));
+        assert_eq!(contact.flipped().displacement(), Vec2::new(depth, 0.));
+    }
+
+    #[test]
+    fn overlap_is_reported_once_with_full_depth() {
+        let depth = 3.;
+        let (player, wall) = player_and_wall(depth);
+        for (first, second) in [(player, wall), (wall, player)] {
+            let (pairs, player_owner) = detect(first, second);
+            assert_eq!(pairs.len(), 1);
+            let pair = pairs[0];
+            // The contact as seen from the player, who gets pushed out
+            let contact = if pair.ent1 == player_owner {
+                pair.contact
+            } else {
+                pair.contact.flipped()
+            };
+            assert_eq!(contact.displacement(), Vec2::new(-depth, 0.));
+        }
+    }
+
+    #[test]
+    fn non_positive_mass_is_immovable() {
+        assert_eq!(Mass(2.).inverse(), 0.5);
+        assert_eq!(Mass(f32::INFINITY).inverse(), 0.);
+        for mass in [0., -0., -1., f32::NAN] {
+            assert_eq!(Mass(mass).inverse(), 0.);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::{path::PathBuf, time::SystemTime};
+
+use bevy::{
+    prelude::*,
+    window::{WindowId, WindowMode},
+    winit::WinitWindows,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::mods;
+
+static GAME_CONFIG_FILE: &str = "config.ron";
+const CONFIG_RELOAD_SECS: f32 = 1.;
+
+/// Startup configuration of the game, loaded from `assets/config.ron`, or a
+/// mod's override of it, before the window is created. Fields missing from
+/// the file keep their defaults.
+///
+/// The file is reloaded whenever it changes and the window picks up the new
+/// values, except for the resolution outside of windowed mode.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GameConfig {
+    pub title: String,
+    /// PNG, relative to `assets/`. Without one the platform's default icon
+    /// is used.
+    pub icon: Option<String>,
+    /// Logical size of the window in windowed mode.
+    pub resolution: (f32, f32),
+    pub vsync: bool,
+    pub resizable: bool,
+    /// Seed of `GameRng`, for repeatable runs. Without one every run is
+    /// seeded differently. Only read at startup.
+    pub seed: Option<u64>,
+    /// File the config was loaded from, and when it was last modified.
+    #[serde(skip)]
+    loaded: Option<(PathBuf, SystemTime)>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            title: String::from("mini-exp-1"),
+            icon: None,
+            resolution: (1280., 720.),
+            vsync: true,
+            resizable: true,
+            seed: None,
+            loaded: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Loads the config file, falling back to defaults if it is missing or
+    /// malformed. Called before the app is built so the window is created
+    /// with it.
+    pub fn load() -> Self {
+        let mut config = Self::default();
+        // The logger isn't set up yet
+        if let Err(err) = config.reload() {
+            eprintln!("Ignoring {}", err);
+        }
+        config
+    }
+
+    /// Reads the config file again if it, or the mod it comes from, changed
+    /// since it was last loaded, returning whether it did. A malformed mod
+    /// override falls back to the file below it, see `mods::load_ron`. If
+    /// none loads the config is kept and the error returned, once until the
+    /// file changes again.
+    fn reload(&mut self) -> Result<bool, String> {
+        let path = mods::resolve(GAME_CONFIG_FILE);
+        let modified = match std::fs::metadata(&path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return Ok(false),
+        };
+        let loaded = Some((path, modified));
+        if loaded == self.loaded {
+            return Ok(false);
+        }
+        self.loaded = loaded;
+        let config = mods::load_ron::<GameConfig>(GAME_CONFIG_FILE)?;
+        *self = GameConfig {
+            loaded: self.loaded.take(),
+            ..config
+        };
+        Ok(true)
+    }
+
+    pub fn window_descriptor(&self, mode: WindowMode) -> WindowDescriptor {
+        WindowDescriptor {
+            title: self.title.clone(),
+            width: self.resolution.0,
+            height: self.resolution.1,
+            vsync: self.vsync,
+            resizable: self.resizable,
+            mode,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ConfigSystem {
+    Reload,
+}
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .add_system(reload_game_config.label(ConfigSystem::Reload))
+            .add_system(apply_game_config.after(ConfigSystem::Reload))
+            .add_system(apply_window_icon.after(ConfigSystem::Reload));
+    }
+}
+
+fn reload_game_config(
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+    mut config: ResMut<GameConfig>,
+) {
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(CONFIG_RELOAD_SECS, true));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    // Only mark the config changed if the file had something new in it
+    let mut reloaded = config.clone();
+    match reloaded.reload() {
+        Ok(true) => {
+            info!("Reloaded {}", GAME_CONFIG_FILE);
+            *config = reloaded;
+        }
+        Ok(false) => {}
+        Err(err) => {
+            warn!("Ignoring {}", err);
+            // Not reported again until the file changes
+            config.loaded = reloaded.loaded;
+        }
+    }
+}
+
+fn apply_game_config(config: Res<GameConfig>, mut windows: ResMut<Windows>) {
+    if !config.is_changed() {
+        return;
+    }
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+    if window.title() != config.title {
+        window.set_title(config.title.clone());
+    }
+    if window.vsync() != config.vsync {
+        window.set_vsync(config.vsync);
+    }
+    if window.resizable() != config.resizable {
+        window.set_resizable(config.resizable);
+    }
+    let (width, height) = config.resolution;
+    if window.mode() == WindowMode::Windowed
+        && (window.width() != width || window.height() != height)
+    {
+        window.set_resolution(width, height);
+    }
+}
+
+/// Bevy has no icon setting, so it's set on the winit window directly, once
+/// the window exists and whenever the icon changes.
+fn apply_window_icon(
+    config: Res<GameConfig>,
+    winit_windows: NonSend<WinitWindows>,
+    mut applied: Local<Option<Option<String>>>,
+) {
+    if applied.as_ref() == Some(&config.icon) {
+        return;
+    }
+    let window = match winit_windows.get_window(WindowId::primary()) {
+        Some(window) => window,
+        None => return,
+    };
+    *applied = Some(config.icon.clone());
+
+    let icon = config.icon.as_ref().and_then(|icon| {
+        let path = mods::resolve(icon);
+        let image = match image::open(&path) {
+            Ok(image) => image.into_rgba8(),
+            Err(err) => {
+                warn!("Failed to load window icon {}: {}", path.display(), err);
+                return None;
+            }
+        };
+        let (width, height) = image.dimensions();
+        match winit::window::Icon::from_rgba(image.into_raw(), width, height) {
+            Ok(icon) => Some(icon),
+            Err(err) => {
+                warn!("Invalid window icon {}: {}", path.display(), err);
+                None
+            }
+        }
+    });
+    window.set_window_icon(icon);
+}
@@ -0,0 +1,191 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_egui::egui;
+
+use crate::{
+    camera::PixelCameraTag,
+    physics_dump::{dump_physics, DEFAULT_DUMP_PATH},
+    reflect_query::{entity_by_id, find_with_component},
+    rng::GameRng,
+    settings::Settings,
+    world_diff::WorldSnapshot,
+};
+
+/// Lines of output kept, the oldest are dropped first.
+const MAX_OUTPUT_LINES: usize = 200;
+/// Matches `find` lists before summing up the rest.
+const MAX_FOUND_LINES: usize = 20;
+/// Lines of a `diff` shown, the full diff goes to the log.
+const MAX_DIFF_LINES: usize = 40;
+const HELP: &str = "Commands: find <component>, count <component>, goto <entity>, timed, \
+    dump_physics [file], seed [seed], snapshot, diff";
+
+/// Debug commands typed into the debug menu, see `Console::show`.
+#[derive(Default)]
+pub struct Console {
+    input: String,
+    output: Vec<String>,
+    /// Submitted commands, run by `run_console_commands` at the end of the
+    /// frame as they need the whole world.
+    pending: Vec<String>,
+    /// Recorded by `snapshot` for `diff`.
+    snapshot: Option<WorldSnapshot>,
+}
+
+impl Console {
+    /// Draws the output and the command line into `ui`.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .max_height(160.)
+            .show(ui, |ui| {
+                for line in self.output.iter() {
+                    ui.monospace(line);
+                }
+            });
+        let response = ui.text_edit_singleline(&mut self.input);
+        if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+            let command = std::mem::take(&mut self.input);
+            if !command.trim().is_empty() {
+                self.pending.push(command);
+            }
+            response.request_focus();
+        }
+    }
+
+    fn print(&mut self, line: impl Into<String>) {
+        if self.output.len() == MAX_OUTPUT_LINES {
+            self.output.remove(0);
+        }
+        self.output.push(line.into());
+    }
+}
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_system(run_console_commands.exclusive_system().at_end());
+    }
+}
+
+fn run_console_commands(world: &mut World) {
+    let pending = std::mem::take(&mut world.get_resource_mut::<Console>().unwrap().pending);
+    for command in pending {
+        let output = run_command(world, &command);
+        let mut console = world.get_resource_mut::<Console>().unwrap();
+        console.print(format!("> {}", command));
+        for line in output {
+            console.print(line);
+        }
+    }
+}
+
+fn run_command(world: &mut World, command: &str) -> Vec<String> {
+    let mut words = command.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("find"), Some(component)) => match find_with_component(world, component) {
+            Ok(found) => {
+                let mut lines: Vec<String> = found
+                    .iter()
+                    .take(MAX_FOUND_LINES)
+                    .map(|found| {
+                        format!(
+                            "{:?} {} {}",
+                            found.entity,
+                            found.name.as_deref().unwrap_or(""),
+                            found.position.map_or(String::from("-"), |position| {
+                                format!("({:.1}, {:.1})", position.x, position.y)
+                            }),
+                        )
+                    })
+                    .collect();
+                if found.len() > MAX_FOUND_LINES {
+                    lines.push(format!("... and {} more", found.len() - MAX_FOUND_LINES));
+                }
+                if found.is_empty() {
+                    lines.push(String::from("None found"));
+                }
+                lines
+            }
+            Err(err) => vec![err],
+        },
+        (Some("count"), Some(component)) => match find_with_component(world, component) {
+            Ok(found) => vec![format!("{} entities with {}", found.len(), component)],
+            Err(err) => vec![err],
+        },
+        (Some("goto"), Some(entity)) => {
+            // Entities print as e.g. `12v0`, only the index is needed
+            let id = entity.split('v').next().and_then(|id| id.parse().ok());
+            let position = id
+                .and_then(|id| entity_by_id(world, id))
+                .and_then(|entity| world.get::<GlobalTransform>(entity))
+                .map(|trans| trans.translation.xy());
+            let position = match position {
+                Some(position) => position,
+                None => return vec![format!("No entity {} with a position", entity)],
+            };
+            let mut camera_q = world.query_filtered::<&mut Transform, With<PixelCameraTag>>();
+            for mut camera_trans in camera_q.iter_mut(world) {
+                camera_trans.translation.x = position.x;
+                camera_trans.translation.y = position.y;
+            }
+            vec![format!(
+                "Camera moved to ({:.1}, {:.1})",
+                position.x, position.y
+            )]
+        }
+        (Some("timed"), None) => {
+            let mut settings = world.get_resource_mut::<Settings>().unwrap();
+            settings.timed_mode = !settings.timed_mode;
+            settings.save();
+            vec![format!(
+                "Timed mode {}",
+                if settings.timed_mode { "on" } else { "off" }
+            )]
+        }
+        (Some("dump_physics"), path) => {
+            let path = path.unwrap_or(DEFAULT_DUMP_PATH);
+            match dump_physics(world, path) {
+                Ok(count) => vec![format!("Dumped {} AABBs to {}", count, path)],
+                Err(err) => vec![format!("Failed to dump to {}: {}", path, err)],
+            }
+        }
+        (Some("seed"), None) => {
+            let rng = world.get_resource::<GameRng>().unwrap();
+            vec![format!("Random seed {}", rng.seed())]
+        }
+        (Some("seed"), Some(seed)) => match seed.parse() {
+            Ok(seed) => {
+                world.get_resource_mut::<GameRng>().unwrap().reseed(seed);
+                vec![format!("Reseeded with {}", seed)]
+            }
+            Err(_) => vec![format!("Not a seed: {}", seed)],
+        },
+        (Some("snapshot"), None) => {
+            let snapshot = WorldSnapshot::capture(world);
+            let mut console = world.get_resource_mut::<Console>().unwrap();
+            console.snapshot = Some(snapshot);
+            vec![String::from("Snapshot recorded, run diff to compare")]
+        }
+        (Some("diff"), None) => {
+            let before = match &world.get_resource::<Console>().unwrap().snapshot {
+                Some(snapshot) => snapshot.clone(),
+                None => return vec![String::from("No snapshot, run snapshot first")],
+            };
+            let mut lines = before.diff(&WorldSnapshot::capture(world), world);
+            for line in lines.iter() {
+                info!("{}", line);
+            }
+            if lines.len() > MAX_DIFF_LINES {
+                let more = lines.len() - MAX_DIFF_LINES;
+                lines.truncate(MAX_DIFF_LINES);
+                lines.push(format!("... and {} more, see the log", more));
+            }
+            if lines.is_empty() {
+                lines.push(String::from("No differences"));
+            }
+            lines
+        }
+        _ => vec![String::from(HELP)],
+    }
+}
@@ -0,0 +1,160 @@
+use std::{marker::PhantomData, time::Duration};
+
+use bevy::{
+    ecs::component::{Component, TableStorage},
+    prelude::*,
+};
+
+use crate::time_scale::TimeScale;
+
+/// Something that can only happen once every so often, e.g. a dash. Starts
+/// ready, `reset` starts the wait.
+///
+/// `T` tells apart the cooldowns of an entity, e.g. `Cooldown<Dash>`. Ticked
+/// in game time before `Update`, for every `T` registered with
+/// `TimerAppExt::add_cooldown`, as a component or as a resource.
+#[derive(Debug)]
+pub struct Cooldown<T> {
+    timer: Timer,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> Component for Cooldown<T> {
+    type Storage = TableStorage;
+}
+
+impl<T> Clone for Cooldown<T> {
+    fn clone(&self) -> Self {
+        Self {
+            timer: self.timer.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Cooldown<T> {
+    pub fn new(secs: f32) -> Self {
+        let mut timer = Timer::from_seconds(secs, false);
+        timer.tick(timer.duration());
+        Self {
+            timer,
+            marker: PhantomData,
+        }
+    }
+
+    /// Waiting `secs` from the start, rather than ready.
+    pub fn started(secs: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(secs, false),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.timer.finished()
+    }
+
+    /// Starts waiting again.
+    pub fn reset(&mut self) {
+        self.timer.reset();
+    }
+
+    /// Starts waiting again, for `secs` this time and from now on.
+    pub fn reset_to(&mut self, secs: f32) {
+        self.timer.set_duration(Duration::from_secs_f32(secs));
+        self.timer.reset();
+    }
+
+    /// How much of the wait is over, 1 once ready.
+    pub fn percent(&self) -> f32 {
+        self.timer.percent()
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+    }
+}
+
+/// Goes off every so often, e.g. for autosaves. See `Cooldown` for `T` and
+/// ticking.
+#[derive(Debug)]
+pub struct RepeatingTimer<T> {
+    timer: Timer,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> Component for RepeatingTimer<T> {
+    type Storage = TableStorage;
+}
+
+impl<T> RepeatingTimer<T> {
+    pub fn new(secs: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(secs, true),
+            marker: PhantomData,
+        }
+    }
+
+    /// Whether it went off in the last tick.
+    pub fn is_ready(&self) -> bool {
+        self.timer.just_finished()
+    }
+
+    /// Starts the current period over, e.g. after doing early what the timer
+    /// is for.
+    pub fn reset(&mut self) {
+        self.timer.reset();
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+    }
+}
+
+pub trait TimerAppExt {
+    /// Ticks every `Cooldown<T>`.
+    fn add_cooldown<T: 'static>(&mut self) -> &mut Self;
+
+    /// Ticks every `RepeatingTimer<T>`.
+    fn add_repeating_timer<T: 'static>(&mut self) -> &mut Self;
+}
+
+impl TimerAppExt for App {
+    fn add_cooldown<T: 'static>(&mut self) -> &mut Self {
+        self.add_system_to_stage(CoreStage::PreUpdate, tick_cooldowns::<T>)
+    }
+
+    fn add_repeating_timer<T: 'static>(&mut self) -> &mut Self {
+        self.add_system_to_stage(CoreStage::PreUpdate, tick_repeating_timers::<T>)
+    }
+}
+
+fn tick_cooldowns<T: 'static>(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    resource: Option<ResMut<Cooldown<T>>>,
+    mut cooldown_q: Query<&mut Cooldown<T>>,
+) {
+    let delta = time_scale.delta(&time);
+    if let Some(mut cooldown) = resource {
+        cooldown.tick(delta);
+    }
+    for mut cooldown in cooldown_q.iter_mut() {
+        cooldown.tick(delta);
+    }
+}
+
+fn tick_repeating_timers<T: 'static>(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    resource: Option<ResMut<RepeatingTimer<T>>>,
+    mut timer_q: Query<&mut RepeatingTimer<T>>,
+) {
+    let delta = time_scale.delta(&time);
+    if let Some(mut timer) = resource {
+        timer.tick(delta);
+    }
+    for mut timer in timer_q.iter_mut() {
+        timer.tick(delta);
+    }
+}
@@ -0,0 +1,319 @@
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin},
+    shapes,
+};
+
+use crate::{
+    camera::PixelCameraTag,
+    chunk::{chunk_coord, CHUNK_SIZE},
+    collision::{AabbBundle, AabbKind, ColliderEnabled, CollisionBehavior, SensorEvent},
+    frame_budget::TimedExt,
+    layer::RenderLayerGroup,
+    phase::{Phase, PhaseExt},
+    pool::EntityPool,
+    rng::GameRng,
+    time_scale::TimeScale,
+    AppState, PlayerTag,
+};
+
+/// Most critters around in one chunk at once.
+const CRITTERS_PER_CHUNK: usize = 2;
+/// Chunks up to this many chunks away from the player's get critters.
+const CRITTER_CHUNK_RADIUS: i32 = 1;
+/// Seconds between attempts to top up the chunks' critters.
+const SPAWN_SECS: f32 = 0.5;
+/// Critters further than this outside the view are released to the pool.
+/// New ones spawn outside the view but within it, so they don't pop in.
+const OFFSCREEN_MARGIN: f32 = 48.;
+/// Seconds between a wandering critter's turns.
+const WANDER_SECS: f32 = 1.2;
+/// Most a wander turn changes the heading by, in radians.
+const WANDER_TURN: f32 = 1.2;
+/// Reach of the sensor a critter spots the player with.
+const SCARE_REACH: f32 = 40.;
+/// Wing beats per second.
+const FLAP_SPEED: f32 = 8.;
+/// Narrowest the wings get while flapping, as a share of their span.
+const MIN_FLAP: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CritterKind {
+    Butterfly,
+    Bird,
+}
+
+impl CritterKind {
+    fn color(self) -> Color {
+        match self {
+            CritterKind::Butterfly => Color::rgb(0.95, 0.75, 0.3),
+            CritterKind::Bird => Color::rgb(0.3, 0.28, 0.35),
+        }
+    }
+
+    /// Wingspan and height, in world pixels.
+    fn size(self) -> Vec2 {
+        match self {
+            CritterKind::Butterfly => Vec2::new(4., 3.),
+            CritterKind::Bird => Vec2::new(7., 3.),
+        }
+    }
+
+    fn speed(self) -> f32 {
+        match self {
+            CritterKind::Butterfly => 12.,
+            CritterKind::Bird => 24.,
+        }
+    }
+
+    fn flee_speed(self) -> f32 {
+        match self {
+            CritterKind::Butterfly => 36.,
+            CritterKind::Bird => 110.,
+        }
+    }
+
+    /// Birds fly off for good once scared, butterflies settle down again as
+    /// soon as the player is out of reach.
+    fn flees_off(self) -> bool {
+        self == CritterKind::Bird
+    }
+}
+
+/// A decorative bird or butterfly wandering around the player, see
+/// `spawn_critters`. Critters are pooled, released ones are hidden and have
+/// their sensor disabled.
+#[derive(Component, Debug)]
+pub struct Critter {
+    kind: CritterKind,
+    /// Radians.
+    heading: f32,
+    turn: Timer,
+    /// Direction to flee in while the player is close.
+    fleeing: Option<Vec2>,
+    /// Wing beat phase, in radians.
+    flap: f32,
+    alive: bool,
+}
+
+struct CritterSpawner {
+    timer: Timer,
+}
+
+impl Default for CritterSpawner {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(SPAWN_SECS, true),
+        }
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CritterSystem {
+    Spawn,
+}
+
+pub struct CritterPlugin;
+
+impl Plugin for CritterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityPool<Critter>>().add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Ai)
+                .with_system(spawn_critters.timed().label(CritterSystem::Spawn))
+                .with_system(move_critters.after(CritterSystem::Spawn)),
+        );
+    }
+}
+
+/// World space rectangle the camera sees, grown by `OFFSCREEN_MARGIN`.
+fn view_bounds(trans: &GlobalTransform, projection: &OrthographicProjection) -> (Vec2, Vec2) {
+    let center = trans.translation.xy();
+    let min = Vec2::new(projection.left, projection.bottom) * projection.scale;
+    let max = Vec2::new(projection.right, projection.top) * projection.scale;
+    (
+        center + min - Vec2::splat(OFFSCREEN_MARGIN),
+        center + max + Vec2::splat(OFFSCREEN_MARGIN),
+    )
+}
+
+fn in_bounds(position: Vec2, (min, max): (Vec2, Vec2)) -> bool {
+    position.cmpge(min).all() && position.cmple(max).all()
+}
+
+/// Tops up the chunks around the player to `CRITTERS_PER_CHUNK`, one critter
+/// per chunk at a time, at a random spot just outside the view.
+fn spawn_critters(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut spawner: Local<CritterSpawner>,
+    mut rng: ResMut<GameRng>,
+    mut pool: ResMut<EntityPool<Critter>>,
+    player_q: Query<&GlobalTransform, With<PlayerTag>>,
+    camera_q: Query<(&GlobalTransform, &OrthographicProjection), With<PixelCameraTag>>,
+    critter_q: Query<(&Critter, &Transform)>,
+) {
+    if !spawner.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let (player_trans, (camera_trans, projection)) =
+        match (player_q.get_single(), camera_q.get_single()) {
+            (Ok(player), Ok(camera)) => (player, camera),
+            _ => return,
+        };
+    let bounds = view_bounds(camera_trans, projection);
+    let margin = Vec2::splat(OFFSCREEN_MARGIN);
+    let view = (bounds.0 + margin, bounds.1 - margin);
+
+    let mut population: HashMap<IVec2, usize> = HashMap::default();
+    for (critter, trans) in critter_q.iter() {
+        if critter.alive {
+            *population
+                .entry(chunk_coord(trans.translation.xy()))
+                .or_default() += 1;
+        }
+    }
+
+    let center = chunk_coord(player_trans.translation.xy());
+    let radius = CRITTER_CHUNK_RADIUS;
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            let coord = center + IVec2::new(x, y);
+            if population.get(&coord).copied().unwrap_or(0) >= CRITTERS_PER_CHUNK {
+                continue;
+            }
+            let rng = rng.stream("critter_spawn");
+            let position = (coord.as_vec2() + Vec2::new(rng.next(), rng.next())) * CHUNK_SIZE;
+            if in_bounds(position, view) || !in_bounds(position, bounds) {
+                continue;
+            }
+            let kind = if rng.next() < 0.5 {
+                CritterKind::Butterfly
+            } else {
+                CritterKind::Bird
+            };
+            let critter = Critter {
+                kind,
+                heading: rng.next() * std::f32::consts::TAU,
+                turn: Timer::from_seconds(WANDER_SECS, true),
+                fleeing: None,
+                flap: rng.next() * std::f32::consts::TAU,
+                alive: true,
+            };
+            pool.acquire(
+                &mut commands,
+                (
+                    critter,
+                    Transform {
+                        translation: RenderLayerGroup::Overhead.at(position),
+                        scale: kind.size().extend(1.),
+                        ..Default::default()
+                    },
+                    DrawMode::Fill(FillMode::color(kind.color())),
+                ),
+                spawn_critter,
+            );
+        }
+    }
+}
+
+/// The shape is a unit square scaled to the critter's size, so the pooled
+/// entity fits either kind.
+fn spawn_critter(commands: &mut Commands) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: Vec2::ONE,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Fill(FillMode::color(Color::NONE)),
+            Transform::default(),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(AabbBundle::new(
+                    Vec2::splat(SCARE_REACH * 2.),
+                    AabbKind::Sensor,
+                    CollisionBehavior::None,
+                    Color::PURPLE,
+                ))
+                .insert(ColliderEnabled::default());
+        })
+        .insert(RenderLayerGroup::Overhead)
+        .id()
+}
+
+/// Wanders, flaps and scares critters, releasing the ones that left the
+/// view.
+#[allow(clippy::too_many_arguments)]
+fn move_critters(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut rng: ResMut<GameRng>,
+    mut pool: ResMut<EntityPool<Critter>>,
+    mut sensor_events: EventReader<SensorEvent>,
+    player_q: Query<&GlobalTransform, With<PlayerTag>>,
+    camera_q: Query<(&GlobalTransform, &OrthographicProjection), With<PixelCameraTag>>,
+    mut critter_q: Query<(Entity, &mut Critter, &mut Transform, &Children)>,
+    mut sensor_q: Query<&mut ColliderEnabled>,
+) {
+    for event in sensor_events.iter() {
+        let (sensor, other, entered) = match *event {
+            SensorEvent::Entered { sensor, other } => (sensor, other, true),
+            SensorEvent::Exited { sensor, other } => (sensor, other, false),
+        };
+        let player = match player_q.get(other) {
+            Ok(player) => player.translation.xy(),
+            Err(_) => continue,
+        };
+        if let Ok((_, mut critter, trans, _)) = critter_q.get_mut(sensor) {
+            if entered {
+                let away = (trans.translation.xy() - player).normalize_or_zero();
+                critter.fleeing = Some(away);
+            } else if !critter.kind.flees_off() {
+                critter.fleeing = None;
+            }
+        }
+    }
+
+    let bounds = camera_q
+        .get_single()
+        .ok()
+        .map(|(trans, projection)| view_bounds(trans, projection));
+    let delta = time_scale.delta(&time);
+    let delta_secs = delta.as_secs_f32();
+    for (entity, mut critter, mut trans, children) in critter_q.iter_mut() {
+        for child in children.iter() {
+            if let Ok(mut enabled) = sensor_q.get_mut(*child) {
+                if enabled.0 != critter.alive {
+                    enabled.0 = critter.alive;
+                }
+            }
+        }
+        if !critter.alive {
+            continue;
+        }
+
+        let kind = critter.kind;
+        let velocity = match critter.fleeing {
+            Some(away) if away != Vec2::ZERO => away * kind.flee_speed(),
+            _ => {
+                if critter.turn.tick(delta).just_finished() {
+                    critter.heading += rng.stream("critter_wander").range(-1., 1.) * WANDER_TURN;
+                }
+                Vec2::new(critter.heading.cos(), critter.heading.sin()) * kind.speed()
+            }
+        };
+        trans.translation += (velocity * delta_secs).extend(0.);
+        critter.flap += FLAP_SPEED * std::f32::consts::TAU * delta_secs;
+        trans.scale.x = kind.size().x * critter.flap.sin().abs().max(MIN_FLAP);
+
+        if let Some(bounds) = bounds {
+            if !in_bounds(trans.translation.xy(), bounds) {
+                critter.alive = false;
+                pool.release(&mut commands, entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+use crate::{
+    anim::AnimStateMachine,
+    collision::{IFrames, Velocity},
+    cooldown::{Cooldown, TimerAppExt},
+    frame_budget::TimedExt,
+    mount::Riding,
+    phase::{Phase, PhaseExt},
+    settings::{Action, ActionBuffer, SettingsSystem},
+    time_scale::TimeScale,
+    AppState, PlayerSystem, PlayerTag,
+};
+
+const DASH_SPEED: f32 = 260.;
+const DASH_DURATION: f32 = 0.15;
+const DASH_COOLDOWN: f32 = 1.;
+const INDICATOR_SIZE: Vec2 = Vec2::new(60., 8.);
+
+/// A short burst of speed on the dash key, passing through NPCs meanwhile.
+#[derive(Component)]
+pub struct Dash {
+    active: Timer,
+    direction: Vec2,
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        let mut active = Timer::from_seconds(DASH_DURATION, false);
+        // Not dashing from the start
+        active.tick(active.duration());
+        Self {
+            active,
+            direction: Vec2::ZERO,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct DashBundle {
+    pub dash: Dash,
+    pub cooldown: Cooldown<Dash>,
+}
+
+impl Default for DashBundle {
+    fn default() -> Self {
+        Self {
+            dash: Dash::default(),
+            cooldown: Cooldown::new(DASH_COOLDOWN),
+        }
+    }
+}
+
+#[derive(Component)]
+struct DashIndicatorTag;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DashSystem {
+    Dash,
+}
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_cooldown::<Dash>()
+            .add_startup_system(setup_dash_indicator)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Input)
+                    .with_system(
+                        dash.timed()
+                            .label(DashSystem::Dash)
+                            .after(PlayerSystem::Input)
+                            .after(SettingsSystem::BufferActions),
+                    ),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_dash_indicator.after(DashSystem::Dash)),
+            );
+    }
+}
+
+fn setup_dash_indicator(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.),
+                    right: Val::Px(20.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(INDICATOR_SIZE.x), Val::Px(INDICATOR_SIZE.y)),
+                ..Default::default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                        ..Default::default()
+                    },
+                    color: Color::WHITE.into(),
+                    ..Default::default()
+                })
+                .insert(DashIndicatorTag);
+        });
+}
+
+fn dash(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut buffer: ResMut<ActionBuffer>,
+    mut player_q: Query<
+        (
+            Entity,
+            &mut Dash,
+            &mut Cooldown<Dash>,
+            &mut Velocity,
+            &AnimStateMachine,
+        ),
+        (With<PlayerTag>, Without<Riding>),
+    >,
+) {
+    // No dashing while riding
+    let (player, mut dash, mut cooldown, mut velocity, machine) = match player_q.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    dash.active.tick(time_scale.delta(&time));
+
+    if cooldown.is_ready() && buffer.consume(Action::Dash) {
+        // Dash where the player is heading, or where they face when standing
+        dash.direction = if velocity.0 != Vec2::ZERO {
+            velocity.0.normalize()
+        } else {
+            machine.facing().direction()
+        };
+        dash.active.reset();
+        cooldown.reset();
+        commands
+            .entity(player)
+            .insert(IFrames(Timer::from_seconds(DASH_DURATION, false)));
+    }
+
+    if !dash.active.finished() {
+        velocity.0 = dash.direction * DASH_SPEED;
+    }
+}
+
+fn update_dash_indicator(
+    player_q: Query<&Cooldown<Dash>, With<PlayerTag>>,
+    mut indicator_q: Query<(&mut Style, &mut UiColor), With<DashIndicatorTag>>,
+) {
+    let cooldown = player_q.single();
+    for (mut style, mut color) in indicator_q.iter_mut() {
+        style.size.width = Val::Percent(cooldown.percent() * 100.);
+        color.0 = if cooldown.is_ready() {
+            Color::WHITE
+        } else {
+            Color::GRAY
+        };
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    anim::AnimStateMachine,
+    camera::PixelCameraSettings,
+    collision::{AabbKind, CollisionWorld},
+    layer::RenderLayerGroup,
+    npc::NpcState,
+    phase::{Phase, PhaseExt},
+    DebugRender,
+};
+
+/// Font size in screen pixels, scaled down by the camera zoom like floating
+/// texts.
+const DEBUG_LABEL_FONT_SIZE: f32 = 12.;
+/// Height above the entity's origin, in sprite pixels.
+const DEBUG_LABEL_OFFSET: f32 = 28.;
+
+/// Shows the entity's id, collision behavior and AI state as small text above
+/// it while debug rendering is on, followed by the string if not empty, e.g.
+/// whatever a system being debugged wants to show about it.
+#[derive(Component, Debug, Clone, Default)]
+pub struct DebugLabel(pub String);
+
+/// The text showing the `DebugLabel` of `owner`. Kept apart from the owner
+/// rather than as a child so it isn't flipped or scaled with the sprite.
+#[derive(Component)]
+struct DebugLabelText {
+    owner: Entity,
+}
+
+struct DebugLabelFont(Handle<Font>);
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DebugLabelSystem {
+    SpawnTexts,
+}
+
+pub struct DebugLabelPlugin;
+
+impl Plugin for DebugLabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_font).add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(spawn_debug_label_texts.label(DebugLabelSystem::SpawnTexts))
+                .with_system(update_debug_label_texts.after(DebugLabelSystem::SpawnTexts)),
+        );
+    }
+}
+
+fn load_font(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DebugLabelFont(asset_server.load("Share-Regular.ttf")));
+}
+
+fn spawn_debug_label_texts(
+    mut commands: Commands,
+    font: Res<DebugLabelFont>,
+    label_q: Query<Entity, Added<DebugLabel>>,
+) {
+    for owner in label_q.iter() {
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: DEBUG_LABEL_FONT_SIZE,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Bottom,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                visibility: Visibility { is_visible: false },
+                ..Default::default()
+            })
+            .insert(DebugLabelText { owner });
+    }
+}
+
+/// Follows the owners while debug rendering is on, and despawns the texts of
+/// owners that are gone or no longer labeled.
+fn update_debug_label_texts(
+    mut commands: Commands,
+    debug_render: Res<DebugRender>,
+    collision_world: Res<CollisionWorld>,
+    camera_settings: Res<PixelCameraSettings>,
+    owner_q: Query<(
+        &DebugLabel,
+        &GlobalTransform,
+        Option<&NpcState>,
+        Option<&AnimStateMachine>,
+    )>,
+    mut text_q: Query<(
+        Entity,
+        &DebugLabelText,
+        &mut Text,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let mut behaviors: HashMap<Entity, Vec<String>> = HashMap::default();
+    if debug_render.0 {
+        for (owner, aabb) in collision_world.aabbs.values() {
+            if aabb.aabb_kind == AabbKind::Collider {
+                behaviors
+                    .entry(*owner)
+                    .or_default()
+                    .push(format!("{:?}", aabb.collision_behavior));
+            }
+        }
+    }
+
+    for (entity, label_text, mut text, mut transform, mut visibility) in text_q.iter_mut() {
+        let (label, owner_trans, npc_state, machine) = match owner_q.get(label_text.owner) {
+            Ok(owner) => owner,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        visibility.is_visible = debug_render.0;
+        if !debug_render.0 {
+            continue;
+        }
+
+        let mut lines = vec![format!("{:?}", label_text.owner)];
+        if let Some(behaviors) = behaviors.get(&label_text.owner) {
+            lines.push(behaviors.join(", "));
+        }
+        let states: Vec<String> = npc_state
+            .map(|state| format!("{:?}", state))
+            .into_iter()
+            .chain(machine.map(|machine| format!("{:?}", machine.state())))
+            .collect();
+        if !states.is_empty() {
+            lines.push(states.join(" / "));
+        }
+        if !label.0.is_empty() {
+            lines.push(label.0.clone());
+        }
+        let value = lines.join("\n");
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+
+        let anchor = owner_trans.translation;
+        transform.translation = Vec3::new(
+            anchor.x,
+            anchor.y + DEBUG_LABEL_OFFSET,
+            RenderLayerGroup::UiWorld.z() + 1.,
+        );
+        transform.scale = Vec3::splat(1. / camera_settings.effective_zoom());
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext, EguiPlugin};
+
+use crate::{
+    camera::{cursor_world_position, PixelCameraSettings, PixelCameraTag},
+    collision::{CollisionWorlds, PhysicsSettings},
+    console::Console,
+    frame_budget::SystemTimings,
+    level::{spawn_prefab, Prefab},
+    phase::{Phase, PhaseExt},
+    Controlled, DebugRender, PlayerTag,
+};
+
+const MAX_SUBSTEPS: u32 = 8;
+const MAX_PLAYER_SPEED: f32 = 300.;
+const MAX_ZOOM: f32 = 8.;
+/// Largest per-system frame budget, in milliseconds.
+const MAX_BUDGET_MS: f32 = 16.;
+/// Correction limit used while "Limit penetration correction" is ticked.
+const PENETRATION_CORRECT_LIMIT: f32 = 2.;
+
+/// Window for tweaking the game while it runs, toggled with F1.
+#[derive(Default)]
+struct DebugMenu {
+    open: bool,
+    /// Prefab spawned at the next click into the world.
+    spawning: Option<Prefab>,
+}
+
+pub struct DebugMenuPlugin;
+
+impl Plugin for DebugMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(EguiPlugin)
+            .init_resource::<DebugMenu>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(toggle_debug_menu)
+                    .with_system(spawn_at_cursor),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(debug_menu_window),
+            );
+    }
+}
+
+fn toggle_debug_menu(keys: Res<Input<KeyCode>>, mut menu: ResMut<DebugMenu>) {
+    if keys.just_pressed(KeyCode::F1) {
+        menu.open = !menu.open;
+        menu.spawning = None;
+    }
+}
+
+fn spawn_at_cursor(
+    mut commands: Commands,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    mut egui_context: ResMut<EguiContext>,
+    mut menu: ResMut<DebugMenu>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelCameraTag>>,
+) {
+    let prefab = match menu.spawning {
+        Some(prefab) => prefab,
+        None => return,
+    };
+    // Clicks on the window itself are for the window
+    if !mouse.just_pressed(MouseButton::Left) || egui_context.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let (camera, camera_trans) = camera_q.single();
+    if let Some(cursor) = windows
+        .get_primary()
+        .and_then(|window| cursor_world_position(window, camera, camera_trans))
+    {
+        spawn_prefab(&mut commands, prefab, cursor);
+        menu.spawning = None;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn debug_menu_window(
+    mut egui_context: ResMut<EguiContext>,
+    mut menu: ResMut<DebugMenu>,
+    mut camera_settings: ResMut<PixelCameraSettings>,
+    mut physics_settings: ResMut<PhysicsSettings>,
+    mut collision_worlds: ResMut<CollisionWorlds>,
+    mut debug_render: ResMut<DebugRender>,
+    mut console: ResMut<Console>,
+    mut timings: ResMut<SystemTimings>,
+    mut player_q: Query<&mut Controlled, With<PlayerTag>>,
+) {
+    if !menu.open {
+        return;
+    }
+    let menu = &mut *menu;
+    egui::Window::new("Debug").show(egui_context.ctx_mut(), |ui| {
+        ui.heading("Spawn at cursor");
+        ui.horizontal_wrapped(|ui| {
+            for prefab in Prefab::ALL {
+                let selected = menu.spawning == Some(prefab);
+                if ui
+                    .selectable_label(selected, format!("{:?}", prefab))
+                    .clicked()
+                {
+                    menu.spawning = if selected { None } else { Some(prefab) };
+                }
+            }
+        });
+
+        ui.separator();
+        ui.heading("Player");
+        for mut controlled in player_q.iter_mut() {
+            ui.add(egui::Slider::new(&mut controlled.speed, 0.0..=MAX_PLAYER_SPEED).text("Speed"));
+        }
+
+        ui.separator();
+        ui.heading("Camera");
+        // Only write back changes, `apply_camera_zoom` runs on change
+        let mut zoom = camera_settings.zoom;
+        let mut integer_scaling = camera_settings.integer_scaling;
+        let mut snap_to_pixels = camera_settings.snap_to_pixels;
+        ui.add(egui::Slider::new(&mut zoom, 1.0..=MAX_ZOOM).text("Scale"));
+        ui.checkbox(&mut integer_scaling, "Integer scaling");
+        ui.checkbox(&mut snap_to_pixels, "Snap to pixels");
+        if zoom != camera_settings.zoom
+            || integer_scaling != camera_settings.integer_scaling
+            || snap_to_pixels != camera_settings.snap_to_pixels
+        {
+            camera_settings.zoom = zoom;
+            camera_settings.integer_scaling = integer_scaling;
+            camera_settings.snap_to_pixels = snap_to_pixels;
+        }
+
+        ui.separator();
+        ui.heading("Physics");
+        ui.add(
+            egui::Slider::new(&mut physics_settings.substeps, 1..=MAX_SUBSTEPS).text("Substeps"),
+        );
+        let mut limit_correction = physics_settings.max_penetration_correct.is_finite();
+        if ui
+            .checkbox(&mut limit_correction, "Limit penetration correction")
+            .changed()
+        {
+            physics_settings.max_penetration_correct = if limit_correction {
+                PENETRATION_CORRECT_LIMIT
+            } else {
+                f32::INFINITY
+            };
+        }
+        if limit_correction {
+            ui.add(
+                egui::Slider::new(&mut physics_settings.max_penetration_correct, 0.1..=16.)
+                    .text("Max correction"),
+            );
+        }
+        ui.add(
+            egui::Slider::new(&mut physics_settings.penetration_correction, 0.05..=1.)
+                .text("Correction factor"),
+        );
+        let active = collision_worlds.active().to_string();
+        let names: Vec<String> = collision_worlds
+            .names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        egui::ComboBox::from_label("Collision world")
+            .selected_text(&active)
+            .show_ui(ui, |ui| {
+                for name in names {
+                    if ui.selectable_label(name == active, &name).clicked() {
+                        collision_worlds.activate(name);
+                    }
+                }
+            });
+        ui.checkbox(&mut debug_render.0, "Debug render");
+
+        ui.separator();
+        ui.heading("Frame budget");
+        let mut budget_ms = timings.budget.as_secs_f32() * 1000.;
+        if ui
+            .add(egui::Slider::new(&mut budget_ms, 0.1..=MAX_BUDGET_MS).text("Per system (ms)"))
+            .changed()
+        {
+            timings.budget = Duration::from_secs_f32(budget_ms / 1000.);
+        }
+
+        ui.separator();
+        ui.collapsing("Console", |ui| console.show(ui));
+    });
+}
@@ -0,0 +1,511 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_spicy_aseprite::{AsepriteAnimation, AsepriteBundle, AsepriteTag};
+use serde::Deserialize;
+
+use crate::{
+    camera::PixelCameraTag,
+    clock::WorldClock,
+    frame_budget::TimedExt,
+    interaction::{InteractEvent, InteractionSystem},
+    localization::Localization,
+    menu::halt_velocities,
+    mods,
+    npc::{AffectionTier, Relationship},
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    quest::QuestLog,
+    set_state,
+    settings::Settings,
+    sprites, AppState, CowTag,
+};
+
+static DIALOGUE_FILE: &str = "dialogue.ron";
+/// Node shown instead of an NPC's dialogue while their quest is unavailable.
+static UNAVAILABLE_NODE: &str = "unavailable";
+const TYPE_CHARS_PER_SEC: f32 = 40.;
+/// Screen pixels per portrait sprite pixel.
+const PORTRAIT_PIXEL_SCALE: f32 = 3.;
+/// Screen size of the portrait's box, left of the dialogue text.
+const PORTRAIT_BOX_SIZE: f32 = 120.;
+const TEXT_LEFT: f32 = 20.;
+const TEXT_BOTTOM: f32 = 60.;
+
+/// On-disk format of `assets/dialogue.ron`, or a mod's override of it.
+#[derive(Deserialize, Debug, Default)]
+pub struct DialogueTree {
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+/// `speaker` and all text fields are `Localization` keys.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueNode {
+    /// Defaults to the name of the entity being talked to.
+    #[serde(default)]
+    pub speaker: String,
+    pub text: String,
+    /// Options for the player to pick from. Without any, confirming moves on
+    /// to `next`, or ends the dialogue.
+    #[serde(default)]
+    pub choices: Vec<DialogueChoiceOption>,
+    #[serde(default)]
+    pub next: Option<String>,
+    /// Shown next to the text, talking while it types out.
+    #[serde(default)]
+    pub portrait: Option<Portrait>,
+}
+
+impl DialogueNode {
+    /// Choices offered to a player with `tier` of affection from the speaker,
+    /// along with their index in `choices`.
+    fn visible_choices(&self, tier: AffectionTier) -> Vec<(usize, &DialogueChoiceOption)> {
+        self.choices
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| option.requires.map_or(true, |required| tier >= required))
+            .collect()
+    }
+}
+
+/// Speaker portraits, played from the speaker's aseprite file.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Portrait {
+    Cow,
+    Player,
+}
+
+impl Portrait {
+    fn bundle(self, tag: AsepriteTag, transform: Transform) -> AsepriteBundle {
+        let aseprite = match self {
+            Portrait::Cow => sprites::Cow::sprite(),
+            Portrait::Player => sprites::Player::sprite(),
+        };
+        AsepriteBundle {
+            aseprite,
+            animation: AsepriteAnimation::from(tag),
+            transform,
+            ..Default::default()
+        }
+    }
+
+    /// Tags played while the text is typing out and once it's done. There
+    /// are no talk tags yet, walk cycles stand in for them.
+    fn tags(self) -> (AsepriteTag, AsepriteTag) {
+        match self {
+            Portrait::Cow => (
+                sprites::Cow::tags::SOUTH_WALK,
+                sprites::Cow::tags::SOUTH_IDLE,
+            ),
+            Portrait::Player => (
+                sprites::Player::tags::EAST_WALK,
+                sprites::Player::tags::EAST_IDLE,
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueChoiceOption {
+    pub text: String,
+    /// Node to branch to, `None` ends the dialogue.
+    #[serde(default)]
+    pub next: Option<String>,
+    /// Quest flag set when this option is picked.
+    #[serde(default)]
+    pub flag: Option<String>,
+    /// Only offered once the speaker's `Relationship` has reached this tier.
+    #[serde(default)]
+    pub requires: Option<AffectionTier>,
+}
+
+/// Dialogue started when the player interacts with this entity.
+#[derive(Component, Debug, Clone)]
+pub struct Dialogue {
+    pub start: String,
+}
+
+/// Sent when the player picks one of a node's choices.
+#[derive(Debug, Clone)]
+pub struct DialogueChoice {
+    pub speaker: Entity,
+    pub node: String,
+    pub choice: usize,
+    pub flag: Option<String>,
+}
+
+/// Starts talking to `speaker` at `node`, e.g. on interacting with an entity
+/// that has a `Dialogue`.
+#[derive(Debug, Clone)]
+pub struct StartDialogue {
+    pub speaker: Entity,
+    pub node: String,
+}
+
+/// The conversation in progress, present while in `AppState::Dialogue`.
+pub struct ActiveDialogue {
+    pub speaker: Entity,
+    pub node: String,
+    pub selected: usize,
+    /// Characters of the node's text typed out so far.
+    pub typed: f32,
+}
+
+impl ActiveDialogue {
+    fn new(speaker: Entity, node: String) -> Self {
+        Self {
+            speaker,
+            node,
+            selected: 0,
+            typed: 0.,
+        }
+    }
+
+    fn is_typing(&self, text: &str) -> bool {
+        (self.typed as usize) < text.chars().count()
+    }
+}
+
+#[derive(Component)]
+struct DialogueTextTag;
+
+#[derive(Component)]
+struct DialoguePortrait(Portrait);
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DialogueSystem {
+    Interact,
+    Type,
+}
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DialogueTree>()
+            .add_event::<DialogueChoice>()
+            .add_startup_system(load_dialogue)
+            .add_startup_system(setup_dialogue_text)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(render_dialogue),
+            )
+            .add_event::<StartDialogue>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(
+                        interact_dialogue
+                            .timed()
+                            .label(DialogueSystem::Interact)
+                            .after(InteractionSystem::Interact),
+                    )
+                    .with_system(start_dialogue.after(DialogueSystem::Interact)),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Dialogue).with_system(halt_velocities))
+            .add_system_set(
+                SystemSet::on_update(AppState::Dialogue)
+                    .in_phase(Phase::Input)
+                    .with_system(type_dialogue.timed().label(DialogueSystem::Type))
+                    .with_system(dialogue_input.after(DialogueSystem::Type)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Dialogue)
+                    .in_phase(Phase::Ui)
+                    .with_system(sync_portrait.after(DialogueSystem::Type)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Dialogue).with_system(despawn_portrait));
+    }
+}
+
+/// A missing or malformed dialogue file leaves the tree empty.
+fn load_dialogue(mut tree: ResMut<DialogueTree>) {
+    *tree = mods::load_ron(DIALOGUE_FILE).unwrap_or_else(|err| {
+        warn!("No dialogue, {}", err);
+        DialogueTree::default()
+    });
+}
+
+fn setup_dialogue_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(TEXT_BOTTOM),
+                    left: Val::Px(TEXT_LEFT),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 30.,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(DialogueTextTag);
+}
+
+fn interact_dialogue(
+    mut interact_events: EventReader<InteractEvent>,
+    quest_log: Res<QuestLog>,
+    clock: Res<WorldClock>,
+    dialogue_q: Query<(&Dialogue, Option<&Name>)>,
+    mut start_events: EventWriter<StartDialogue>,
+) {
+    for event in interact_events.iter() {
+        let (dialogue, name) = match dialogue_q.get(event.target) {
+            Ok(dialogue) => dialogue,
+            Err(_) => continue,
+        };
+        let available = name.map_or(true, |name| {
+            quest_log.is_available_for(name.as_str(), &clock)
+        });
+        let node = if available {
+            dialogue.start.clone()
+        } else {
+            UNAVAILABLE_NODE.to_string()
+        };
+        start_events.send(StartDialogue {
+            speaker: event.target,
+            node,
+        });
+    }
+}
+
+fn start_dialogue(
+    mut commands: Commands,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut start_events: EventReader<StartDialogue>,
+    tree: Res<DialogueTree>,
+    mut stats: ResMut<PlayerStats>,
+    cow_q: Query<(), With<CowTag>>,
+) {
+    for event in start_events.iter() {
+        if !tree.nodes.contains_key(&event.node) {
+            warn!("Unknown dialogue node {:?}", event.node);
+            continue;
+        }
+        if !set_state(&mut state, AppState::Dialogue) {
+            return;
+        }
+        if cow_q.get(event.speaker).is_ok() {
+            stats.cows_talked_to += 1;
+        }
+        commands.insert_resource(ActiveDialogue::new(event.speaker, event.node.clone()));
+        // The interact press that started the dialogue shouldn't also confirm
+        // its first line
+        keys.clear();
+        return;
+    }
+}
+
+fn type_dialogue(time: Res<Time>, active: Option<ResMut<ActiveDialogue>>) {
+    if let Some(mut active) = active {
+        active.typed += time.delta_seconds() * TYPE_CHARS_PER_SEC;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn dialogue_input(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    tree: Res<DialogueTree>,
+    localization: Res<Localization>,
+    mut state: ResMut<State<AppState>>,
+    active: Option<ResMut<ActiveDialogue>>,
+    mut choices: EventWriter<DialogueChoice>,
+    relationship_q: Query<&Relationship>,
+) {
+    let mut active = match active {
+        Some(active) => active,
+        None => return,
+    };
+    let node = &tree.nodes[&active.node];
+    let tier = relationship_q
+        .get(active.speaker)
+        .map_or(AffectionTier::Stranger, Relationship::tier);
+    let visible = node.visible_choices(tier);
+    let bindings = &settings.key_bindings;
+    // Confirming while the text types out shows all of it
+    if active.is_typing(&localization.get(&node.text)) {
+        if keys.just_pressed(bindings.interact) {
+            active.typed = f32::INFINITY;
+        }
+        return;
+    }
+    let option_count = visible.len().max(1);
+    if keys.just_pressed(bindings.up) || keys.just_pressed(KeyCode::Up) {
+        active.selected = (active.selected + option_count - 1) % option_count;
+    } else if keys.just_pressed(bindings.down) || keys.just_pressed(KeyCode::Down) {
+        active.selected = (active.selected + 1) % option_count;
+    } else if keys.just_pressed(bindings.interact) {
+        let next = match visible.get(active.selected) {
+            Some((choice, option)) => {
+                choices.send(DialogueChoice {
+                    speaker: active.speaker,
+                    node: active.node.clone(),
+                    choice: *choice,
+                    flag: option.flag.clone(),
+                });
+                option.next.clone()
+            }
+            None => node.next.clone(),
+        };
+        match next.filter(|next| tree.nodes.contains_key(next)) {
+            Some(next) => {
+                *active = ActiveDialogue::new(active.speaker, next);
+            }
+            None => {
+                commands.remove_resource::<ActiveDialogue>();
+                // A transition already queued leaves the dialogue just as well
+                set_state(&mut state, AppState::Playing);
+            }
+        }
+    }
+}
+
+fn render_dialogue(
+    tree: Res<DialogueTree>,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    active: Option<Res<ActiveDialogue>>,
+    name_q: Query<&Name>,
+    relationship_q: Query<&Relationship>,
+    mut text_q: Query<(&mut Text, &mut Style), With<DialogueTextTag>>,
+) {
+    let node = active.as_ref().map(|active| &tree.nodes[&active.node]);
+    let value = match (active.as_ref(), node) {
+        (Some(active), Some(node)) => {
+            let speaker = match (node.speaker.is_empty(), name_q.get(active.speaker)) {
+                (true, Ok(name)) => name.as_str(),
+                _ => node.speaker.as_str(),
+            };
+            let text = localization.get(&node.text);
+            let tier = relationship_q
+                .get(active.speaker)
+                .map_or(AffectionTier::Stranger, Relationship::tier);
+            let visible = node.visible_choices(tier);
+            let mut lines = vec![
+                format!("{}:", localization.get(speaker)),
+                text.chars().take(active.typed as usize).collect(),
+                String::new(),
+            ];
+            if active.is_typing(&text) {
+                // Choices show up once the text is done
+            } else if visible.is_empty() {
+                let key = format!("{:?}", settings.key_bindings.interact);
+                lines.push(localization.format("dialogue.continue", &[("key", &key)]));
+            }
+            for (i, (_, option)) in visible.iter().enumerate() {
+                let cursor = if i == active.selected { "> " } else { "  " };
+                lines.push(format!("{}{}", cursor, localization.get(&option.text)));
+            }
+            lines.join("\n")
+        }
+        _ => String::new(),
+    };
+    // Make room for the portrait left of the text
+    let left = match node.and_then(|node| node.portrait) {
+        Some(_) => TEXT_LEFT + PORTRAIT_BOX_SIZE,
+        None => TEXT_LEFT,
+    };
+
+    for (mut text, mut style) in text_q.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+        if style.position.left != Val::Px(left) {
+            style.position.left = Val::Px(left);
+        }
+    }
+}
+
+/// Keeps the current node's portrait in its box, talking while the text
+/// types out. The portrait is a sprite, so it's a child of the world camera
+/// scaled against the camera's zoom to stay the same size on screen.
+fn sync_portrait(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    tree: Res<DialogueTree>,
+    localization: Res<Localization>,
+    active: Option<Res<ActiveDialogue>>,
+    camera_q: Query<(Entity, &OrthographicProjection), With<PixelCameraTag>>,
+    mut portrait_q: Query<(
+        Entity,
+        &DialoguePortrait,
+        &mut Transform,
+        &mut AsepriteAnimation,
+    )>,
+) {
+    let (camera, projection) = match camera_q.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let (node, typing) = match active.as_ref() {
+        Some(active) => {
+            let node = &tree.nodes[&active.node];
+            (node, active.is_typing(&localization.get(&node.text)))
+        }
+        None => return,
+    };
+
+    let mut current = None;
+    for (entity, portrait, trans, anim) in portrait_q.iter_mut() {
+        if Some(portrait.0) == node.portrait {
+            current = Some((trans, anim));
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    let portrait = match node.portrait {
+        Some(portrait) => portrait,
+        None => return,
+    };
+    let (talk, idle) = portrait.tags();
+    let tag = if typing { talk } else { idle };
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    // Centered in the box at the bottom left corner of the screen, in camera
+    // space, with the camera looking down -z
+    let screen_offset = Vec2::new(
+        TEXT_LEFT + PORTRAIT_BOX_SIZE / 2. - window.width() / 2.,
+        TEXT_BOTTOM + PORTRAIT_BOX_SIZE / 2. - window.height() / 2.,
+    );
+    let mut transform = Transform::from_translation((screen_offset * projection.scale).extend(-1.));
+    transform.scale = Vec3::splat(PORTRAIT_PIXEL_SCALE * projection.scale);
+
+    match current {
+        Some((mut trans, mut anim)) => {
+            if *trans != transform {
+                *trans = transform;
+            }
+            if !anim.is_tag(tag) {
+                *anim = AsepriteAnimation::from(tag);
+            }
+        }
+        None => {
+            let entity = commands
+                .spawn_bundle(portrait.bundle(tag, transform))
+                .insert(DialoguePortrait(portrait))
+                .id();
+            commands.entity(camera).push_children(&[entity]);
+        }
+    }
+}
+
+fn despawn_portrait(mut commands: Commands, portrait_q: Query<Entity, With<DialoguePortrait>>) {
+    for entity in portrait_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin},
+    shapes,
+};
+
+use crate::{
+    camera::{cursor_world_position, PixelCameraTag},
+    collision::{CollisionWorld, InCollisionWorld, QueryMask},
+    layer::RenderLayerGroup,
+    level::{spawn_prefab, spawn_wall, Level, Prefab, PrefabLayout, Wall, WallLayout},
+    menu::halt_velocities,
+    patrol::PatrolPath,
+    phase::{Phase, PhaseExt},
+    set_state, AppState,
+};
+
+/// Placement and wall sizes snap to this many sprite pixels.
+const GRID_SIZE: f32 = 8.;
+const CAMERA_PAN_SPEED: f32 = 150.;
+const WALL_PREVIEW_COLOR: Color = Color::rgba(0.45, 0.38, 0.3, 0.5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteEntry {
+    Wall,
+    Prefab(Prefab),
+}
+
+impl PaletteEntry {
+    pub fn all() -> Vec<PaletteEntry> {
+        let mut entries = vec![PaletteEntry::Wall];
+        entries.extend(
+            Prefab::ALL
+                .iter()
+                .map(|prefab| PaletteEntry::Prefab(*prefab)),
+        );
+        entries
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            PaletteEntry::Wall => String::from("Wall"),
+            PaletteEntry::Prefab(prefab) => format!("{:?}", prefab),
+        }
+    }
+}
+
+enum Drag {
+    /// Drawing a new wall from `start`, `preview` shows its current extent.
+    Wall { start: Vec2, preview: Entity },
+    /// Moving a placed entity, keeping it at `offset` from the cursor.
+    Move { entity: Entity, offset: Vec2 },
+}
+
+#[derive(Default)]
+pub struct Editor {
+    /// Index into `PaletteEntry::all()`.
+    pub selected: usize,
+    drag: Option<Drag>,
+}
+
+#[derive(Component)]
+struct WallPreviewTag;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(toggle_editor),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Editor).with_system(halt_velocities))
+            .add_system_set(SystemSet::on_exit(AppState::Editor).with_system(leave_editor))
+            .add_system_set(
+                SystemSet::on_update(AppState::Editor)
+                    .in_phase(Phase::Input)
+                    .with_system(editor_keys)
+                    .with_system(editor_mouse),
+            );
+    }
+}
+
+fn snap(point: Vec2) -> Vec2 {
+    (point / GRID_SIZE).round() * GRID_SIZE
+}
+
+fn toggle_editor(keys: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if !keys.just_pressed(KeyCode::F2) {
+        return;
+    }
+    match *state.current() {
+        AppState::Playing => {
+            set_state(&mut state, AppState::Editor);
+        }
+        AppState::Editor => {
+            set_state(&mut state, AppState::Playing);
+        }
+        _ => {}
+    }
+}
+
+fn leave_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    mut camera_q: Query<&mut Transform, With<PixelCameraTag>>,
+) {
+    if let Some(Drag::Wall { preview, .. }) = editor.drag.take() {
+        commands.entity(preview).despawn();
+    }
+    // Hand the view back centered, where it was before panning around
+    for mut camera_trans in camera_q.iter_mut() {
+        camera_trans.translation.x = 0.;
+        camera_trans.translation.y = 0.;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn editor_keys(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut editor: ResMut<Editor>,
+    mut camera_q: Query<&mut Transform, With<PixelCameraTag>>,
+    wall_q: Query<(&Transform, &Wall), Without<PixelCameraTag>>,
+    prefab_q: Query<
+        (
+            &Transform,
+            &Prefab,
+            Option<&PatrolPath>,
+            Option<&InCollisionWorld>,
+        ),
+        Without<PixelCameraTag>,
+    >,
+) {
+    if keys.just_pressed(KeyCode::Tab) {
+        editor.selected = (editor.selected + 1) % PaletteEntry::all().len();
+    }
+
+    let mut pan = Vec2::ZERO;
+    if keys.pressed(KeyCode::A) || keys.pressed(KeyCode::Left) {
+        pan.x -= 1.;
+    }
+    if keys.pressed(KeyCode::D) || keys.pressed(KeyCode::Right) {
+        pan.x += 1.;
+    }
+    if keys.pressed(KeyCode::W) || keys.pressed(KeyCode::Up) {
+        pan.y += 1.;
+    }
+    if keys.pressed(KeyCode::S) || keys.pressed(KeyCode::Down) {
+        pan.y -= 1.;
+    }
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if !ctrl {
+        for mut camera_trans in camera_q.iter_mut() {
+            camera_trans.translation +=
+                (pan.normalize_or_zero() * CAMERA_PAN_SPEED * time.delta_seconds()).extend(0.);
+        }
+    }
+
+    if ctrl && keys.just_pressed(KeyCode::S) {
+        let level = Level {
+            walls: wall_q
+                .iter()
+                .map(|(trans, wall)| WallLayout {
+                    position: trans.translation.truncate().into(),
+                    size: wall.size.into(),
+                })
+                .collect(),
+            prefabs: prefab_q
+                .iter()
+                .map(|(trans, prefab, patrol, world)| PrefabLayout {
+                    prefab: *prefab,
+                    position: trans.translation.truncate().into(),
+                    patrol: patrol.cloned(),
+                    world: world.map(|world| world.0.clone()),
+                })
+                .collect(),
+        };
+        level.save();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn editor_mouse(
+    mut commands: Commands,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    collision_world: Res<CollisionWorld>,
+    mut editor: ResMut<Editor>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelCameraTag>>,
+    mut placed_q: Query<&mut Transform, (Or<(With<Wall>, With<Prefab>)>, Without<WallPreviewTag>)>,
+    mut preview_q: Query<&mut Transform, With<WallPreviewTag>>,
+) {
+    let (camera, camera_trans) = camera_q.single();
+    let cursor = match windows
+        .get_primary()
+        .and_then(|window| cursor_world_position(window, camera, camera_trans))
+    {
+        Some(cursor) => snap(cursor),
+        None => return,
+    };
+    // Only entities that are part of the level can be moved or deleted
+    let picked = collision_world
+        .query_point(cursor, QueryMask::COLLIDERS)
+        .into_iter()
+        .find(|entity| placed_q.get(*entity).is_ok());
+
+    if mouse.just_pressed(MouseButton::Left) {
+        let entry = PaletteEntry::all()[editor.selected];
+        editor.drag = match (picked, entry) {
+            (Some(entity), _) => {
+                let position = placed_q.get(entity).unwrap().translation.truncate();
+                Some(Drag::Move {
+                    entity,
+                    offset: position - cursor,
+                })
+            }
+            (None, PaletteEntry::Wall) => {
+                let preview = commands
+                    .spawn_bundle(GeometryBuilder::build_as(
+                        &shapes::Rectangle {
+                            extents: Vec2::ONE,
+                            origin: RectangleOrigin::Center,
+                        },
+                        DrawMode::Fill(FillMode::color(WALL_PREVIEW_COLOR)),
+                        Transform::from_translation(cursor.extend(RenderLayerGroup::UiWorld.z())),
+                    ))
+                    .insert(WallPreviewTag)
+                    .id();
+                Some(Drag::Wall {
+                    start: cursor,
+                    preview,
+                })
+            }
+            (None, PaletteEntry::Prefab(prefab)) => {
+                spawn_prefab(&mut commands, prefab, cursor);
+                None
+            }
+        };
+    } else if mouse.just_released(MouseButton::Left) {
+        if let Some(Drag::Wall { start, preview }) = editor.drag.take() {
+            commands.entity(preview).despawn();
+            let size = (cursor - start).abs();
+            if size.x >= GRID_SIZE && size.y >= GRID_SIZE {
+                spawn_wall(&mut commands, (start + cursor) / 2., size);
+            }
+        }
+    } else if mouse.just_pressed(MouseButton::Right) {
+        if let Some(entity) = picked {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    match editor.drag {
+        Some(Drag::Wall { start, preview }) => {
+            if let Ok(mut preview_trans) = preview_q.get_mut(preview) {
+                preview_trans.translation =
+                    ((start + cursor) / 2.).extend(RenderLayerGroup::UiWorld.z());
+                preview_trans.scale = (cursor - start).abs().max(Vec2::ONE).extend(1.);
+            }
+        }
+        Some(Drag::Move { entity, offset }) => {
+            if let Ok(mut trans) = placed_q.get_mut(entity) {
+                let z = trans.translation.z;
+                trans.translation = (cursor + offset).extend(z);
+            }
+        }
+        None => {}
+    }
+}
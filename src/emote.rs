@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::{DrawMode, FillMode, GeometryBuilder, PathBuilder};
+
+use crate::{
+    lifetime::Lifetime,
+    phase::{Phase, PhaseExt},
+    time_scale::TimeScale,
+};
+
+const EMOTE_DURATION: f32 = 1.2;
+const EMOTE_RISE_SPEED: f32 = 8.;
+/// Height above the owner's origin where emotes appear, in sprite pixels.
+const EMOTE_OFFSET: f32 = 20.;
+
+#[derive(Component)]
+pub struct Emote {
+    color: Color,
+}
+
+pub struct EmotePlugin;
+
+impl Plugin for EmotePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(animate_emotes),
+        );
+    }
+}
+
+/// Spawns a heart that floats up from `owner` and fades out.
+pub fn spawn_heart(commands: &mut Commands, owner: Entity) {
+    let mut path = PathBuilder::new();
+    path.move_to(Vec2::new(0., -3.));
+    path.cubic_bezier_to(Vec2::new(-6., 1.), Vec2::new(-3., 5.), Vec2::new(0., 2.));
+    path.cubic_bezier_to(Vec2::new(3., 5.), Vec2::new(6., 1.), Vec2::new(0., -3.));
+    path.close();
+
+    let color = Color::rgb(0.9, 0.2, 0.3);
+    let heart = commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &path.build(),
+            DrawMode::Fill(FillMode::color(color)),
+            Transform::from_translation(Vec3::new(0., EMOTE_OFFSET, 1.)),
+        ))
+        .insert(Emote { color })
+        .insert(Lifetime::from_seconds(EMOTE_DURATION))
+        .id();
+    commands.entity(owner).push_children(&[heart]);
+}
+
+fn animate_emotes(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut emote_q: Query<(&Emote, &Lifetime, &mut Transform, &mut DrawMode)>,
+) {
+    for (emote, lifetime, mut transform, mut draw_mode) in emote_q.iter_mut() {
+        transform.translation.y += EMOTE_RISE_SPEED * time_scale.delta_seconds(&time);
+        let mut color = emote.color;
+        color.set_a(1. - lifetime.percent());
+        *draw_mode = DrawMode::Fill(FillMode::color(color));
+    }
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::PixelCameraTag,
+    collision::ImpactEvent,
+    frame_budget::TimedExt,
+    hitbox::HitEvent,
+    phase::{Phase, PhaseExt},
+    settings::Settings,
+    time_scale::TimeScale,
+};
+
+/// Largest camera offset, in world pixels, at full trauma.
+const MAX_SHAKE_OFFSET: f32 = 6.;
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.5;
+/// Impacts at least this fast shake the camera and briefly stop time.
+const HEAVY_IMPACT_SPEED: f32 = 200.;
+const HIT_STOP_SECS: f32 = 0.06;
+
+/// Camera shake driven by trauma in `0..=1`, which decays over time. The
+/// offset grows with the square of the trauma, so small bumps stay subtle.
+#[derive(Default)]
+pub struct ScreenShake {
+    trauma: f32,
+}
+
+impl ScreenShake {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0., 1.);
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FeedbackSystem {
+    Trigger,
+}
+
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenShake>().add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(heavy_impact_feedback.timed().label(FeedbackSystem::Trigger))
+                .with_system(hit_feedback.timed().label(FeedbackSystem::Trigger))
+                .with_system(shake_camera.after(FeedbackSystem::Trigger)),
+        );
+    }
+}
+
+fn heavy_impact_feedback(
+    mut impacts: EventReader<ImpactEvent>,
+    mut shake: ResMut<ScreenShake>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    for impact in impacts.iter() {
+        if impact.speed >= HEAVY_IMPACT_SPEED {
+            shake.add_trauma(0.4);
+            time_scale.hit_stop(HIT_STOP_SECS);
+        }
+    }
+}
+
+fn hit_feedback(
+    mut hits: EventReader<HitEvent>,
+    mut shake: ResMut<ScreenShake>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    for _ in hits.iter() {
+        shake.add_trauma(0.3);
+        time_scale.hit_stop(HIT_STOP_SECS);
+    }
+}
+
+/// Offsets the camera on top of wherever it has been moved to, undoing last
+/// frame's offset first. Scaled down, or off, by
+/// `Accessibility::screen_shake`.
+fn shake_camera(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut shake: ResMut<ScreenShake>,
+    mut last_offset: Local<Vec2>,
+    mut camera_q: Query<&mut Transform, With<PixelCameraTag>>,
+) {
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+    if shake.trauma == 0. && *last_offset == Vec2::ZERO {
+        return;
+    }
+
+    // Cheap smooth noise, sines at unrelated frequencies per axis
+    let t = time.seconds_since_startup() as f32;
+    let noise = Vec2::new(
+        (t * 37.).sin() + (t * 61.3).sin() * 0.5,
+        (t * 43.7).sin() + (t * 53.1).sin() * 0.5,
+    ) / 1.5;
+    let strength = settings.accessibility.screen_shake;
+    let offset = (noise * MAX_SHAKE_OFFSET * strength * shake.trauma * shake.trauma).round();
+    for mut camera_trans in camera_q.iter_mut() {
+        camera_trans.translation += (offset - *last_offset).extend(0.);
+    }
+    *last_offset = offset;
+}
@@ -0,0 +1,201 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder},
+    shapes,
+};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine},
+    collision::{
+        AabbBundle, AabbKind, CollisionBehavior, CollisionPair, ContactFilterAppExt, SensorEvent,
+        Velocity,
+    },
+    emote::spawn_heart,
+    floating_text::SpawnFloatingText,
+    frame_budget::TimedExt,
+    hotbar::Hotbar,
+    inventory::Inventory,
+    layer::RenderLayerGroup,
+    lifetime::Lifetime,
+    localization::Localization,
+    mount::Riding,
+    npc::{announce_tier, NpcState, Relationship, FEED_AFFECTION},
+    phase::{Phase, PhaseExt},
+    settings::{Action, ActionBuffer, SettingsSystem},
+    AppState, CowTag, PlayerTag,
+};
+
+/// Items that can be thrown to NPCs to feed them.
+const THROWABLE: [&str; 1] = ["Hay"];
+const THROW_SPEED: f32 = 160.;
+/// Thrown items that don't hit anything land and are lost after this long.
+const THROW_LIFETIME_SECS: f32 = 0.8;
+const PROJECTILE_SIZE: f32 = 6.;
+const HAY_COLOR: Color = Color::rgb(0.9, 0.8, 0.3);
+
+/// A thrown item in flight.
+#[derive(Component, Debug)]
+pub struct Projectile {
+    pub item: String,
+    pub thrower: Entity,
+}
+
+/// Marks NPCs that were fed, and can be milked once, see `milking`.
+#[derive(Component, Debug)]
+pub struct Fed;
+
+/// Sent when a thrown item hits an NPC.
+#[derive(Debug, Clone)]
+pub struct FedEvent {
+    pub npc: Entity,
+    pub item: String,
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FeedingSystem {
+    ProjectileHits,
+}
+
+pub struct FeedingPlugin;
+
+impl Plugin for FeedingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FedEvent>()
+            .add_contact_filter(projectile_passes_thrower)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Input)
+                    .with_system(throw_item.after(SettingsSystem::BufferActions)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(projectile_hits.timed().label(FeedingSystem::ProjectileHits))
+                    .with_system(feed_npcs.after(FeedingSystem::ProjectileHits)),
+            );
+    }
+}
+
+fn throw_item(
+    mut commands: Commands,
+    mut buffer: ResMut<ActionBuffer>,
+    hotbar: Res<Hotbar>,
+    mut inventory: ResMut<Inventory>,
+    player_q: Query<(Entity, &GlobalTransform, &Velocity, &AnimStateMachine), With<PlayerTag>>,
+) {
+    let item = match hotbar.selected_item(&inventory) {
+        Some(item) if THROWABLE.contains(&item) => item.to_string(),
+        _ => return,
+    };
+    let (player, trans, velocity, machine) = match player_q.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    if !buffer.consume(Action::UseItem) || !inventory.take(&item, 1) {
+        return;
+    }
+
+    // Throw where the player is heading, or where they face when standing
+    let direction = if velocity.0 != Vec2::ZERO {
+        velocity.0.normalize()
+    } else {
+        machine.facing().direction()
+    };
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: PROJECTILE_SIZE / 2.,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Fill(FillMode::color(HAY_COLOR)),
+            Transform::from_translation(RenderLayerGroup::Actors.at(trans.translation.xy())),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                Vec2::splat(PROJECTILE_SIZE),
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::PURPLE,
+            ));
+        })
+        .insert(Projectile {
+            item,
+            thrower: player,
+        })
+        .insert(Lifetime::from_seconds(THROW_LIFETIME_SECS))
+        .insert(Velocity(direction * THROW_SPEED))
+        .insert(RenderLayerGroup::Actors);
+}
+
+/// Thrown items fly through their thrower and whatever the thrower rides,
+/// rather than hitting them on the way out.
+fn projectile_passes_thrower(world: &World, pair: &CollisionPair) -> bool {
+    let passes = |projectile: Entity, other: Entity| {
+        let thrower = match world.get::<Projectile>(projectile) {
+            Some(projectile) => projectile.thrower,
+            None => return false,
+        };
+        other == thrower
+            || world
+                .get::<Riding>(thrower)
+                .map_or(false, |riding| riding.mount == other)
+    };
+    !passes(pair.ent1, pair.ent2) && !passes(pair.ent2, pair.ent1)
+}
+
+/// Thrown items feed the cows they hit and stop at anything else solid.
+fn projectile_hits(
+    mut commands: Commands,
+    mut sensor_events: EventReader<SensorEvent>,
+    mut fed_events: EventWriter<FedEvent>,
+    projectile_q: Query<&Projectile>,
+    cow_q: Query<(), With<CowTag>>,
+) {
+    for event in sensor_events.iter() {
+        let (sensor, other) = match *event {
+            SensorEvent::Entered { sensor, other } => (sensor, other),
+            SensorEvent::Exited { .. } => continue,
+        };
+        let projectile = match projectile_q.get(sensor) {
+            Ok(projectile) => projectile,
+            Err(_) => continue,
+        };
+        if cow_q.get(other).is_ok() {
+            fed_events.send(FedEvent {
+                npc: other,
+                item: projectile.item.clone(),
+            });
+        }
+        commands.entity(sensor).despawn_recursive();
+    }
+}
+
+fn feed_npcs(
+    mut commands: Commands,
+    localization: Res<Localization>,
+    mut fed_events: EventReader<FedEvent>,
+    mut floating_texts: EventWriter<SpawnFloatingText>,
+    mut npc_q: Query<(
+        &mut Relationship,
+        &mut NpcState,
+        &mut Velocity,
+        &mut AnimStateMachine,
+    )>,
+) {
+    for event in fed_events.iter() {
+        let (mut relationship, mut state, mut velocity, mut machine) =
+            match npc_q.get_mut(event.npc) {
+                Ok(npc) => npc,
+                Err(_) => continue,
+            };
+        if let Some(tier) = relationship.raise(FEED_AFFECTION) {
+            announce_tier(&mut floating_texts, &localization, event.npc, tier);
+        }
+        // Food calms down even fleeing cows
+        *state = NpcState::Idle;
+        velocity.0 = Vec2::ZERO;
+        machine.set_state(AnimState::Eat);
+        spawn_heart(&mut commands, event.npc);
+        commands.entity(event.npc).insert(Fed);
+    }
+}
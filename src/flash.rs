@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+
+use crate::{
+    hitbox::HitEvent,
+    interaction::InteractEvent,
+    phase::{Phase, PhaseExt},
+};
+
+/// Sprite colors multiply the texture, so white only brightens it when
+/// overdriven past 1.
+pub const FLASH_WHITE: Color = Color::rgb(4., 4., 4.);
+pub const FLASH_RED: Color = Color::rgb(1., 0.3, 0.3);
+const HIT_FLASH_SECS: f32 = 0.3;
+/// Flashes per second of a hit's blink.
+const HIT_FLASH_RATE: f32 = 20.;
+const INTERACT_FLASH_SECS: f32 = 0.15;
+
+/// How strongly the flash color shows over the flash's duration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlashCurve {
+    /// Full color until the flash ends.
+    Constant,
+    /// Fades from full color back to the original one.
+    FadeOut,
+    /// Switches between full and original color `rate` times per second.
+    Blink { rate: f32 },
+}
+
+impl FlashCurve {
+    /// Strength in `0..=1` of the flash color, `t` seconds into a flash of
+    /// `duration` seconds.
+    fn strength(&self, t: f32, duration: f32) -> f32 {
+        match *self {
+            FlashCurve::Constant => 1.,
+            FlashCurve::FadeOut => 1. - (t / duration).clamp(0., 1.),
+            FlashCurve::Blink { rate } => {
+                if (t * rate) as u32 % 2 == 0 {
+                    1.
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+}
+
+/// Temporarily tints an entity's `TextureAtlasSprite` towards `color`,
+/// restoring its color once the flash is over. Start one by inserting it.
+///
+/// A flash inserted while another is running replaces it, but still restores
+/// the color from before the first one.
+#[derive(Component, Debug, Clone)]
+pub struct SpriteFlash {
+    pub color: Color,
+    pub curve: FlashCurve,
+    timer: Timer,
+}
+
+impl SpriteFlash {
+    pub fn new(color: Color, secs: f32, curve: FlashCurve) -> Self {
+        Self {
+            color,
+            curve,
+            timer: Timer::from_seconds(secs, false),
+        }
+    }
+
+    /// Red blink of something taking damage.
+    pub fn hit() -> Self {
+        Self::new(
+            FLASH_RED,
+            HIT_FLASH_SECS,
+            FlashCurve::Blink {
+                rate: HIT_FLASH_RATE,
+            },
+        )
+    }
+
+    /// Short white flash of something being interacted with.
+    pub fn interact() -> Self {
+        Self::new(FLASH_WHITE, INTERACT_FLASH_SECS, FlashCurve::FadeOut)
+    }
+}
+
+/// Sprite color from before the flashes, until they are over.
+#[derive(Component)]
+struct FlashBase(Color);
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FlashSystem {
+    Start,
+}
+
+pub struct FlashPlugin;
+
+impl Plugin for FlashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(flash_hits.label(FlashSystem::Start))
+                .with_system(flash_interactions.label(FlashSystem::Start))
+                .with_system(update_flashes.after(FlashSystem::Start)),
+        );
+    }
+}
+
+fn flash_hits(mut commands: Commands, mut hits: EventReader<HitEvent>) {
+    for hit in hits.iter() {
+        commands.entity(hit.target).insert(SpriteFlash::hit());
+    }
+}
+
+fn flash_interactions(mut commands: Commands, mut interact_events: EventReader<InteractEvent>) {
+    for event in interact_events.iter() {
+        commands
+            .entity(event.target)
+            .insert(SpriteFlash::interact());
+    }
+}
+
+fn update_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flash_q: Query<(
+        Entity,
+        &mut SpriteFlash,
+        Option<&mut TextureAtlasSprite>,
+        Option<&FlashBase>,
+    )>,
+) {
+    for (entity, mut flash, sprite, base) in flash_q.iter_mut() {
+        let mut sprite = match sprite {
+            Some(sprite) => sprite,
+            None => {
+                commands.entity(entity).remove::<SpriteFlash>();
+                continue;
+            }
+        };
+        let base = match base {
+            Some(base) => base.0,
+            None => {
+                commands.entity(entity).insert(FlashBase(sprite.color));
+                sprite.color
+            }
+        };
+
+        // Real time, so flashes still show during hit-stop
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            sprite.color = base;
+            commands
+                .entity(entity)
+                .remove::<SpriteFlash>()
+                .remove::<FlashBase>();
+            continue;
+        }
+        let strength = flash.curve.strength(
+            flash.timer.elapsed_secs(),
+            flash.timer.duration().as_secs_f32(),
+        );
+        let [r, g, b, a] = base.as_rgba_f32();
+        let [fr, fg, fb, _] = flash.color.as_rgba_f32();
+        let lerp = |from: f32, to: f32| from + (to - from) * strength;
+        sprite.color = Color::rgba(lerp(r, fr), lerp(g, fg), lerp(b, fb), a);
+    }
+}
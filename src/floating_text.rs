@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    camera::PixelCameraSettings,
+    frame_budget::TimedExt,
+    layer::RenderLayerGroup,
+    lifetime::{Lifetime, OnExpire},
+    phase::{Phase, PhaseExt},
+    pool::EntityPool,
+    time_scale::TimeScale,
+};
+
+const FLOATING_TEXT_DURATION: f32 = 1.;
+const FLOATING_TEXT_RISE_SPEED: f32 = 12.;
+/// Font size in screen pixels; the text is scaled down by the camera zoom so
+/// it stays crisp instead of being magnified with the sprites.
+const FLOATING_TEXT_FONT_SIZE: f32 = 24.;
+/// Height above the anchor's origin, in sprite pixels.
+const FLOATING_TEXT_OFFSET: f32 = 22.;
+/// Spawning text is relatively expensive (layout, glyph upload), so bursts
+/// are spread over several frames.
+const MAX_SPAWNS_PER_FRAME: usize = 8;
+
+/// Request for a short-lived text rising above `anchor`.
+#[derive(Debug, Clone)]
+pub struct SpawnFloatingText {
+    pub anchor: Entity,
+    pub text: String,
+    pub color: Color,
+}
+
+/// Pooled, released once its `Lifetime` is up.
+#[derive(Component)]
+pub struct FloatingText {
+    color: Color,
+}
+
+struct FloatingTextFont(Handle<Font>);
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FloatingTextSystem {
+    Spawn,
+}
+
+pub struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityPool<FloatingText>>()
+            .add_event::<SpawnFloatingText>()
+            .add_startup_system(load_font)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(
+                        spawn_floating_texts
+                            .timed()
+                            .label(FloatingTextSystem::Spawn),
+                    )
+                    .with_system(animate_floating_texts.after(FloatingTextSystem::Spawn)),
+            );
+    }
+}
+
+fn load_font(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(FloatingTextFont(asset_server.load("Share-Regular.ttf")));
+}
+
+fn spawn_floating_texts(
+    mut commands: Commands,
+    mut requests: EventReader<SpawnFloatingText>,
+    mut backlog: Local<VecDeque<SpawnFloatingText>>,
+    mut pool: ResMut<EntityPool<FloatingText>>,
+    font: Res<FloatingTextFont>,
+    camera_settings: Res<PixelCameraSettings>,
+    anchor_q: Query<&GlobalTransform>,
+) {
+    backlog.extend(requests.iter().cloned());
+
+    let count = backlog.len().min(MAX_SPAWNS_PER_FRAME);
+    for request in backlog.drain(..count) {
+        let anchor = match anchor_q.get(request.anchor) {
+            Ok(anchor) => anchor.translation,
+            Err(_) => continue,
+        };
+        let transform = Transform {
+            // Above particles
+            translation: Vec3::new(
+                anchor.x,
+                anchor.y + FLOATING_TEXT_OFFSET,
+                RenderLayerGroup::UiWorld.z() + 1.,
+            ),
+            scale: Vec3::splat(1. / camera_settings.effective_zoom()),
+            ..Default::default()
+        };
+        let text = Text::with_section(
+            request.text,
+            TextStyle {
+                font: font.0.clone(),
+                font_size: FLOATING_TEXT_FONT_SIZE,
+                color: request.color,
+            },
+            TextAlignment {
+                vertical: VerticalAlign::Center,
+                horizontal: HorizontalAlign::Center,
+            },
+        );
+        pool.acquire(
+            &mut commands,
+            (
+                text,
+                transform,
+                FloatingText {
+                    color: request.color,
+                },
+                Lifetime::from_seconds(FLOATING_TEXT_DURATION).keep(),
+            ),
+            |commands| commands.spawn_bundle(Text2dBundle::default()).id(),
+        );
+    }
+}
+
+fn animate_floating_texts(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut pool: ResMut<EntityPool<FloatingText>>,
+    mut expired: EventReader<OnExpire>,
+    mut text_q: Query<(&FloatingText, &Lifetime, &mut Transform, &mut Text)>,
+) {
+    for OnExpire { entity } in expired.iter() {
+        if text_q.get(*entity).is_ok() {
+            pool.release(&mut commands, *entity);
+        }
+    }
+
+    for (floating, lifetime, mut transform, mut text) in text_q.iter_mut() {
+        if lifetime.finished() {
+            continue;
+        }
+
+        transform.translation.y += FLOATING_TEXT_RISE_SPEED * time_scale.delta_seconds(&time);
+        let mut color = floating.color;
+        color.set_a(1. - lifetime.percent());
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine},
+    collision::{CollisionBehavior, CollisionWorld, QueryMask, Velocity, Yielding},
+    group::GroupTag,
+    herd::Herd,
+    level::COW_COLLIDER_SIZE,
+    npc::{AffectionTier, NpcSystem, Relationship},
+    pathfinding::find_path,
+    phase::{Phase, PhaseExt},
+    script::Script,
+    time_scale::TimeScale,
+    AppState, Controlled, CowTag, PlayerTag,
+};
+
+/// Followers stop this close to their target.
+const STOP_DISTANCE: f32 = 36.;
+/// Followers further than this from their target hurry to catch up.
+const CATCH_UP_DISTANCE: f32 = 96.;
+const FOLLOW_SPEED: f32 = 60.;
+const CATCH_UP_SPEED: f32 = 110.;
+const REPATH_SECS: f32 = 0.5;
+/// Waypoints closer than this count as reached.
+const WAYPOINT_RADIUS: f32 = 4.;
+
+/// Makes an NPC trail the given entity, pathfinding around static colliders.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FollowTarget(pub Entity);
+
+/// Path to the follow target, used while it isn't in direct line of sight.
+#[derive(Component)]
+pub struct FollowPath {
+    waypoints: Vec<Vec2>,
+    repath: Timer,
+}
+
+impl FollowPath {
+    /// Moves the waypoints by `-offset` along with the world origin, see
+    /// `origin::shift_origin`.
+    pub fn shift(&mut self, offset: Vec2) {
+        for waypoint in self.waypoints.iter_mut() {
+            *waypoint -= offset;
+        }
+    }
+}
+
+impl Default for FollowPath {
+    fn default() -> Self {
+        Self {
+            waypoints: Vec::new(),
+            repath: Timer::from_seconds(REPATH_SECS, true),
+        }
+    }
+}
+
+pub struct FollowPlugin;
+
+impl Plugin for FollowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Ai)
+                .with_system(recruit_companions.after(NpcSystem::Reactions))
+                .with_system(follow.after(NpcSystem::Reactions)),
+        );
+    }
+}
+
+/// Cows the player is friends with start following them, one at a time.
+fn recruit_companions(
+    mut commands: Commands,
+    player_q: Query<Entity, With<PlayerTag>>,
+    follower_q: Query<(), With<FollowTarget>>,
+    // Scripted NPCs keep to their script
+    cow_q: Query<(Entity, &Relationship), (With<CowTag>, Changed<Relationship>, Without<Script>)>,
+) {
+    if !follower_q.is_empty() {
+        return;
+    }
+    let player = player_q.single();
+    if let Some((cow, _)) = cow_q
+        .iter()
+        .find(|(_, relationship)| relationship.tier() >= AffectionTier::Friend)
+    {
+        // Companions leave their herd for good
+        commands
+            .entity(cow)
+            .insert(FollowTarget(player))
+            .insert(Yielding)
+            .remove::<Herd>()
+            .remove::<GroupTag>();
+    }
+}
+
+fn follow(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    collision_world: Res<CollisionWorld>,
+    target_q: Query<&GlobalTransform>,
+    mut follower_q: Query<
+        (
+            Entity,
+            &FollowTarget,
+            &GlobalTransform,
+            &mut Velocity,
+            &mut AnimStateMachine,
+            Option<&mut FollowPath>,
+        ),
+        // Ridden followers are steered by the player
+        Without<Controlled>,
+    >,
+) {
+    for (follower, target, trans, mut velocity, mut machine, path) in follower_q.iter_mut() {
+        let mut path = match path {
+            Some(path) => path,
+            None => {
+                commands.entity(follower).insert(FollowPath::default());
+                continue;
+            }
+        };
+        let target_pos = match target_q.get(target.0) {
+            Ok(target_trans) => target_trans.translation.xy(),
+            Err(_) => {
+                commands
+                    .entity(follower)
+                    .remove::<FollowTarget>()
+                    .remove::<FollowPath>()
+                    .remove::<Yielding>();
+                continue;
+            }
+        };
+        let position = trans.translation.xy();
+        let to_target = target_pos - position;
+        let distance = to_target.length();
+
+        if distance <= STOP_DISTANCE {
+            velocity.0 = Vec2::ZERO;
+            path.waypoints.clear();
+            machine.face(to_target);
+            machine.set_state(AnimState::Idle);
+            continue;
+        }
+
+        let in_sight = collision_world
+            .aabb_cast(
+                position,
+                COW_COLLIDER_SIZE / 2.,
+                to_target,
+                distance,
+                QueryMask {
+                    exclude: Some(follower),
+                    ..QueryMask::COLLIDERS.with_behavior(CollisionBehavior::Static)
+                },
+            )
+            .is_none();
+        let heading = if in_sight {
+            path.waypoints.clear();
+            to_target
+        } else {
+            path.repath.tick(time_scale.delta(&time));
+            if path.waypoints.is_empty() || path.repath.just_finished() {
+                path.waypoints = find_path(
+                    &collision_world,
+                    position,
+                    target_pos,
+                    COW_COLLIDER_SIZE / 2.,
+                )
+                .unwrap_or_default();
+            }
+            while path.waypoints.first().map_or(false, |waypoint| {
+                waypoint.distance(position) < WAYPOINT_RADIUS
+            }) {
+                path.waypoints.remove(0);
+            }
+            path.waypoints
+                .first()
+                .map_or(Vec2::ZERO, |waypoint| *waypoint - position)
+        };
+
+        let speed = if distance > CATCH_UP_DISTANCE {
+            CATCH_UP_SPEED
+        } else {
+            FOLLOW_SPEED
+        };
+        velocity.0 = heading.normalize_or_zero() * speed;
+        machine.face(velocity.0);
+        machine.set_state(if velocity.0 == Vec2::ZERO {
+            AnimState::Idle
+        } else {
+            AnimState::Walk
+        });
+    }
+}
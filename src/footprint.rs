@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    anim::{AnimFrameEvent, AnimState},
+    frame_budget::TimedExt,
+    layer::RenderLayerGroup,
+    phase::{Phase, PhaseExt},
+    terrain::OnTerrain,
+    time_scale::TimeScale,
+};
+
+/// Most footprints on the ground at once, the oldest are removed first.
+const MAX_FOOTPRINTS: usize = 64;
+/// Seconds a footprint takes to fade out.
+const FOOTPRINT_SECS: f32 = 6.;
+const FOOTPRINT_SIZE: Vec2 = Vec2::new(3., 4.);
+/// From the walker's origin down to its feet.
+const FEET_OFFSET: f32 = -14.;
+/// From the middle of the feet to either foot.
+const FOOT_SPACING: f32 = 3.;
+/// Depth in the ground band, above hazard zones and below patrol paths.
+const FOOTPRINT_DEPTH: f32 = 0.1;
+
+/// Leaves footprints on terrain with a `footprint_color`, one per frame of
+/// the walk animation, alternating feet.
+#[derive(Component, Debug, Default)]
+pub struct LeavesFootprints {
+    left: bool,
+}
+
+#[derive(Component)]
+struct Footprint {
+    age: f32,
+    color: Color,
+}
+
+/// Footprints on the ground, oldest first.
+#[derive(Default)]
+struct FootprintTrail(VecDeque<Entity>);
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FootprintSystem {
+    Spawn,
+}
+
+pub struct FootprintPlugin;
+
+impl Plugin for FootprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FootprintTrail>().add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(spawn_footprints.timed().label(FootprintSystem::Spawn))
+                .with_system(fade_footprints.after(FootprintSystem::Spawn)),
+        );
+    }
+}
+
+fn spawn_footprints(
+    mut commands: Commands,
+    mut trail: ResMut<FootprintTrail>,
+    mut frame_events: EventReader<AnimFrameEvent>,
+    mut walker_q: Query<(&mut LeavesFootprints, &GlobalTransform, &OnTerrain)>,
+) {
+    for event in frame_events.iter() {
+        if event.state != AnimState::Walk {
+            continue;
+        }
+        let (mut walker, trans, on_terrain) = match walker_q.get_mut(event.entity) {
+            Ok(walker) => walker,
+            Err(_) => continue,
+        };
+        let color = match on_terrain
+            .modifier
+            .and_then(|terrain| terrain.footprint_color)
+        {
+            Some(color) => color,
+            None => continue,
+        };
+        walker.left = !walker.left;
+        let side = if walker.left {
+            -FOOT_SPACING
+        } else {
+            FOOT_SPACING
+        };
+        let position = trans.translation.xy()
+            + Vec2::new(0., FEET_OFFSET)
+            + event.facing.clockwise().direction() * side;
+
+        if trail.0.len() == MAX_FOOTPRINTS {
+            if let Some(oldest) = trail.0.pop_front() {
+                commands.entity(oldest).despawn();
+            }
+        }
+        let footprint = commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(FOOTPRINT_SIZE),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(
+                    position.extend(RenderLayerGroup::Ground.z() + FOOTPRINT_DEPTH),
+                ),
+                ..Default::default()
+            })
+            .insert(Footprint { age: 0., color })
+            .id();
+        trail.0.push_back(footprint);
+    }
+}
+
+fn fade_footprints(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut trail: ResMut<FootprintTrail>,
+    mut footprint_q: Query<(&mut Footprint, &mut Sprite)>,
+) {
+    let delta = time_scale.delta_seconds(&time);
+    for (mut footprint, mut sprite) in footprint_q.iter_mut() {
+        footprint.age += delta;
+        let mut color = footprint.color;
+        color.set_a(footprint.color.a() * (1. - footprint.age / FOOTPRINT_SECS).max(0.));
+        sprite.color = color;
+    }
+
+    // All footprints fade equally long, so the expired ones are at the front
+    while let Some(&oldest) = trail.0.front() {
+        let expired = footprint_q
+            .get(oldest)
+            .map_or(true, |(footprint, _)| footprint.age >= FOOTPRINT_SECS);
+        if !expired {
+            break;
+        }
+        trail.0.pop_front();
+        commands.entity(oldest).despawn();
+    }
+}
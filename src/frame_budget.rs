@@ -0,0 +1,167 @@
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    ecs::{
+        archetype::{Archetype, ArchetypeComponentId},
+        component::ComponentId,
+        query::Access,
+        system::{IntoSystem, System, SystemId},
+    },
+    prelude::*,
+    utils::HashSet,
+};
+
+/// Budget each timed system gets per frame, before it's reported as slow.
+const DEFAULT_BUDGET: Duration = Duration::from_millis(2);
+
+/// Per-frame time of every `Timed` system, filled in by the systems
+/// themselves and checked against `budget` at the end of the frame.
+pub struct SystemTimings {
+    pub budget: Duration,
+    /// Time of each system in the last frame, slowest first.
+    pub last_frame: Vec<(String, Duration)>,
+    /// Times added up over the current frame, indexed by `Timed::slot`.
+    /// Systems running in several substeps count once per substep.
+    frame: Arc<Mutex<Vec<(String, Duration)>>>,
+    /// Systems that were over budget in the last frame, so a system that
+    /// stays slow is only reported once.
+    over_budget: HashSet<String>,
+}
+
+impl Default for SystemTimings {
+    fn default() -> Self {
+        Self {
+            budget: DEFAULT_BUDGET,
+            last_frame: Vec::new(),
+            frame: Default::default(),
+            over_budget: HashSet::default(),
+        }
+    }
+}
+
+impl SystemTimings {
+    pub fn is_over_budget(&self, time: Duration) -> bool {
+        time > self.budget
+    }
+}
+
+/// Wraps a system to add its run time to `SystemTimings`.
+pub struct Timed<S> {
+    system: S,
+    /// The system's name without its module path.
+    name: String,
+    frame: Arc<Mutex<Vec<(String, Duration)>>>,
+    slot: usize,
+}
+
+pub trait TimedExt<Params>: IntoSystem<(), (), Params> {
+    /// Measures the system for the frame budget watchdog.
+    fn timed(self) -> Timed<Self::System> {
+        let system = IntoSystem::into_system(self);
+        let name = system.name();
+        let name = name.rsplit("::").next().unwrap_or(&name).to_string();
+        Timed {
+            system,
+            name,
+            frame: Default::default(),
+            slot: 0,
+        }
+    }
+}
+
+impl<S, Params> TimedExt<Params> for S where S: IntoSystem<(), (), Params> {}
+
+impl<S: System<In = (), Out = ()>> System for Timed<S> {
+    type In = ();
+    type Out = ();
+
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+
+    fn id(&self) -> SystemId {
+        self.system.id()
+    }
+
+    fn new_archetype(&mut self, archetype: &Archetype) {
+        self.system.new_archetype(archetype);
+    }
+
+    fn component_access(&self) -> &Access<ComponentId> {
+        self.system.component_access()
+    }
+
+    fn archetype_component_access(&self) -> &Access<ArchetypeComponentId> {
+        self.system.archetype_component_access()
+    }
+
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    unsafe fn run_unsafe(&mut self, input: (), world: &World) {
+        let start = Instant::now();
+        self.system.run_unsafe(input, world);
+        let elapsed = start.elapsed();
+        self.frame.lock().unwrap()[self.slot].1 += elapsed;
+    }
+
+    fn apply_buffers(&mut self, world: &mut World) {
+        self.system.apply_buffers(world);
+    }
+
+    /// Takes a slot in `SystemTimings`, adding the resource if this is the
+    /// first timed system.
+    fn initialize(&mut self, world: &mut World) {
+        self.system.initialize(world);
+        let timings = world.get_resource_or_insert_with(SystemTimings::default);
+        self.frame = timings.frame.clone();
+        let mut frame = self.frame.lock().unwrap();
+        self.slot = frame.len();
+        frame.push((self.name.clone(), Duration::ZERO));
+    }
+
+    fn check_change_tick(&mut self, change_tick: u32) {
+        self.system.check_change_tick(change_tick);
+    }
+}
+
+/// Warns about systems that take longer than `SystemTimings::budget` in a
+/// frame, to catch performance regressions while playing. Opt systems in with
+/// `TimedExt::timed`.
+pub struct FrameBudgetPlugin;
+
+impl Plugin for FrameBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SystemTimings>()
+            .add_system_to_stage(CoreStage::Last, check_frame_budget);
+    }
+}
+
+/// Runs after the physics stages, so the frame's times are complete.
+fn check_frame_budget(mut timings: ResMut<SystemTimings>) {
+    let timings = &mut *timings;
+    let mut last_frame = Vec::new();
+    for (name, time) in timings.frame.lock().unwrap().iter_mut() {
+        last_frame.push((name.clone(), std::mem::take(time)));
+    }
+    last_frame.sort_by(|a, b| b.1.cmp(&a.1));
+
+    for (name, time) in last_frame.iter() {
+        if !timings.is_over_budget(*time) {
+            timings.over_budget.remove(name);
+        } else if timings.over_budget.insert(name.clone()) {
+            warn!(
+                "{} took {:.3} ms, over the {:.3} ms frame budget",
+                name,
+                time.as_secs_f64() * 1000.,
+                timings.budget.as_secs_f64() * 1000.,
+            );
+        }
+    }
+    timings.last_frame = last_frame;
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin, StrokeMode},
+    shapes,
+};
+
+use crate::{
+    collision::{AabbBundle, AabbKind, ColliderEnabled, CollisionBehavior},
+    interaction::{InteractEvent, Interactable, InteractionSystem},
+    layer::RenderLayerGroup,
+    phase::{Phase, PhaseExt},
+    AppState,
+};
+
+const GATE_SIZE: Vec2 = Vec2::new(32., 6.);
+/// Reach of the gate's sensor around its collider, so it can be operated from
+/// either side.
+const GATE_REACH: f32 = 16.;
+const GATE_COLOR: Color = Color::rgb(0.55, 0.4, 0.25);
+
+/// A gate or door toggled by interacting with it. Open gates keep their
+/// collider entity but disable it.
+#[derive(Component, Debug, Default)]
+pub struct Openable {
+    pub open: bool,
+}
+
+pub struct GatePlugin;
+
+impl Plugin for GatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(toggle_gates.after(InteractionSystem::Interact)),
+        );
+    }
+}
+
+/// There is no gate sprite yet, an open gate is drawn as its outline.
+fn gate_draw_mode(open: bool) -> DrawMode {
+    if open {
+        DrawMode::Outlined {
+            fill_mode: FillMode::color(Color::NONE),
+            outline_mode: StrokeMode::color(GATE_COLOR),
+        }
+    } else {
+        DrawMode::Fill(FillMode::color(GATE_COLOR))
+    }
+}
+
+pub fn spawn_gate(commands: &mut Commands, position: Vec2) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: GATE_SIZE,
+                origin: RectangleOrigin::Center,
+            },
+            gate_draw_mode(false),
+            Transform::from_translation(RenderLayerGroup::Actors.at(position)),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(AabbBundle::new(
+                    GATE_SIZE,
+                    AabbKind::Collider,
+                    CollisionBehavior::Static,
+                    Color::GREEN,
+                ))
+                .insert(ColliderEnabled::default());
+            parent.spawn_bundle(AabbBundle::new(
+                GATE_SIZE + Vec2::splat(GATE_REACH * 2.),
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::PURPLE,
+            ));
+        })
+        .insert(Openable::default())
+        .insert(Interactable)
+        .insert(RenderLayerGroup::Actors)
+        .id()
+}
+
+fn toggle_gates(
+    mut interact_events: EventReader<InteractEvent>,
+    mut gate_q: Query<(&mut Openable, &mut DrawMode, &Children)>,
+    mut collider_q: Query<&mut ColliderEnabled>,
+) {
+    for event in interact_events.iter() {
+        let (mut openable, mut draw_mode, children) = match gate_q.get_mut(event.target) {
+            Ok(gate) => gate,
+            Err(_) => continue,
+        };
+        openable.open = !openable.open;
+        *draw_mode = gate_draw_mode(openable.open);
+        for child in children.iter() {
+            if let Ok(mut enabled) = collider_q.get_mut(*child) {
+                enabled.0 = !openable.open;
+            }
+        }
+    }
+}
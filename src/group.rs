@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use crate::{
+    collision::CollisionWorld,
+    phase::{Phase, PhaseExt},
+};
+
+/// Names the group a root entity was spawned in by `spawn_group`, e.g. a
+/// room's props or a cutscene's cast.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupTag(pub &'static str);
+
+/// Despawns every entity of the named group, with their children.
+#[derive(Debug, Clone, Copy)]
+pub struct DespawnGroup(pub &'static str);
+
+/// Spawns entities through `spawn` and tags the returned roots with `group`.
+/// Like any other commands, they all show up together once applied.
+pub fn spawn_group(
+    commands: &mut Commands,
+    group: &'static str,
+    spawn: impl FnOnce(&mut Commands) -> Vec<Entity>,
+) -> Vec<Entity> {
+    let entities = spawn(commands);
+    for entity in entities.iter() {
+        commands.entity(*entity).insert(GroupTag(group));
+    }
+    entities
+}
+
+pub struct GroupPlugin;
+
+impl Plugin for GroupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DespawnGroup>().add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Gameplay)
+                .with_system(despawn_groups),
+        );
+    }
+}
+
+fn despawn_groups(
+    mut commands: Commands,
+    mut despawns: EventReader<DespawnGroup>,
+    mut collision_world: ResMut<CollisionWorld>,
+    group_q: Query<(Entity, &GroupTag)>,
+) {
+    for DespawnGroup(group) in despawns.iter() {
+        for (entity, tag) in group_q.iter() {
+            if tag.0 == *group {
+                // Gone from queries right away, instead of once the physics
+                // stage sees the removed AABBs
+                collision_world.remove_owner(entity);
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin},
+    shapes,
+};
+
+use crate::{
+    collision::{AabbBundle, AabbKind, CollisionBehavior, CollisionWorld, IFrames},
+    cooldown::{Cooldown, TimerAppExt},
+    flash::SpriteFlash,
+    layer::RenderLayerGroup,
+    localization::Localization,
+    notify::Notify,
+    origin::WorldOrigin,
+    persist::PersistAppExt,
+    phase::{Phase, PhaseExt},
+    AppState, PlayerTag, PLAYER_START,
+};
+
+const PLAYER_MAX_HEALTH: f32 = 10.;
+const HEALTH_BAR_SIZE: Vec2 = Vec2::new(60., 8.);
+
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+#[reflect(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            current: PLAYER_MAX_HEALTH,
+            max: PLAYER_MAX_HEALTH,
+        }
+    }
+}
+
+/// Ground that hurts the player every `interval` seconds while they stand in
+/// the zone's sensor, starting as they step in unless it hurt them less than
+/// `interval` ago. I-frames, e.g. from a dash, skip the damage of the ticks
+/// they overlap.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Hazard {
+    pub damage: f32,
+    pub interval: f32,
+    pub color: Color,
+}
+
+impl Hazard {
+    pub fn thorns() -> Self {
+        Self {
+            damage: 1.,
+            interval: 1.,
+            color: Color::rgb(0.25, 0.4, 0.15),
+        }
+    }
+
+    pub fn fire() -> Self {
+        Self {
+            damage: 2.,
+            interval: 0.5,
+            color: Color::rgb(0.9, 0.35, 0.1),
+        }
+    }
+}
+
+#[derive(Component)]
+struct HealthBarTag;
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.persist_component::<Health>()
+            .add_cooldown::<Hazard>()
+            .add_startup_system(setup_health_bar)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(apply_hazards),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_health_bar),
+            );
+    }
+}
+
+pub fn spawn_hazard_zone(
+    commands: &mut Commands,
+    position: Vec2,
+    size: Vec2,
+    hazard: Hazard,
+) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: size,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Fill(FillMode::color(hazard.color)),
+            Transform::from_translation(position.extend(RenderLayerGroup::Ground.z())),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                size,
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::RED,
+            ));
+        })
+        .insert(hazard)
+        .insert(Cooldown::<Hazard>::new(hazard.interval))
+        .id()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_hazards(
+    mut commands: Commands,
+    origin: Res<WorldOrigin>,
+    collision_world: Res<CollisionWorld>,
+    localization: Res<Localization>,
+    mut notifications: EventWriter<Notify>,
+    mut hazard_q: Query<(&Hazard, &mut Cooldown<Hazard>)>,
+    mut player_q: Query<(Entity, &mut Health, &mut Transform, Option<&IFrames>), With<PlayerTag>>,
+) {
+    let (player, mut health, mut trans, iframes) = match player_q.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let mut damage = 0.;
+    for (sensor, _) in collision_world
+        .sensor_overlaps
+        .iter()
+        .filter(|(_, other)| *other == player)
+    {
+        let (hazard, mut cooldown) = match hazard_q.get_mut(*sensor) {
+            Ok(hazard) => hazard,
+            Err(_) => continue,
+        };
+        if !cooldown.is_ready() {
+            continue;
+        }
+        cooldown.reset();
+        if iframes.is_none() {
+            damage += hazard.damage;
+        }
+    }
+    if damage == 0. {
+        return;
+    }
+
+    health.current = (health.current - damage).max(0.);
+    commands.entity(player).insert(SpriteFlash::hit());
+    if health.current == 0. {
+        health.current = health.max;
+        trans.translation = origin.to_local(PLAYER_START).extend(trans.translation.z);
+        notifications.send(Notify::new(localization.get("notify.fainted")));
+    }
+}
+
+fn setup_health_bar(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.),
+                    right: Val::Px(20. + HEALTH_BAR_SIZE.x + 8.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(HEALTH_BAR_SIZE.x), Val::Px(HEALTH_BAR_SIZE.y)),
+                ..Default::default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                        ..Default::default()
+                    },
+                    color: Color::RED.into(),
+                    ..Default::default()
+                })
+                .insert(HealthBarTag);
+        });
+}
+
+fn update_health_bar(
+    player_q: Query<&Health, (With<PlayerTag>, Changed<Health>)>,
+    mut bar_q: Query<&mut Style, With<HealthBarTag>>,
+) {
+    let health = match player_q.get_single() {
+        Ok(health) => health,
+        Err(_) => return,
+    };
+    for mut style in bar_q.iter_mut() {
+        style.size.width = Val::Percent(health.current / health.max.max(1.) * 100.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fainting_respawns_at_start_after_origin_shift() {
+        let origin = WorldOrigin(Vec2::new(2048., -1024.));
+        let mut world = World::new();
+        world.insert_resource(origin);
+        world.init_resource::<Localization>();
+        world.init_resource::<Events<Notify>>();
+        let player = world
+            .spawn()
+            .insert(PlayerTag)
+            .insert(Health {
+                current: 1.,
+                max: PLAYER_MAX_HEALTH,
+            })
+            .insert(Transform::from_xyz(300., 200., 5.))
+            .id();
+        let hazard = world
+            .spawn()
+            .insert(Hazard::fire())
+            .insert(Cooldown::<Hazard>::new(Hazard::fire().interval))
+            .id();
+        let mut collision_world = CollisionWorld::default();
+        collision_world.sensor_overlaps.insert((hazard, player));
+        world.insert_resource(collision_world);
+
+        SystemStage::single(apply_hazards).run(&mut world);
+
+        assert_eq!(
+            world.get::<Health>(player).unwrap().current,
+            PLAYER_MAX_HEALTH
+        );
+        let trans = world.get::<Transform>(player).unwrap();
+        assert_eq!(trans.translation, origin.to_local(PLAYER_START).extend(5.));
+    }
+}
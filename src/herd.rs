@@ -0,0 +1,191 @@
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine},
+    collision::Velocity,
+    follow::FollowTarget,
+    group::{spawn_group, DespawnGroup},
+    level::spawn_cow,
+    npc::{still_eating, NpcState},
+    persist::Persist,
+    phase::{Phase, PhaseExt},
+    rng::GameRng,
+    time_scale::TimeScale,
+    weather::Weather,
+    AppState,
+};
+
+static HERD_GROUP: &str = "herd";
+const HERD_SIZE: u32 = 20;
+const HERD_HOME: Vec2 = Vec2::new(160., 40.);
+/// Cows wandering further than this from their herd's home are steered back.
+const HOME_RADIUS: f32 = 96.;
+/// Fraction of `HOME_RADIUS` herds huddle within in heavy rain.
+const RAIN_HOME_RADIUS_SCALE: f32 = 0.4;
+/// Herd members within this distance influence each other.
+const NEIGHBOR_RADIUS: f32 = 64.;
+/// Herd members closer than this push each other apart.
+const SEPARATION_RADIUS: f32 = 36.;
+const SEPARATION_WEIGHT: f32 = 1.5;
+const COHESION_WEIGHT: f32 = 0.3;
+const ALIGNMENT_WEIGHT: f32 = 0.4;
+const WANDER_WEIGHT: f32 = 0.5;
+const HOMING_WEIGHT: f32 = 1.;
+const GRAZE_SPEED: f32 = 20.;
+/// Below this speed a cow plays its idle animation instead of walking.
+const WALK_MIN_SPEED: f32 = 4.;
+
+/// Groups cows that flock together while idle.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Herd {
+    pub id: u32,
+    /// Point the herd stays around.
+    pub home: Vec2,
+}
+
+pub struct HerdPlugin;
+
+impl Plugin for HerdPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_herd)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Ai)
+                    .with_system(flock),
+            )
+            // The herd isn't part of the level, keep it out of the way while editing
+            .add_system_set(SystemSet::on_enter(AppState::Editor).with_system(despawn_herd))
+            .add_system_set(SystemSet::on_exit(AppState::Editor).with_system(spawn_herd));
+    }
+}
+
+fn spawn_herd(mut commands: Commands) {
+    let herd = Herd {
+        id: 0,
+        home: HERD_HOME,
+    };
+    // Sunflower spiral, so the cows start evenly spread without overlapping
+    let golden_angle = std::f32::consts::PI * (3. - 5f32.sqrt());
+    spawn_group(&mut commands, HERD_GROUP, |commands| {
+        (0..HERD_SIZE)
+            .map(|i| {
+                let angle = i as f32 * golden_angle;
+                let radius = SEPARATION_RADIUS * (i as f32).sqrt();
+                let position = herd.home + Vec2::new(angle.cos(), angle.sin()) * radius;
+                let cow = spawn_cow(commands, position);
+                commands
+                    .entity(cow)
+                    .insert(herd)
+                    .insert(Persist::new(format!("herd_{}", i)));
+                cow
+            })
+            .collect()
+    });
+}
+
+fn despawn_herd(mut despawns: EventWriter<DespawnGroup>) {
+    despawns.send(DespawnGroup(HERD_GROUP));
+}
+
+/// Boids steering for idle herd members: separation, cohesion and alignment
+/// with the rest of the herd, plus some wandering around the herd's home.
+///
+/// Cows alternate between walking and grazing in place. Grazing cows still
+/// apply separation, and overlaps left over are resolved by the physics stage.
+#[allow(clippy::too_many_arguments)]
+fn flock(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    weather: Res<Weather>,
+    mut rng: ResMut<GameRng>,
+    // Game time, so replays flock the same
+    mut secs: Local<f32>,
+    // Random offsets of each cow's grazing and wandering cycles
+    mut phases: Local<HashMap<Entity, Vec2>>,
+    mut cow_q: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Herd,
+            &NpcState,
+            &mut Velocity,
+            &mut AnimStateMachine,
+        ),
+        Without<FollowTarget>,
+    >,
+) {
+    let boids: Vec<(Entity, Vec2, u32, Vec2)> = cow_q
+        .iter()
+        .map(|(entity, trans, herd, _, velocity, _)| {
+            (entity, trans.translation.xy(), herd.id, velocity.0)
+        })
+        .collect();
+    *secs += time_scale.delta_seconds(&time);
+    let secs = *secs;
+    phases.retain(|entity, _| boids.iter().any(|(boid, ..)| boid == entity));
+    // Herds huddle up closer to home in the rain
+    let home_radius = HOME_RADIUS * (1. - (1. - RAIN_HOME_RADIUS_SCALE) * weather.rain());
+
+    for (entity, trans, herd, state, mut velocity, mut machine) in cow_q.iter_mut() {
+        if *state != NpcState::Idle {
+            continue;
+        }
+        if still_eating(&mut machine, &mut velocity) {
+            continue;
+        }
+
+        let position = trans.translation.xy();
+        let mut separation = Vec2::ZERO;
+        let mut center = Vec2::ZERO;
+        let mut heading = Vec2::ZERO;
+        let mut neighbors = 0;
+        for (other, other_pos, other_herd, other_vel) in boids.iter() {
+            if *other == entity || *other_herd != herd.id {
+                continue;
+            }
+            let offset = position - *other_pos;
+            let distance = offset.length();
+            if distance > NEIGHBOR_RADIUS {
+                continue;
+            }
+            if distance < SEPARATION_RADIUS {
+                separation +=
+                    offset.normalize_or_zero() * (SEPARATION_RADIUS - distance) / SEPARATION_RADIUS;
+            }
+            center += *other_pos;
+            heading += *other_vel;
+            neighbors += 1;
+        }
+
+        let mut steering = separation * SEPARATION_WEIGHT;
+        // Offset each cow's cycles so the herd doesn't move in lockstep
+        let offsets = *phases.entry(entity).or_insert_with(|| {
+            let rng = rng.stream("herd");
+            Vec2::new(rng.next(), rng.next()) * std::f32::consts::TAU
+        });
+        let phase = secs * 0.2 + offsets.x;
+        let grazing = phase.sin() > 0.3;
+        if !grazing {
+            let wander = secs * 0.5 + offsets.y;
+            steering += Vec2::new(wander.cos(), wander.sin()) * WANDER_WEIGHT;
+            if neighbors > 0 {
+                let neighbors = neighbors as f32;
+                steering += (center / neighbors - position).normalize_or_zero() * COHESION_WEIGHT;
+                steering += (heading / neighbors).normalize_or_zero() * ALIGNMENT_WEIGHT;
+            }
+            let to_home = herd.home - position;
+            if to_home.length() > home_radius {
+                steering += to_home.normalize_or_zero() * HOMING_WEIGHT;
+            }
+        }
+        velocity.0 = steering.clamp_length_max(1.) * GRAZE_SPEED;
+
+        // Stopping keeps the facing
+        if velocity.0.length() > WALK_MIN_SPEED {
+            machine.face(velocity.0);
+            machine.set_state(AnimState::Walk);
+        } else {
+            machine.set_state(AnimState::Idle);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use crate::phase::{Phase, PhaseExt};
+use bevy_spicy_aseprite::{AsepriteAnimation, AsepriteTag};
+
+use crate::collision::{AabbBundle, AabbKind, CollisionBehavior, RigidBodyRoot, SensorEvent};
+
+#[derive(Debug, Clone, Copy)]
+pub struct HitboxShape {
+    /// Center relative to the attacker's origin.
+    pub offset: Vec2,
+    /// Full size, like `Aabb::extents`.
+    pub extents: Vec2,
+}
+
+/// Hitboxes active while `tag` shows sprite sheet frame `frame`.
+#[derive(Debug, Clone)]
+pub struct HitboxFrame {
+    pub tag: AsepriteTag,
+    pub frame: usize,
+    pub shapes: Vec<HitboxShape>,
+}
+
+/// Per-frame hitbox data of an animated attacker. While the current tag and
+/// frame have an entry, matching sensor AABBs exist as children of the
+/// attacker; they're despawned as soon as the frame changes.
+#[derive(Component, Debug, Clone, Default)]
+pub struct HitboxFrames(pub Vec<HitboxFrame>);
+
+impl HitboxFrames {
+    fn shapes(&self, tag: AsepriteTag, frame: usize) -> &[HitboxShape] {
+        self.0
+            .iter()
+            .find(|entry| entry.tag == tag && entry.frame == frame)
+            .map_or(&[][..], |entry| entry.shapes.as_slice())
+    }
+}
+
+/// Root of a temporary hitbox. Owns the sensor AABB, so sensor events tell
+/// hitboxes apart from the attacker's other sensors.
+#[derive(Component, Debug)]
+pub struct Hitbox {
+    pub attacker: Entity,
+}
+
+/// Sent when an active hitbox of `attacker` touches a collider of `target`.
+#[derive(Debug, Clone, Copy)]
+pub struct HitEvent {
+    pub attacker: Entity,
+    pub target: Entity,
+}
+
+pub struct HitboxPlugin;
+
+impl Plugin for HitboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HitEvent>().add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Gameplay)
+                .with_system(update_hitboxes)
+                .with_system(emit_hits),
+        );
+    }
+}
+
+fn update_hitboxes(
+    mut commands: Commands,
+    attacker_q: Query<
+        (
+            Entity,
+            &HitboxFrames,
+            &AsepriteAnimation,
+            &TextureAtlasSprite,
+            Option<&Children>,
+        ),
+        Changed<TextureAtlasSprite>,
+    >,
+    hitbox_q: Query<(), With<Hitbox>>,
+) {
+    for (attacker, frames, anim, sprite, children) in attacker_q.iter() {
+        for child in children.iter().flat_map(|children| children.iter()) {
+            if hitbox_q.get(*child).is_ok() {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+
+        let tag = match *anim {
+            AsepriteAnimation::Tag { tag } => tag,
+            _ => continue,
+        };
+        for shape in frames.shapes(tag, sprite.index) {
+            // Flipped sprites swing the other way
+            let mut offset = shape.offset;
+            if sprite.flip_x {
+                offset.x = -offset.x;
+            }
+            let hitbox = commands
+                .spawn_bundle((
+                    Transform::from_translation(offset.extend(0.)),
+                    GlobalTransform::default(),
+                    Hitbox { attacker },
+                    // Owns its sensor, even under an attacker that's a root
+                    RigidBodyRoot,
+                ))
+                .with_children(|parent| {
+                    parent.spawn_bundle(AabbBundle::new(
+                        shape.extents,
+                        AabbKind::Sensor,
+                        CollisionBehavior::None,
+                        Color::RED,
+                    ));
+                })
+                .id();
+            commands.entity(attacker).push_children(&[hitbox]);
+        }
+    }
+}
+
+fn emit_hits(
+    mut sensor_events: EventReader<SensorEvent>,
+    mut hits: EventWriter<HitEvent>,
+    hitbox_q: Query<&Hitbox>,
+) {
+    for event in sensor_events.iter() {
+        if let SensorEvent::Entered { sensor, other } = *event {
+            if let Ok(hitbox) = hitbox_q.get(sensor) {
+                if other != hitbox.attacker {
+                    hits.send(HitEvent {
+                        attacker: hitbox.attacker,
+                        target: other,
+                    });
+                }
+            }
+        }
+    }
+}
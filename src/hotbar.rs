@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+
+use crate::{
+    inventory::Inventory,
+    localization::{Localization, LocalizationSystem},
+    phase::{Phase, PhaseExt},
+    AppState,
+};
+
+/// Number keys select the hotbar slot with the same number.
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// Inventory items in slot order, with the selected one used by
+/// `Action::UseItem`.
+#[derive(Default)]
+pub struct Hotbar {
+    pub selected: usize,
+}
+
+impl Hotbar {
+    /// Items are laid out alphabetically, so slots stay put as counts change.
+    pub fn items(inventory: &Inventory) -> Vec<(&str, u32)> {
+        let mut items: Vec<_> = inventory
+            .items
+            .iter()
+            .map(|(item, count)| (item.as_str(), *count))
+            .collect();
+        items.sort_unstable();
+        items.truncate(SLOT_KEYS.len());
+        items
+    }
+
+    pub fn selected_item<'a>(&self, inventory: &'a Inventory) -> Option<&'a str> {
+        Self::items(inventory)
+            .get(self.selected)
+            .map(|(item, _)| *item)
+    }
+}
+
+#[derive(Component)]
+struct HotbarTextTag;
+
+pub struct HotbarPlugin;
+
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hotbar>()
+            .add_startup_system(setup_hotbar_text)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Input)
+                    .with_system(select_slot),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_hotbar_text.after(LocalizationSystem::SwitchLanguage)),
+            );
+    }
+}
+
+fn setup_hotbar_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.),
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 20.,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(HotbarTextTag);
+}
+
+fn select_slot(keys: Res<Input<KeyCode>>, mut hotbar: ResMut<Hotbar>) {
+    if let Some(slot) = SLOT_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+        hotbar.selected = slot;
+    }
+}
+
+fn update_hotbar_text(
+    hotbar: Res<Hotbar>,
+    inventory: Res<Inventory>,
+    localization: Res<Localization>,
+    mut text_q: Query<&mut Text, With<HotbarTextTag>>,
+) {
+    if !hotbar.is_changed() && !inventory.is_changed() && !localization.is_changed() {
+        return;
+    }
+
+    let value = Hotbar::items(&inventory)
+        .iter()
+        .enumerate()
+        .map(|(i, (item, count))| {
+            let slot = format!("{} {} x{}", i + 1, localization.get(item), count);
+            if i == hotbar.selected {
+                format!("[{}]", slot)
+            } else {
+                format!(" {} ", slot)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("  ");
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
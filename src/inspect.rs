@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_spicy_aseprite::AsepriteAnimation;
+
+use crate::{
+    camera::{cursor_world_position, PixelCameraTag},
+    collision::{CollisionWorld, QueryMask},
+    phase::{Phase, PhaseExt},
+    DebugRender,
+};
+
+#[derive(Component)]
+struct InspectTextTag;
+
+pub struct InspectPlugin;
+
+impl Plugin for InspectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_inspect_text).add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Input)
+                .with_system(inspect_on_click),
+        );
+    }
+}
+
+fn setup_inspect_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.),
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 20.,
+                    color: Color::YELLOW,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(InspectTextTag);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn inspect_on_click(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    debug_render: Res<DebugRender>,
+    collision_world: Res<CollisionWorld>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelCameraTag>>,
+    inspected_q: Query<(&GlobalTransform, Option<&Name>, Option<&AsepriteAnimation>)>,
+    mut text_q: Query<&mut Text, With<InspectTextTag>>,
+) {
+    let mut text = text_q.single_mut();
+    if !debug_render.0 {
+        text.sections[0].value.clear();
+        return;
+    }
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let (camera, camera_trans) = camera_q.single();
+    let cursor = match windows
+        .get_primary()
+        .and_then(|window| cursor_world_position(window, camera, camera_trans))
+    {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    let picked = collision_world.query_point(cursor, QueryMask::ALL);
+    let entity = match picked.first() {
+        Some(entity) => *entity,
+        None => {
+            text.sections[0].value.clear();
+            return;
+        }
+    };
+
+    let behaviors: Vec<String> = collision_world
+        .aabbs
+        .values()
+        .filter(|(owner, _)| *owner == entity)
+        .map(|(_, aabb)| format!("{:?} {:?}", aabb.aabb_kind, aabb.collision_behavior))
+        .collect();
+    let (trans, name, anim) = match inspected_q.get(entity) {
+        Ok(inspected) => inspected,
+        Err(_) => return,
+    };
+    let info = format!(
+        "{:?} {}\nposition: {:.1}, {:.1}\naabbs: {}\nanimation: {}",
+        entity,
+        name.map_or("", |name| name.as_str()),
+        trans.translation.x,
+        trans.translation.y,
+        behaviors.join(", "),
+        anim.map_or(String::from("-"), |anim| format!("{:?}", anim)),
+    );
+    info!("Inspected {}", info);
+    text.sections[0].value = info;
+}
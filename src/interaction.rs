@@ -0,0 +1,231 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin, StrokeMode},
+    shapes,
+};
+
+use crate::{
+    anim::AnimStateMachine,
+    collision::{Aabb, AabbKind, Overlapping},
+    frame_budget::TimedExt,
+    phase::{Phase, PhaseExt},
+    settings::{Action, ActionBuffer, SettingsSystem},
+    AppState, PlayerTag,
+};
+
+/// Marks entities the player can interact with by pressing the interact key
+/// while their collider is inside the player's sensor, or the player's
+/// collider is inside their sensor.
+#[derive(Component)]
+pub struct Interactable;
+
+const HIGHLIGHT_COLOR: Color = Color::rgba(1., 1., 0.7, 0.8);
+/// Gap between an entity's colliders and its highlight outline.
+const HIGHLIGHT_MARGIN: f32 = 3.;
+/// Size of the highlight for entities without colliders.
+const HIGHLIGHT_DEFAULT_SIZE: f32 = 16.;
+const HIGHLIGHT_PULSE_SPEED: f32 = 5.;
+/// How much the highlight grows and shrinks while pulsing, relative to its size.
+const HIGHLIGHT_PULSE_SCALE: f32 = 0.06;
+/// Above the entity and its debug shapes.
+const HIGHLIGHT_Z: f32 = 0.5;
+/// Switches the interact target between everything in reach.
+const CYCLE_TARGET_KEY: KeyCode = KeyCode::Tab;
+
+#[derive(Debug, Clone, Copy)]
+pub struct InteractEvent {
+    pub target: Entity,
+}
+
+/// What pressing the interact key would interact with right now, if anything.
+///
+/// With several interactables in reach, the closest one in front of the
+/// player is picked, unless another one was cycled to with `CYCLE_TARGET_KEY`
+/// and is still in reach.
+#[derive(Debug, Default)]
+pub struct InteractTarget(pub Option<Entity>);
+
+/// Outline pulsing around the `InteractTarget`, as a child of it.
+#[derive(Component)]
+struct InteractHighlight;
+
+/// Labels of the interaction systems, e.g. to react to this frame's
+/// `InteractEvent`s after `Interact`.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InteractionSystem {
+    Target,
+    Interact,
+    Highlight,
+}
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InteractEvent>()
+            .init_resource::<InteractTarget>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Input)
+                    .with_system(
+                        update_interact_target
+                            .timed()
+                            .label(InteractionSystem::Target),
+                    )
+                    .with_system(
+                        interact
+                            .timed()
+                            .label(InteractionSystem::Interact)
+                            .after(InteractionSystem::Target)
+                            .after(SettingsSystem::BufferActions),
+                    ),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(
+                        highlight_interact_target
+                            .timed()
+                            .label(InteractionSystem::Highlight),
+                    )
+                    .with_system(pulse_highlights.after(InteractionSystem::Highlight)),
+            );
+    }
+}
+
+fn update_interact_target(
+    keys: Res<Input<KeyCode>>,
+    mut interact_target: ResMut<InteractTarget>,
+    mut cycled: Local<Option<Entity>>,
+    player_q: Query<(Entity, &GlobalTransform, &AnimStateMachine), With<PlayerTag>>,
+    overlapping_q: Query<(Entity, &Overlapping)>,
+    interactable_q: Query<&GlobalTransform, With<Interactable>>,
+) {
+    let (player, player_trans, anim) = player_q.single();
+    let mut candidates: Vec<Entity> = Vec::new();
+    for (sensor, overlapping) in overlapping_q.iter() {
+        if sensor == player {
+            candidates.extend(overlapping.0.iter().copied());
+        } else if overlapping.contains(player) {
+            candidates.push(sensor);
+        }
+    }
+
+    // Closest first, with everything in front of the player before anything
+    // behind them
+    let facing = anim.facing().direction();
+    let mut ranked: Vec<(bool, f32, Entity)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let offset = interactable_q.get(candidate).ok()?.translation.xy()
+                - player_trans.translation.xy();
+            Some((facing.dot(offset) < 0., offset.length(), candidate))
+        })
+        .collect();
+    ranked.sort_by(|a, b| (a.0, a.1).partial_cmp(&(b.0, b.1)).unwrap());
+    ranked.dedup_by_key(|(_, _, candidate)| *candidate);
+    let ranked: Vec<Entity> = ranked.into_iter().map(|(_, _, target)| target).collect();
+
+    if cycled.map_or(false, |target| !ranked.contains(&target)) {
+        *cycled = None;
+    }
+    if keys.just_pressed(CYCLE_TARGET_KEY) && ranked.len() > 1 {
+        let current = interact_target
+            .0
+            .and_then(|target| ranked.iter().position(|ranked| *ranked == target));
+        let next = current.map_or(0, |index| (index + 1) % ranked.len());
+        *cycled = Some(ranked[next]);
+    }
+    interact_target.0 = cycled.or_else(|| ranked.first().copied());
+}
+
+/// A buffered press also interacts with a target coming into reach shortly
+/// after.
+fn interact(
+    mut buffer: ResMut<ActionBuffer>,
+    interact_target: Res<InteractTarget>,
+    mut interact_events: EventWriter<InteractEvent>,
+) {
+    if let Some(target) = interact_target.0 {
+        if buffer.consume(Action::Interact) {
+            interact_events.send(InteractEvent { target });
+        }
+    }
+}
+
+/// Outlines the interact target's colliders, cleared once it's out of reach
+/// or the game isn't being played, e.g. during dialogue.
+fn highlight_interact_target(
+    mut commands: Commands,
+    state: Res<State<AppState>>,
+    interact_target: Res<InteractTarget>,
+    highlight_q: Query<Entity, With<InteractHighlight>>,
+    children_q: Query<&Children>,
+    aabb_q: Query<(&Aabb, &AabbKind)>,
+    mut shown: Local<Option<Entity>>,
+) {
+    let target = interact_target
+        .0
+        .filter(|_| *state.current() == AppState::Playing);
+    if *shown == target {
+        return;
+    }
+    *shown = target;
+    for highlight in highlight_q.iter() {
+        commands.entity(highlight).despawn_recursive();
+    }
+    let target = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    // Bounds of the target's colliders, relative to the target
+    let mut bounds: Option<(Vec2, Vec2)> = None;
+    let colliders = children_q
+        .get(target)
+        .iter()
+        .flat_map(|children| children.iter())
+        .filter_map(|child| aabb_q.get(*child).ok())
+        .filter(|(_, kind)| **kind == AabbKind::Collider);
+    for (aabb, _) in colliders {
+        let (min, max) = (aabb.offset - aabb.extents(), aabb.offset + aabb.extents());
+        bounds = Some(match bounds {
+            Some((bounds_min, bounds_max)) => (bounds_min.min(min), bounds_max.max(max)),
+            None => (min, max),
+        });
+    }
+    // Entities without colliders, like plants, get a box around their origin
+    let (min, max) = bounds.unwrap_or((
+        Vec2::splat(-HIGHLIGHT_DEFAULT_SIZE / 2.),
+        Vec2::splat(HIGHLIGHT_DEFAULT_SIZE / 2.),
+    ));
+    let center = (min + max) / 2.;
+    let highlight = commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: max - min + Vec2::splat(HIGHLIGHT_MARGIN * 2.),
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::NONE),
+                outline_mode: StrokeMode::color(HIGHLIGHT_COLOR),
+            },
+            Transform::from_translation(center.extend(HIGHLIGHT_Z)),
+        ))
+        .insert(InteractHighlight)
+        .id();
+    commands.entity(target).push_children(&[highlight]);
+}
+
+/// Pulses by scaling, as changing the outline's color would tessellate it again.
+fn pulse_highlights(
+    time: Res<Time>,
+    mut highlight_q: Query<&mut Transform, With<InteractHighlight>>,
+) {
+    let pulse = 1.
+        + HIGHLIGHT_PULSE_SCALE
+            * (time.seconds_since_startup() as f32 * HIGHLIGHT_PULSE_SPEED).sin();
+    for mut trans in highlight_q.iter_mut() {
+        trans.scale = Vec3::new(pulse, pulse, 1.);
+    }
+}
@@ -0,0 +1,30 @@
+use bevy::utils::HashMap;
+
+#[derive(Default, Debug)]
+pub struct Inventory {
+    pub items: HashMap<String, u32>,
+}
+
+impl Inventory {
+    pub fn add(&mut self, item: &str, count: u32) {
+        *self.items.entry(item.to_string()).or_insert(0) += count;
+    }
+
+    pub fn count(&self, item: &str) -> u32 {
+        self.items.get(item).copied().unwrap_or(0)
+    }
+
+    /// Removes `count` of `item`, or nothing at all if there aren't enough.
+    pub fn take(&mut self, item: &str, count: u32) -> bool {
+        match self.items.get_mut(item) {
+            Some(held) if *held >= count => {
+                *held -= count;
+                if *held == 0 {
+                    self.items.remove(item);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+/// Depth of each band of z values.
+const BAND_DEPTH: f32 = 100.;
+/// Depth per unit of y when y-sorting, so a band fits 9000 units of y.
+const Y_SORT_SCALE: f32 = 0.01;
+/// Left free at either end of a band for children's local z offsets, like
+/// shadows and emotes.
+const BAND_MARGIN: f32 = 5.;
+
+/// Bands of z values sprites are drawn in, back to front, so e.g. tree tops
+/// always cover actors however they are y-sorted.
+///
+/// Entities with this component are y-sorted within their band, lower ones
+/// drawn in front. Children in the same band as their parent keep their local
+/// z, e.g. riders and shadows. Anything drawn flat, like terrain, is placed in
+/// its band's z range when spawned instead, see `z`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderLayerGroup {
+    /// Terrain covering the ground.
+    Background,
+    /// Markings on the ground, like footprints and hazard zones.
+    Ground,
+    /// Characters and props.
+    Actors,
+    /// Tree tops, roofs and anything else above the actors.
+    Overhead,
+    /// World space overlays, like floating text and particles.
+    UiWorld,
+}
+
+impl RenderLayerGroup {
+    /// Lowest z of the band. All bands are in front of the 2D camera's near
+    /// plane.
+    pub fn z(self) -> f32 {
+        let band = match self {
+            RenderLayerGroup::Background => 0.,
+            RenderLayerGroup::Ground => 1.,
+            RenderLayerGroup::Actors => 2.,
+            RenderLayerGroup::Overhead => 3.,
+            RenderLayerGroup::UiWorld => 4.,
+        };
+        band * BAND_DEPTH
+    }
+
+    /// Y-sorted z of something at height `y`.
+    fn sorted_z(self, y: f32) -> f32 {
+        let half = BAND_DEPTH / 2. - BAND_MARGIN;
+        self.z() + BAND_DEPTH / 2. - (y * Y_SORT_SCALE).clamp(-half, half)
+    }
+
+    /// `position` y-sorted into the band, for spawning.
+    pub fn at(self, position: Vec2) -> Vec3 {
+        position.extend(self.sorted_z(position.y))
+    }
+}
+
+pub struct LayerPlugin;
+
+impl Plugin for LayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            y_sort.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Keeps `RenderLayerGroup` entities y-sorted in their band. Children are
+/// placed by their parent's last propagated transform, which only lags a
+/// frame behind for moving parents.
+fn y_sort(
+    mut sorted_q: Query<(&RenderLayerGroup, &mut Transform, Option<&Parent>)>,
+    parent_q: Query<(&GlobalTransform, Option<&RenderLayerGroup>)>,
+) {
+    for (group, mut trans, parent) in sorted_q.iter_mut() {
+        let z = match parent.map(|parent| parent_q.get(parent.0)) {
+            None => group.sorted_z(trans.translation.y),
+            Some(Ok((_, Some(parent_group)))) if parent_group == group => continue,
+            Some(Ok((parent_trans, _))) => {
+                let y = parent_trans.translation.y + trans.translation.y;
+                group.sorted_z(y) - parent_trans.translation.z
+            }
+            Some(Err(_)) => continue,
+        };
+        if trans.translation.z != z {
+            trans.translation.z = z;
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin},
+    shapes,
+};
+use bevy_spicy_aseprite::{AsepriteAnimation, AsepriteBundle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anim::AnimStateMachine,
+    avoidance::CrowdAgent,
+    bark::{Barks, BarksBundle},
+    chunk::WorldChunks,
+    collision::{
+        AabbBundle, AabbKind, CharacterController, CollisionBehavior, InCollisionWorld, Mass,
+        Velocity,
+    },
+    debug_label::DebugLabel,
+    gate::spawn_gate,
+    interaction::Interactable,
+    layer::RenderLayerGroup,
+    mods,
+    npc::{NpcState, Relationship},
+    patrol::PatrolPath,
+    persist::Persist,
+    plant::spawn_plant,
+    sprites,
+    vision::VisionCone,
+    CowTag,
+};
+
+static LEVEL_FILE: &str = "level.ron";
+/// Where the editor exports to. Mods overriding the level aren't written to.
+static LEVEL_PATH: &str = "assets/level.ron";
+
+const WALL_COLOR: Color = Color::rgb(0.45, 0.38, 0.3);
+const ROCK_COLOR: Color = Color::GRAY;
+const ROCK_RADIUS: f32 = 8.;
+const CRATE_COLOR: Color = Color::rgb(0.6, 0.42, 0.22);
+const CRATE_SIZE: f32 = 20.;
+/// Relative to the player's mass of 1, so pushing a crate is slow going.
+const CRATE_MASS: f32 = 3.;
+/// Also what NPC pathfinding and line of sight checks assume NPCs take up.
+pub const COW_COLLIDER_SIZE: Vec2 = Vec2::new(32., 32.);
+const TRUNK_COLOR: Color = Color::rgb(0.4, 0.26, 0.13);
+const TRUNK_SIZE: Vec2 = Vec2::new(8., 14.);
+const CANOPY_COLOR: Color = Color::rgb(0.2, 0.5, 0.22);
+const CANOPY_RADIUS: f32 = 22.;
+/// From the trunk's center up to the canopy's.
+const CANOPY_OFFSET: f32 = 24.;
+const COW_VISION_RADIUS: f32 = 96.;
+const COW_VISION_HALF_ANGLE: f32 = 0.6;
+/// A bit over half a cow's collider, so passing cows keep some room.
+const COW_AVOID_RADIUS: f32 = 20.;
+
+/// Crop plants placed from the editor grow.
+static PLANT_CROP: &str = "Hay";
+
+/// Entities that can be placed from the editor palette. Also marks placed
+/// entities so they are written back out on export.
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefab {
+    Cow,
+    Rock,
+    Gate,
+    Plant,
+    Crate,
+    Tree,
+}
+
+impl Prefab {
+    pub const ALL: [Prefab; 6] = [
+        Prefab::Cow,
+        Prefab::Rock,
+        Prefab::Gate,
+        Prefab::Plant,
+        Prefab::Crate,
+        Prefab::Tree,
+    ];
+}
+
+/// A static rectangular collider placed in the level.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Wall {
+    /// Full size, like `Aabb::extents`.
+    pub size: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WallLayout {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrefabLayout {
+    pub prefab: Prefab,
+    pub position: [f32; 2],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patrol: Option<PatrolPath>,
+    /// Collision world to put the prefab in, see `InCollisionWorld`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world: Option<String>,
+}
+
+/// On-disk format of `assets/level.ron`, or a mod's override of it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Level {
+    pub walls: Vec<WallLayout>,
+    pub prefabs: Vec<PrefabLayout>,
+}
+
+impl Level {
+    /// Loads the level file, falling back to an empty level if it is missing
+    /// or malformed.
+    pub fn load() -> Self {
+        mods::load_ron(LEVEL_FILE).unwrap_or_else(|err| {
+            warn!("Empty level, {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|source| std::fs::write(LEVEL_PATH, source).map_err(|err| err.to_string()));
+        match result {
+            Ok(()) => info!("Exported level to {}", LEVEL_PATH),
+            Err(err) => warn!("Failed to export level to {}: {}", LEVEL_PATH, err),
+        }
+    }
+}
+
+pub fn spawn_wall(commands: &mut Commands, position: Vec2, size: Vec2) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: size,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Fill(FillMode::color(WALL_COLOR)),
+            Transform::from_translation(RenderLayerGroup::Actors.at(position)),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                size,
+                AabbKind::Collider,
+                CollisionBehavior::Static,
+                Color::GREEN,
+            ));
+        })
+        .insert(Wall { size })
+        .insert(RenderLayerGroup::Actors)
+        .id()
+}
+
+pub fn spawn_cow(commands: &mut Commands, position: Vec2) -> Entity {
+    commands
+        .spawn_bundle(AsepriteBundle {
+            aseprite: sprites::Cow::sprite(),
+            animation: AsepriteAnimation::from(sprites::Cow::tags::SOUTH_IDLE),
+            transform: Transform::from_translation(RenderLayerGroup::Actors.at(position)),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                COW_COLLIDER_SIZE,
+                AabbKind::Collider,
+                CollisionBehavior::Npc,
+                Color::GREEN,
+            ));
+            parent.spawn_bundle(AabbBundle::new(
+                Vec2::new(46., 46.),
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::PURPLE,
+            ));
+        })
+        .insert(CowTag)
+        .insert(RenderLayerGroup::Actors)
+        .insert(AnimStateMachine::cow())
+        .insert(VisionCone::new(COW_VISION_RADIUS, COW_VISION_HALF_ANGLE))
+        .insert(Name::new("Cow"))
+        .insert(Interactable)
+        .insert(NpcState::Idle)
+        .insert(Relationship::default())
+        .insert(Velocity::default())
+        .insert(CharacterController::default())
+        .insert(CrowdAgent {
+            radius: COW_AVOID_RADIUS,
+        })
+        .insert_bundle(BarksBundle::new(Barks::cow()))
+        .insert(DebugLabel::default())
+        .id()
+}
+
+/// A trunk the player walks behind or in front of, with a canopy drawn over
+/// any actors under it.
+pub fn spawn_tree(commands: &mut Commands, position: Vec2) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: TRUNK_SIZE,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Fill(FillMode::color(TRUNK_COLOR)),
+            Transform::from_translation(RenderLayerGroup::Actors.at(position)),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                TRUNK_SIZE,
+                AabbKind::Collider,
+                CollisionBehavior::Static,
+                Color::GREEN,
+            ));
+            parent
+                .spawn_bundle(GeometryBuilder::build_as(
+                    &shapes::Circle {
+                        radius: CANOPY_RADIUS,
+                        center: Vec2::ZERO,
+                    },
+                    DrawMode::Fill(FillMode::color(CANOPY_COLOR)),
+                    Transform::from_xyz(0., CANOPY_OFFSET, 0.),
+                ))
+                .insert(RenderLayerGroup::Overhead);
+        })
+        .insert(RenderLayerGroup::Actors)
+        .id()
+}
+
+pub fn spawn_prefab(commands: &mut Commands, prefab: Prefab, position: Vec2) -> Entity {
+    let entity = match prefab {
+        Prefab::Cow => spawn_cow(commands, position),
+        Prefab::Rock => commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Circle {
+                    radius: ROCK_RADIUS,
+                    center: Vec2::ZERO,
+                },
+                DrawMode::Fill(FillMode::color(ROCK_COLOR)),
+                Transform::from_translation(RenderLayerGroup::Actors.at(position)),
+            ))
+            .with_children(|parent| {
+                // A wide middle box with narrower ones above and below follows
+                // the circle closer than a single square, so things slide
+                // around rocks instead of catching on their corners
+                let boxes = [
+                    (Vec2::new(2., 1.), Vec2::ZERO),
+                    (Vec2::new(1.4, 0.5), Vec2::new(0., 0.75)),
+                    (Vec2::new(1.4, 0.5), Vec2::new(0., -0.75)),
+                ];
+                for (extents, offset) in boxes {
+                    parent.spawn_bundle(AabbBundle::with_offset(
+                        extents * ROCK_RADIUS,
+                        offset * ROCK_RADIUS,
+                        AabbKind::Collider,
+                        CollisionBehavior::Static,
+                        Color::GREEN,
+                    ));
+                }
+            })
+            .insert(RenderLayerGroup::Actors)
+            .id(),
+        Prefab::Gate => spawn_gate(commands, position),
+        Prefab::Plant => spawn_plant(commands, position, PLANT_CROP),
+        Prefab::Crate => commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Rectangle {
+                    extents: Vec2::splat(CRATE_SIZE),
+                    origin: RectangleOrigin::Center,
+                },
+                DrawMode::Fill(FillMode::color(CRATE_COLOR)),
+                Transform::from_translation(RenderLayerGroup::Actors.at(position)),
+            ))
+            .with_children(|parent| {
+                parent.spawn_bundle(AabbBundle::new(
+                    Vec2::splat(CRATE_SIZE),
+                    AabbKind::Collider,
+                    CollisionBehavior::Movable,
+                    Color::GREEN,
+                ));
+            })
+            .insert(Mass(CRATE_MASS))
+            .insert(RenderLayerGroup::Actors)
+            .id(),
+        Prefab::Tree => spawn_tree(commands, position),
+    };
+    commands.entity(entity).insert(prefab);
+    entity
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_level);
+    }
+}
+
+/// Walls are spawned by `chunk::stream_chunks` once the player is near them.
+fn load_level(mut commands: Commands) {
+    let level = Level::load();
+    commands.insert_resource(WorldChunks::from_walls(&level.walls));
+    for (i, placed) in level.prefabs.iter().enumerate() {
+        let entity = spawn_prefab(&mut commands, placed.prefab, placed.position.into());
+        commands
+            .entity(entity)
+            .insert(Persist::new(format!("level_{}", i)));
+        if let Some(patrol) = &placed.patrol {
+            commands.entity(entity).insert(patrol.clone());
+        }
+        if let Some(world) = &placed.world {
+            commands
+                .entity(entity)
+                .insert(InCollisionWorld(world.clone()));
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    phase::{Phase, PhaseExt},
+    time_scale::TimeScale,
+};
+
+/// How much longer an entity sticks around, in game time. Once it's up,
+/// `expire_lifetimes` sends `OnExpire` and despawns the entity with its
+/// children, unless it was made with `keep`.
+#[derive(Component, Debug, Clone)]
+pub struct Lifetime {
+    timer: Timer,
+    despawn: bool,
+}
+
+impl Lifetime {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            timer: Timer::new(duration, false),
+            despawn: true,
+        }
+    }
+
+    pub fn from_seconds(secs: f32) -> Self {
+        Self::new(Duration::from_secs_f32(secs))
+    }
+
+    /// Leaves the entity be once the lifetime is up, for `OnExpire` handlers
+    /// to deal with it, e.g. by releasing it to its `EntityPool`.
+    pub fn keep(self) -> Self {
+        Self {
+            despawn: false,
+            ..self
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.timer.finished()
+    }
+
+    /// How much of the lifetime has passed, in `0..=1`.
+    pub fn percent(&self) -> f32 {
+        self.timer.percent()
+    }
+}
+
+/// Sent once when the `Lifetime` of `entity` is up.
+#[derive(Debug, Clone, Copy)]
+pub struct OnExpire {
+    pub entity: Entity,
+}
+
+pub struct LifetimePlugin;
+
+impl Plugin for LifetimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OnExpire>().add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Gameplay)
+                .with_system(expire_lifetimes),
+        );
+    }
+}
+
+fn expire_lifetimes(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut expired: EventWriter<OnExpire>,
+    mut lifetime_q: Query<(Entity, &mut Lifetime)>,
+) {
+    let delta = time_scale.delta(&time);
+    for (entity, mut lifetime) in lifetime_q.iter_mut() {
+        if lifetime.finished() || !lifetime.timer.tick(delta).just_finished() {
+            continue;
+        }
+        expired.send(OnExpire { entity });
+        if lifetime.despawn {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{
+    phase::{Phase, PhaseExt},
+    set_state, AppState,
+};
+
+/// Assets loaded before entering `AppState::Playing`, relative to `assets/`.
+///
+/// There is no audio yet, new sound files belong here as well.
+const PRELOAD_PATHS: &[&str] = &["player.ase", "cow.ase", "Share-Regular.ttf"];
+const PROGRESS_BAR_SIZE: Vec2 = Vec2::new(300., 16.);
+
+/// Strong handles to everything in `PRELOAD_PATHS`, kept around so the
+/// assets aren't unloaded again.
+pub struct PreloadedAssets(pub Vec<HandleUntyped>);
+
+#[derive(Component)]
+struct LoadingScreenTag;
+
+#[derive(Component)]
+struct ProgressBarTag;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Loading)
+                .with_system(preload_assets)
+                .with_system(setup_loading_screen),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Loading)
+                .in_phase(Phase::Gameplay)
+                .with_system(check_loading),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Loading).with_system(despawn_loading_screen));
+    }
+}
+
+fn preload_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = PRELOAD_PATHS
+        .iter()
+        .map(|path| asset_server.load_untyped(*path))
+        .collect();
+    commands.insert_resource(PreloadedAssets(handles));
+}
+
+fn setup_loading_screen(mut commands: Commands) {
+    // Covers the world until its sprites have textures
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: UiColor(Color::BLACK),
+            ..Default::default()
+        })
+        .insert(LoadingScreenTag)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(PROGRESS_BAR_SIZE.x), Val::Px(PROGRESS_BAR_SIZE.y)),
+                        ..Default::default()
+                    },
+                    color: UiColor(Color::DARK_GRAY),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+                                ..Default::default()
+                            },
+                            color: UiColor(Color::WHITE),
+                            ..Default::default()
+                        })
+                        .insert(ProgressBarTag);
+                });
+        });
+}
+
+fn check_loading(
+    asset_server: Res<AssetServer>,
+    preloaded: Res<PreloadedAssets>,
+    mut state: ResMut<State<AppState>>,
+    mut bar_q: Query<&mut Style, With<ProgressBarTag>>,
+) {
+    let mut loaded = 0;
+    for handle in preloaded.0.iter() {
+        match asset_server.get_load_state(handle) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed => warn!("Failed to load {:?}", asset_server.get_handle_path(handle)),
+            _ => {}
+        }
+    }
+    let progress = loaded as f32 / preloaded.0.len().max(1) as f32;
+    for mut style in bar_q.iter_mut() {
+        style.size.width = Val::Percent(progress * 100.);
+    }
+
+    if loaded == preloaded.0.len() {
+        set_state(&mut state, AppState::Playing);
+    }
+}
+
+fn despawn_loading_screen(mut commands: Commands, screen_q: Query<Entity, With<LoadingScreenTag>>) {
+    for screen in screen_q.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
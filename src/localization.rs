@@ -0,0 +1,89 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    frame_budget::TimedExt,
+    phase::{Phase, PhaseExt},
+    settings::Settings,
+};
+
+/// Languages with a file in `assets/lang`, in the order the settings menu
+/// cycles through them.
+pub const LANGUAGES: [&str; 2] = ["en", "de"];
+
+/// Translated strings of the current language.
+///
+/// Lookups fall back to the key itself, so untranslated names (e.g. NPC or
+/// item names) still show up as written.
+#[derive(Default)]
+pub struct Localization {
+    pub language: String,
+    strings: HashMap<String, String>,
+}
+
+impl Localization {
+    pub fn load(language: &str) -> Self {
+        let path = format!("assets/lang/{}.ron", language);
+        let strings = match std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|source| ron::from_str(&source).map_err(|err| err.to_string()))
+        {
+            Ok(strings) => strings,
+            Err(err) => {
+                warn!("Failed to load language file {}: {}", path, err);
+                HashMap::default()
+            }
+        };
+        Self {
+            language: language.to_string(),
+            strings,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.strings
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Like `get`, replacing `{name}` placeholders with the given values.
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut text = self.get(key);
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+}
+
+pub fn next_language(language: &str) -> String {
+    let i = LANGUAGES.iter().position(|l| *l == language).unwrap_or(0);
+    LANGUAGES[(i + 1) % LANGUAGES.len()].to_string()
+}
+
+/// Labels of the localization systems, `SwitchLanguage` changes the
+/// language texts are looked up in.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LocalizationSystem {
+    SwitchLanguage,
+}
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Localization>().add_system_set(
+            SystemSet::new().in_phase(Phase::Input).with_system(
+                switch_language
+                    .timed()
+                    .label(LocalizationSystem::SwitchLanguage),
+            ),
+        );
+    }
+}
+
+fn switch_language(settings: Res<Settings>, mut localization: ResMut<Localization>) {
+    if settings.is_changed() && localization.language != settings.language {
+        *localization = Localization::load(&settings.language);
+    }
+}
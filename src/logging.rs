@@ -0,0 +1,174 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    prelude::*,
+    utils::tracing::{
+        field::{Field, Visit},
+        Event, Subscriber,
+    },
+};
+use tracing_subscriber::{
+    fmt,
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+use crate::{
+    phase::{Phase, PhaseExt},
+    DebugRender,
+};
+
+/// Levels cycled through by the log level hotkey, starting at the first.
+///
+/// Gameplay logs are grouped in `physics`, `ai` and `quest` spans, so a
+/// single subsystem can be filtered with e.g. `RUST_LOG=[physics]=trace`.
+const LEVELS: [&str; 4] = ["info", "debug", "trace", "warn"];
+/// Kept quiet at every level, it logs every frame.
+const QUIET_TARGETS: &str = "wgpu=warn";
+/// Lines kept for the on-screen log tail.
+const TAIL_LINES: usize = 8;
+
+/// Handle to change the log level while the game is running.
+struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    level: usize,
+}
+
+/// Most recent log lines, newest last, written by `TailLayer`.
+#[derive(Clone, Default)]
+struct LogTail(Arc<Mutex<VecDeque<String>>>);
+
+#[derive(Component)]
+struct LogTailTextTag;
+
+/// Replaces bevy's `LogPlugin`, which can't change its filter after startup.
+pub struct LoggingPlugin;
+
+impl Plugin for LoggingPlugin {
+    fn build(&self, app: &mut App) {
+        let tail = LogTail::default();
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| level_filter(0));
+        let (filter, handle) = reload::Layer::new(filter);
+        let subscriber = Registry::default()
+            .with(filter)
+            .with(fmt::Layer::default())
+            .with(TailLayer(tail.clone()));
+        if let Err(err) = subscriber.try_init() {
+            eprintln!("Setting up logging failed: {}", err);
+        }
+
+        app.insert_resource(LogFilter { handle, level: 0 })
+            .insert_resource(tail)
+            .add_startup_system(setup_log_tail_text)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(cycle_log_level),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_log_tail_text),
+            );
+    }
+}
+
+fn level_filter(level: usize) -> EnvFilter {
+    EnvFilter::new(format!("{},{}", LEVELS[level], QUIET_TARGETS))
+}
+
+/// Keeps formatted events in `LogTail`, prefixed by the spans they're in.
+struct TailLayer(LogTail);
+
+impl<S> Layer<S> for TailLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut line = format!("{} ", event.metadata().level());
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                let _ = write!(line, "{}: ", span.name());
+            }
+        }
+        event.record(&mut LineVisitor(&mut line));
+
+        let mut lines = self.0 .0.lock().unwrap();
+        if lines.len() == TAIL_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?} ", value);
+        } else {
+            let _ = write!(self.0, "{}={:?} ", field.name(), value);
+        }
+    }
+}
+
+fn cycle_log_level(keys: Res<Input<KeyCode>>, mut log_filter: ResMut<LogFilter>) {
+    if !keys.just_pressed(KeyCode::F12) {
+        return;
+    }
+    log_filter.level = (log_filter.level + 1) % LEVELS.len();
+    match log_filter.handle.reload(level_filter(log_filter.level)) {
+        Ok(()) => info!("Log level {}", LEVELS[log_filter.level]),
+        Err(err) => warn!("Changing the log level failed: {}", err),
+    }
+}
+
+fn setup_log_tail_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(60.),
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 14.,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(LogTailTextTag);
+}
+
+/// Shows the log tail while debug rendering is on.
+fn update_log_tail_text(
+    debug_render: Res<DebugRender>,
+    tail: Res<LogTail>,
+    mut text_q: Query<(&mut Text, &mut Visibility), With<LogTailTextTag>>,
+) {
+    for (mut text, mut visibility) in text_q.iter_mut() {
+        visibility.is_visible = debug_render.0;
+        if debug_render.0 {
+            let lines = tail.0.lock().unwrap();
+            text.sections[0].value = lines.iter().cloned().collect::<Vec<_>>().join("\n");
+        }
+    }
+}
@@ -1,19 +1,171 @@
+use anim::{AnimPlugin, AnimState, AnimStateMachine, Facing};
+use avoidance::AvoidancePlugin;
+use bark::BarkPlugin;
 use bevy::{
-    math::Vec3Swizzles,
-    prelude::*,
-    transform::{transform_propagate_system::transform_propagate_system, TransformSystem},
-    utils::HashMap,
+    log::LogPlugin, prelude::*, transform::transform_propagate_system::transform_propagate_system,
 };
-use bevy_prototype_lyon::{
-    entity::ShapeBundle,
-    plugin::ShapePlugin,
-    prelude::{DrawMode, FillMode, GeometryBuilder, StrokeMode},
-    shapes,
+use bevy_prototype_lyon::plugin::ShapePlugin;
+use bevy_spicy_aseprite::{AsepriteAnimation, AsepriteBundle, AsepritePlugin};
+use camera::{PixelCameraPlugin, PixelCameraTag};
+use challenge::ChallengePlugin;
+use chunk::ChunkPlugin;
+use clock::ClockPlugin;
+use collision::{
+    apply_velocity, begin_physics_stats, detect_collisions, drop_vetoed_contacts,
+    emit_sensor_events, end_physics_stats, filter_contacts, handle_collision, move_characters,
+    reload_collision_matrix, run_substeps, swap_collision_worlds, tick_iframes,
+    update_debug_shapes, update_overlapping, updated_computed_aabbs, AabbBundle, AabbKind,
+    CharacterController, CollisionBehavior, CollisionMatrix, CollisionWorld, CollisionWorlds,
+    ContactFilters, DebugRenderStyle, ImpactEvent, PhysicsSettings, PhysicsStats, SensorEvent,
+    Velocity,
 };
-use bevy_spicy_aseprite::{
-    AsepriteAnimation, AsepriteAnimationState, AsepriteBundle, AsepriteImage, AsepritePlugin,
+use config::{ConfigPlugin, GameConfig};
+use console::ConsolePlugin;
+use critter::CritterPlugin;
+use dash::{DashBundle, DashPlugin};
+use debug_label::{DebugLabel, DebugLabelPlugin};
+use debug_menu::DebugMenuPlugin;
+use dialogue::{Dialogue, DialoguePlugin};
+use editor::EditorPlugin;
+use emote::EmotePlugin;
+use feedback::FeedbackPlugin;
+use feeding::FeedingPlugin;
+use flash::FlashPlugin;
+use floating_text::FloatingTextPlugin;
+use follow::FollowPlugin;
+use footprint::{FootprintPlugin, LeavesFootprints};
+use frame_budget::{FrameBudgetPlugin, TimedExt};
+use gate::GatePlugin;
+use group::GroupPlugin;
+use hazard::{spawn_hazard_zone, Hazard, HazardPlugin, Health};
+use herd::HerdPlugin;
+use hitbox::{HitboxFrames, HitboxPlugin};
+use hotbar::HotbarPlugin;
+use inspect::InspectPlugin;
+use interaction::InteractionPlugin;
+use layer::{LayerPlugin, RenderLayerGroup};
+use level::LevelPlugin;
+use lifetime::LifetimePlugin;
+use loading::LoadingPlugin;
+use localization::LocalizationPlugin;
+use logging::LoggingPlugin;
+use menu::MenuPlugin;
+use milking::MilkingPlugin;
+use mods::ModsPlugin;
+use mount::MountPlugin;
+use net::NetPlugin;
+use notify::NotifyPlugin;
+use npc::{NpcPlugin, NpcState};
+use origin::OriginPlugin;
+use particle::ParticlePlugin;
+use patrol::PatrolPlugin;
+use pen::{spawn_pen, PenPlugin};
+use persist::{Persist, PersistPlugin};
+use phase::{Phase, PhaseExt, PhysicsStage, PhysicsSystem};
+use photo_mode::PhotoModePlugin;
+use plant::PlantPlugin;
+use player_stats::PlayerStatsPlugin;
+use quest::{QuestHudTag, QuestPlugin};
+use quest_marker::QuestMarkerPlugin;
+use reaction::{spawn_pickup, CollisionReaction, ReactionPlugin};
+use replay::{ReplayPlugin, ReplaySession};
+use rng::RngPlugin;
+use rollback::{
+    apply_rollback, record_physics_snapshot, rollback_hotkey, RollbackBuffer, RollbackRequest,
 };
-use uuid::Uuid;
+use roof::{spawn_roof, RoofPlugin};
+use save::SavePlugin;
+use script::{Script, ScriptPlugin};
+use settings::{Settings, SettingsPlugin};
+use shadow::ShadowPlugin;
+use shop::{Merchant, ShopPlugin};
+use stats::StatsPlugin;
+use terrain::{apply_terrain_modifiers, spawn_terrain_zone, OnTerrain, TerrainModifier};
+use time_scale::TimeScalePlugin;
+use trigger::{spawn_trigger_zone, TriggerPlugin, TriggerPolicy};
+use vision::VisionPlugin;
+use weather::WeatherPlugin;
+
+mod anim;
+mod avoidance;
+mod bark;
+mod bench;
+mod camera;
+mod challenge;
+mod chunk;
+mod clock;
+mod collision;
+mod config;
+mod console;
+mod cooldown;
+mod critter;
+mod dash;
+mod debug_label;
+mod debug_menu;
+mod dialogue;
+mod editor;
+mod emote;
+mod feedback;
+mod feeding;
+mod flash;
+mod floating_text;
+mod follow;
+mod footprint;
+mod frame_budget;
+mod gate;
+mod group;
+mod hazard;
+mod herd;
+mod hitbox;
+mod hotbar;
+mod inspect;
+mod interaction;
+mod inventory;
+mod layer;
+mod level;
+mod lifetime;
+mod loading;
+mod localization;
+mod logging;
+mod menu;
+mod milking;
+mod mods;
+mod mount;
+mod net;
+mod notify;
+mod npc;
+mod origin;
+mod particle;
+mod pathfinding;
+mod patrol;
+mod pen;
+mod persist;
+mod phase;
+mod photo_mode;
+mod physics_dump;
+mod plant;
+mod player_stats;
+mod pool;
+mod quest;
+mod quest_marker;
+mod reaction;
+mod reflect_query;
+mod replay;
+mod rng;
+mod rollback;
+mod roof;
+mod save;
+mod script;
+mod settings;
+mod shadow;
+mod shop;
+mod stats;
+mod terrain;
+mod time_scale;
+mod trigger;
+mod vision;
+mod weather;
+mod world_diff;
 
 mod sprites {
     use bevy_spicy_aseprite::aseprite;
@@ -22,188 +174,307 @@ mod sprites {
     aseprite!(pub Cow, "assets/cow.ase");
 }
 
-const SCALE: f32 = 4.;
+const PLAYER_SPEED: f32 = 75.;
+/// Where the player starts, and wakes up again after fainting.
+const PLAYER_START: Vec2 = Vec2::new(0., -50.);
+const SPRINT_MULTIPLIER: f32 = 1.8;
 
 #[derive(Component)]
 struct PlayerTag;
 
-#[derive(Component)]
-struct CowTag;
-
-#[derive(Component)]
-struct DebugRenderTag;
+#[derive(Component, Default)]
+struct Sprinting(bool);
 
+/// The entity moved by `player_input`, the player or whatever they ride.
 #[derive(Component)]
-struct ColliderTag;
+struct Controlled {
+    speed: f32,
+}
 
 #[derive(Component)]
-struct SensorTag;
+struct CowTag;
 
 #[derive(Component)]
-struct Aabb {
-    uuid: Uuid,
-    extents: Vec2,
-}
-
-impl Aabb {
-    pub fn extents(&self) -> Vec2 {
-        self.extents * SCALE / 2.0 // TODO: why the divide by 2??
-    }
-}
-
-#[derive(Component, Debug, Clone, Copy)]
-enum AabbKind {
-    Sensor,
-    Collider,
-}
+struct DebugRenderTag;
 
-#[derive(Debug, Clone, Copy)]
-enum CollisionKind {
-    SensorSensor,
-    ColliderCollider,
-    SensorCollider,
-}
+/// Whether entities tagged with `DebugRenderTag` are shown.
+struct DebugRender(bool);
 
-#[derive(Component, Debug, Clone, Copy)]
-enum CollisionBehavior {
-    None,
-    Static,
-    Npc,
-    Player,
-    Movable,
+/// Label of `player_input`, which sets the player's velocity and animation
+/// from the keys held.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PlayerSystem {
+    Input,
 }
 
-#[derive(Debug, Copy, Clone)]
-struct AabbComputed {
-    min: Vec2,
-    max: Vec2,
-    aabb_kind: AabbKind,
-    collision_behavior: CollisionBehavior,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum AppState {
+    Loading,
+    Playing,
+    Paused,
+    Settings,
+    SaveSlots,
+    Stats,
+    Editor,
+    Dialogue,
+    PhotoMode,
+    Milking,
+    Shop,
+    /// Every cow is penned, see `pen`.
+    Victory,
 }
 
-impl AabbComputed {
-    fn intersects(
-        &self,
-        other: &AabbComputed,
-        self_ent: Entity,
-        other_ent: Entity,
-    ) -> Option<CollisionKind> {
-        if self_ent == other_ent {
-            return None;
-        }
-
-        if ((self.min.x >= other.min.x && self.min.x <= other.max.x)
-            || (self.max.x >= other.min.x && self.max.x <= other.max.x))
-            && ((self.min.y >= other.min.y && self.min.y <= other.max.y)
-                || (self.max.y >= other.min.y && self.max.y <= other.max.y))
-        {
-            let collision_kind = match (self.aabb_kind, other.aabb_kind) {
-                (AabbKind::Collider, AabbKind::Collider) => CollisionKind::ColliderCollider,
-                (AabbKind::Collider, AabbKind::Sensor) | (AabbKind::Sensor, AabbKind::Collider) => {
-                    CollisionKind::SensorCollider
-                }
-                (AabbKind::Sensor, AabbKind::Sensor) => CollisionKind::SensorSensor,
-            };
-            Some(collision_kind)
-        } else {
-            None
-        }
-    }
-
-    fn shallow_axis_displace(&self, other: &AabbComputed) -> Vec2 {
-        let left_displacement = other.min.x - self.max.x;
-        let right_displacement = other.max.x - self.min.x;
-        let down_displacement = other.min.y - self.max.y;
-        let up_displacement = other.max.y - self.min.y;
-        let horizontal = if left_displacement.abs() < right_displacement.abs() {
-            left_displacement
-        } else {
-            right_displacement
-        };
-        let vertical = if up_displacement.abs() < down_displacement.abs() {
-            up_displacement
-        } else {
-            down_displacement
-        };
-        if horizontal.abs() < vertical.abs() {
-            Vec2::new(horizontal / 2., 0.0)
-        } else {
-            Vec2::new(0.0, vertical / 2.)
+/// Queues a transition to `next`, returning whether it was queued. Another
+/// system may already have queued one this frame, e.g. for a key pressed
+/// together with the caller's, in which case that one wins rather than
+/// `State::set` panicking on the unwrap.
+fn set_state(state: &mut State<AppState>, next: AppState) -> bool {
+    match state.set(next) {
+        Ok(()) => true,
+        Err(err) => {
+            debug!("Not entering {:?}: {:?}", next, err);
+            false
         }
     }
 }
 
-#[derive(Bundle)]
-struct AabbBundle {
-    pub aabb: Aabb,
-    pub aabb_kind: AabbKind,
-    pub collision_behavior: CollisionBehavior,
-    #[bundle]
-    pub debug_shape: ShapeBundle,
-    pub tag: DebugRenderTag,
-}
-
-impl AabbBundle {
-    pub fn new(
-        extents: Vec2,
-        aabb_kind: AabbKind,
-        collision_behavior: CollisionBehavior,
-        color: Color,
-    ) -> Self {
-        let shape = shapes::Rectangle {
-            extents,
-            origin: bevy_prototype_lyon::prelude::RectangleOrigin::Center,
-        };
-
-        let builder = GeometryBuilder::new().add(&shape);
-
-        Self {
-            aabb: Aabb {
-                uuid: Uuid::new_v4(),
-                extents,
-            },
-            aabb_kind,
-            collision_behavior,
-            debug_shape: builder.build(
-                DrawMode::Outlined {
-                    fill_mode: FillMode::color(Color::NONE),
-                    outline_mode: StrokeMode::color(color),
-                },
-                Transform::default(),
-            ),
-            tag: DebugRenderTag,
-        }
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let flag = args.next();
+    if flag.as_deref() == Some("--bench-collisions") {
+        let count = args
+            .next()
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(bench::DEFAULT_COLLIDER_COUNT);
+        bench::bench_collisions(count);
+        return;
     }
-}
-
-#[derive(Default)]
-struct CollisionWorld {
-    aabbs: HashMap<Uuid, (Entity, AabbComputed)>,
-}
+    if flag.as_deref() == Some("--replay-physics") {
+        let path = args
+            .next()
+            .unwrap_or_else(|| physics_dump::DEFAULT_DUMP_PATH.to_string());
+        let frames = args
+            .next()
+            .and_then(|frames| frames.parse().ok())
+            .unwrap_or(1);
+        bench::replay_physics(&path, frames);
+        return;
+    }
+    let replay_session = ReplaySession::from_args(flag.as_deref(), args.next());
 
-static PHYSICS_STAGE: &str = "physics";
+    let settings = Settings::load();
+    let config = GameConfig::load();
 
-fn main() {
     App::new()
-        .add_plugins(DefaultPlugins)
+        .insert_resource(config.window_descriptor(settings.window_mode.window_mode()))
+        .insert_resource(config)
+        .insert_resource(DebugRender(settings.debug_render))
+        .insert_resource(settings)
+        .insert_resource(replay_session)
+        .add_plugin(LoggingPlugin)
+        .add_plugin(ModsPlugin)
+        .add_plugins_with(DefaultPlugins, |group| group.disable::<LogPlugin>())
+        .add_plugin(ConfigPlugin)
+        .add_plugin(RngPlugin)
         .add_plugin(AsepritePlugin)
         .add_plugin(ShapePlugin)
+        .add_plugin(AnimPlugin)
+        .add_plugin(LayerPlugin)
+        .add_plugin(NpcPlugin)
+        .add_plugin(GroupPlugin)
+        .add_plugin(HerdPlugin)
+        .add_plugin(FollowPlugin)
+        .add_plugin(PatrolPlugin)
+        .add_plugin(AvoidancePlugin)
+        .add_plugin(MountPlugin)
+        .add_plugin(EmotePlugin)
+        .add_plugin(BarkPlugin)
+        .add_plugin(ShadowPlugin)
+        .add_plugin(RoofPlugin)
+        .add_plugin(CritterPlugin)
+        .add_plugin(FootprintPlugin)
+        .add_plugin(DashPlugin)
+        .add_plugin(VisionPlugin)
+        .add_plugin(HitboxPlugin)
+        .add_plugin(HazardPlugin)
+        .add_plugin(LifetimePlugin)
+        .add_plugin(ParticlePlugin)
+        .add_plugin(FloatingTextPlugin)
+        .add_plugin(NotifyPlugin)
+        .add_plugin(FeedbackPlugin)
+        .add_plugin(TimeScalePlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(NetPlugin)
+        .add_plugin(InspectPlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(FrameBudgetPlugin)
+        .add_plugin(InteractionPlugin)
+        .add_plugin(GatePlugin)
+        .add_plugin(PlantPlugin)
+        .add_plugin(HotbarPlugin)
+        .add_plugin(FeedingPlugin)
+        .add_plugin(FlashPlugin)
+        .add_plugin(MilkingPlugin)
+        .add_plugin(ShopPlugin)
+        .add_plugin(PersistPlugin)
+        .add_plugin(TriggerPlugin)
+        .add_plugin(ReactionPlugin)
+        .add_plugin(DialoguePlugin)
+        .add_plugin(QuestPlugin)
+        .add_plugin(QuestMarkerPlugin)
+        .add_plugin(ChallengePlugin)
+        .add_plugin(PenPlugin)
+        .add_plugin(ScriptPlugin)
+        .add_plugin(ClockPlugin)
+        .add_plugin(WeatherPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(LocalizationPlugin)
+        .add_plugin(MenuPlugin)
+        .add_plugin(PlayerStatsPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(PixelCameraPlugin)
+        .add_plugin(LoadingPlugin)
+        .add_plugin(ChunkPlugin)
+        .add_plugin(OriginPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(EditorPlugin)
+        .add_plugin(PhotoModePlugin)
+        .add_plugin(DebugMenuPlugin)
+        .add_plugin(DebugLabelPlugin)
+        .add_plugin(ConsolePlugin)
+        .add_state(AppState::Loading)
         .add_stage_after(
             CoreStage::PostUpdate,
-            PHYSICS_STAGE,
+            PhysicsStage::Begin,
+            SystemStage::single_threaded(),
+        )
+        .add_stage_after(
+            PhysicsStage::Begin,
+            PhysicsStage::Substep,
+            SystemStage::single_threaded().with_run_criteria(run_substeps),
+        )
+        .add_stage_after(
+            PhysicsStage::Substep,
+            PhysicsStage::End,
             SystemStage::single_threaded(),
         )
+        .init_resource::<PhysicsSettings>()
         .init_resource::<CollisionWorld>()
+        .init_resource::<CollisionWorlds>()
+        .init_resource::<CollisionMatrix>()
+        .init_resource::<DebugRenderStyle>()
+        .init_resource::<PhysicsStats>()
+        .init_resource::<ContactFilters>()
+        .add_event::<SensorEvent>()
+        .add_event::<ImpactEvent>()
+        .init_resource::<RollbackBuffer>()
+        .add_event::<RollbackRequest>()
         .add_startup_system(setup)
-        .add_system_to_stage(PHYSICS_STAGE, updated_computed_aabbs.label("aabb"))
+        .add_system_to_stage(PhysicsStage::Begin, begin_physics_stats)
         .add_system_to_stage(
-            PHYSICS_STAGE,
-            handle_collision.label("collision").after("aabb"),
+            PhysicsStage::Begin,
+            swap_collision_worlds.before(PhysicsSystem::Rollback),
         )
-        .add_system_to_stage(PHYSICS_STAGE, transform_propagate_system.after("collision"))
+        .add_system_to_stage(
+            PhysicsStage::Begin,
+            apply_rollback.timed().label(PhysicsSystem::Rollback),
+        )
+        // Once per frame. Uses the terrain overlaps of the last substep of the
+        // previous frame.
+        .add_system_to_stage(
+            PhysicsStage::Begin,
+            apply_terrain_modifiers.after(PhysicsSystem::Rollback),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            updated_computed_aabbs.timed().label(PhysicsSystem::Aabb),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            detect_collisions
+                .timed()
+                .label(PhysicsSystem::Narrowphase)
+                .after(PhysicsSystem::Aabb),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            filter_contacts
+                .timed()
+                .label(PhysicsSystem::FilterContacts)
+                .after(PhysicsSystem::Narrowphase),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            drop_vetoed_contacts
+                .timed()
+                .label(PhysicsSystem::DropVetoed)
+                .after(PhysicsSystem::FilterContacts),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            emit_sensor_events
+                .timed()
+                .label(PhysicsSystem::SensorEvents)
+                .after(PhysicsSystem::DropVetoed),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            update_overlapping.after(PhysicsSystem::SensorEvents),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            handle_collision
+                .timed()
+                .label(PhysicsSystem::Collision)
+                .after(PhysicsSystem::DropVetoed),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            apply_velocity
+                .timed()
+                .label(PhysicsSystem::Integrate)
+                .after(PhysicsSystem::Collision),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            move_characters
+                .timed()
+                .label(PhysicsSystem::Integrate)
+                .after(PhysicsSystem::Collision),
+        )
+        .add_system_to_stage(
+            PhysicsStage::Substep,
+            transform_propagate_system
+                .timed()
+                .label(PhysicsSystem::Propagate)
+                .after(PhysicsSystem::Integrate),
+        )
+        .add_system_to_stage(PhysicsStage::End, end_physics_stats)
+        .add_system_to_stage(PhysicsStage::End, record_physics_snapshot)
+        .add_system_to_stage(PhysicsStage::End, update_debug_shapes)
         .add_system(bevy::input::system::exit_on_esc_system)
-        .add_system(player_input)
-        .add_system(toggle_debug_render)
+        .add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Input)
+                .with_system(player_input.timed().label(PlayerSystem::Input)),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Input)
+                .with_system(rollback_hotkey)
+                .with_system(toggle_debug_render),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Gameplay)
+                .with_system(tick_iframes)
+                .with_system(reload_collision_matrix),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(sync_debug_render),
+        )
         .run();
 }
 
@@ -218,16 +489,14 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         ..Default::default()
     };
 
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(PixelCameraTag);
     commands
         .spawn_bundle(AsepriteBundle {
             aseprite: sprites::Player::sprite(),
-            animation: AsepriteAnimation::from(sprites::Player::tags::WEST_WALK),
-            transform: Transform {
-                scale: Vec3::splat(SCALE),
-                translation: Vec3::new(0., -200., 0.),
-                ..Default::default()
-            },
+            animation: AsepriteAnimation::from(sprites::Player::tags::EAST_IDLE),
+            transform: Transform::from_translation(RenderLayerGroup::Actors.at(PLAYER_START)),
             ..Default::default()
         })
         .with_children(|parent| {
@@ -244,216 +513,206 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 Color::PURPLE,
             ));
         })
-        .insert(PlayerTag);
+        .insert(PlayerTag)
+        .insert(RenderLayerGroup::Actors)
+        .insert(Persist::new("player"))
+        .insert(Controlled {
+            speed: PLAYER_SPEED,
+        })
+        .insert(AnimStateMachine::player())
+        .insert(Velocity::default())
+        .insert(CharacterController::default())
+        .insert(Sprinting::default())
+        .insert(OnTerrain::default())
+        .insert(LeavesFootprints::default())
+        .insert_bundle(DashBundle::default())
+        .insert(Health::default())
+        .insert(DebugLabel::default())
+        // player.ase has no attack tags yet, so there are no hitboxes to show
+        .insert(HitboxFrames::default());
+    spawn_terrain_zone(
+        &mut commands,
+        Vec2::new(-60., 70.),
+        Vec2::new(64., 40.),
+        TerrainModifier::water(),
+    );
+    spawn_terrain_zone(
+        &mut commands,
+        Vec2::new(70., -90.),
+        Vec2::new(48., 32.),
+        TerrainModifier::mud(),
+    );
+    spawn_terrain_zone(
+        &mut commands,
+        Vec2::new(0., -130.),
+        Vec2::new(200., 16.),
+        TerrainModifier::path(),
+    );
+    spawn_hazard_zone(
+        &mut commands,
+        Vec2::new(120., 40.),
+        Vec2::new(32., 24.),
+        Hazard::thorns(),
+    );
+    spawn_hazard_zone(
+        &mut commands,
+        Vec2::new(-130., -90.),
+        Vec2::new(16., 16.),
+        Hazard::fire(),
+    );
+    spawn_pickup(
+        &mut commands,
+        Vec2::new(60., -95.),
+        vec![
+            CollisionReaction::SetFlag(String::from("found_cowbell")),
+            CollisionReaction::Notify(String::from("notify.found_cowbell")),
+        ],
+    );
+    spawn_pen(&mut commands, Vec2::new(-24., 170.), Vec2::new(200., 90.));
+    spawn_roof(
+        &mut commands,
+        "shed",
+        Vec2::new(180., 20.),
+        Vec2::new(64., 48.),
+    );
+    let mrs_cow = level::spawn_cow(&mut commands, Vec2::new(-75., -50.));
     commands
-        .spawn_bundle(AsepriteBundle {
-            aseprite: sprites::Cow::sprite(),
-            animation: AsepriteAnimation::from(sprites::Cow::tags::SLEEP),
-            transform: Transform {
-                scale: Vec3::splat(SCALE),
-                translation: Vec3::new(-300., -200., 0.),
+        .entity(mrs_cow)
+        .insert(Name::new("Mrs. Cow"))
+        .insert(Persist::new("mrs_cow"))
+        .insert(NpcState::Sleeping)
+        .insert(Dialogue {
+            start: String::from("mrs_cow_greeting"),
+        });
+    let bessie = level::spawn_cow(&mut commands, Vec2::new(-40., -130.));
+    commands
+        .entity(bessie)
+        .insert(Name::new("Bessie"))
+        .insert(Persist::new("bessie"))
+        .insert(Script::new("bessie.rhai"));
+    let daisy = level::spawn_cow(&mut commands, Vec2::new(-120., -130.));
+    commands
+        .entity(daisy)
+        .insert(Name::new("Daisy"))
+        .insert(Persist::new("daisy"))
+        .insert(Merchant::dairy());
+    let greeting = spawn_trigger_zone(
+        &mut commands,
+        "path_greeting",
+        Vec2::new(0., -130.),
+        Vec2::new(200., 16.),
+        TriggerPolicy::Once,
+    );
+    commands
+        .entity(greeting)
+        .insert(Script::new("path_greeting.rhai"));
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.),
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            parent.spawn_bundle(AabbBundle::new(
-                Vec2::new(32., 32.),
-                AabbKind::Collider,
-                CollisionBehavior::Static,
-                Color::GREEN,
-            ));
-            parent.spawn_bundle(AabbBundle::new(
-                Vec2::new(46., 46.),
-                AabbKind::Sensor,
-                CollisionBehavior::None,
-                Color::PURPLE,
-            ));
-        })
-        .insert(CowTag);
-    commands.spawn_bundle(Text2dBundle {
-        text: Text {
-            alignment: TextAlignment {
-                vertical: VerticalAlign::Center,
-                horizontal: HorizontalAlign::Left,
-            },
-            sections: vec![
-                TextSection {
-                    value: String::from("Quest: Talk to "),
-                    style: TextStyle {
-                        color: Color::WHITE,
-                        ..text_style.clone()
+            text: Text {
+                alignment: Default::default(),
+                sections: vec![
+                    TextSection {
+                        value: String::from("Quest: Talk to "),
+                        style: TextStyle {
+                            color: Color::WHITE,
+                            ..text_style.clone()
+                        },
                     },
-                },
-                TextSection {
-                    value: String::from("Mrs. Cow"),
-                    style: TextStyle {
-                        color: Color::LIME_GREEN,
-                        ..text_style.clone()
+                    TextSection {
+                        value: String::from("Mrs. Cow"),
+                        style: TextStyle {
+                            color: Color::LIME_GREEN,
+                            ..text_style.clone()
+                        },
                     },
-                },
-                TextSection {
-                    value: String::from("."),
-                    style: TextStyle {
-                        color: Color::WHITE,
-                        ..text_style.clone()
+                    TextSection {
+                        value: String::from("."),
+                        style: TextStyle {
+                            color: Color::WHITE,
+                            ..text_style.clone()
+                        },
                     },
-                },
-            ],
-        },
-        transform: Transform::from_translation(Vec3::new(-600., 300., 0.)),
-        ..Default::default()
-    });
+                ],
+            },
+            ..Default::default()
+        })
+        .insert(QuestHudTag);
 }
 
 fn player_input(
-    time: Res<Time>,
     keys: Res<Input<KeyCode>>,
-    mut player: Query<
-        (
-            &mut Transform,
-            &mut AsepriteAnimationState,
-            &mut AsepriteAnimation,
-            &Handle<AsepriteImage>,
-        ),
-        With<PlayerTag>,
-    >,
+    settings: Res<Settings>,
+    mut player: Query<&mut Sprinting, With<PlayerTag>>,
+    mut controlled: Query<(
+        &Controlled,
+        &mut Velocity,
+        &mut AnimStateMachine,
+        Option<&OnTerrain>,
+    )>,
 ) {
-    let (mut player_trans, mut player_anim_state, mut player_anim, h_img) = player.single_mut();
+    let mut sprinting = player.single_mut();
+    let (controlled, mut velocity, mut machine, on_terrain) = controlled.single_mut();
 
-    if keys.pressed(KeyCode::A) {
-        if !player_anim.is_tag(sprites::Player::tags::WEST_WALK) {
-            *player_anim = AsepriteAnimation::from(sprites::Player::tags::WEST_WALK);
-        }
-        if player_anim_state.is_paused() {
-            player_anim_state.start();
-        }
-        player_trans.translation.x -= 300.0 * time.delta_seconds();
-    } else if keys.pressed(KeyCode::D) {
-        if !player_anim.is_tag(sprites::Player::tags::EAST_WALK) {
-            *player_anim = AsepriteAnimation::from(sprites::Player::tags::EAST_WALK);
-        }
-        if player_anim_state.is_paused() {
-            player_anim_state.start();
-        }
-        player_trans.translation.x += 300.0 * time.delta_seconds();
+    let bindings = &settings.key_bindings;
+    let mut direction = Vec2::ZERO;
+    if keys.pressed(bindings.left) {
+        direction.x -= 1.;
     }
-    // Trigger idle anim if no input
-    else if let AsepriteAnimation::Tag { tag } = *player_anim {
-        match tag {
-            sprites::Player::tags::EAST_WALK => {
-                *player_anim = AsepriteAnimation::from(sprites::Player::tags::EAST_IDLE);
-            }
-            sprites::Player::tags::WEST_WALK => {
-                *player_anim = AsepriteAnimation::from(sprites::Player::tags::WEST_IDLE);
-            }
-            _ => {}
-        }
+    if keys.pressed(bindings.right) {
+        direction.x += 1.;
     }
-}
-
-fn toggle_debug_render(
-    keys: Res<Input<KeyCode>>,
-    mut query: Query<&mut Visibility, With<DebugRenderTag>>,
-) {
-    if keys.just_pressed(KeyCode::Grave) {
-        for mut visible in query.iter_mut() {
-            visible.is_visible = !visible.is_visible;
-        }
+    if keys.pressed(bindings.up) {
+        direction.y += 1.;
+    }
+    if keys.pressed(bindings.down) {
+        direction.y -= 1.;
     }
+    sprinting.0 = keys.pressed(bindings.sprint);
+    let speed = if sprinting.0 {
+        controlled.speed * SPRINT_MULTIPLIER
+    } else {
+        controlled.speed
+    };
+    velocity.0 = direction.normalize_or_zero() * speed;
+
+    let overrides = match on_terrain.and_then(|on_terrain| on_terrain.modifier?.player_walk_tags) {
+        Some([west_walk, east_walk]) => vec![
+            (AnimState::Walk, Facing::West, west_walk),
+            (AnimState::Walk, Facing::East, east_walk),
+        ],
+        None => Vec::new(),
+    };
+    machine.set_overrides(&overrides);
+    machine.face(direction);
+    machine.set_state(if direction == Vec2::ZERO {
+        AnimState::Idle
+    } else {
+        AnimState::Walk
+    });
 }
 
-fn updated_computed_aabbs(
-    mut collision_world: ResMut<CollisionWorld>,
-    aabb_query: Query<
-        (
-            &Parent,
-            &Aabb,
-            &AabbKind,
-            &CollisionBehavior,
-            &GlobalTransform,
-        ),
-        Changed<GlobalTransform>,
-    >,
-) {
-    for (parent, aabb, aabb_kind, collision_behavior, g_trans) in aabb_query.iter() {
-        let aabb_computed = AabbComputed {
-            min: g_trans.translation.xy() - aabb.extents(),
-            max: g_trans.translation.xy() + aabb.extents(),
-            aabb_kind: *aabb_kind,
-            collision_behavior: *collision_behavior,
-        };
-        collision_world
-            .aabbs
-            .insert(aabb.uuid, (**parent, aabb_computed));
+fn toggle_debug_render(keys: Res<Input<KeyCode>>, mut debug_render: ResMut<DebugRender>) {
+    if keys.just_pressed(KeyCode::Grave) {
+        debug_render.0 = !debug_render.0;
     }
 }
 
-fn handle_collision(
-    collision_world: Res<CollisionWorld>,
-    mut transform_q: Query<&mut Transform>,
-    mut gtransform_q: Query<&mut GlobalTransform>,
+fn sync_debug_render(
+    debug_render: Res<DebugRender>,
+    mut query: Query<(&mut Visibility, Added<DebugRenderTag>), With<DebugRenderTag>>,
 ) {
-    for (ent1, aabb1) in collision_world.aabbs.values() {
-        for (ent2, aabb2) in collision_world.aabbs.values() {
-            if let Some(collision_kind) = aabb1.intersects(aabb2, *ent1, *ent2) {
-                match collision_kind {
-                    CollisionKind::ColliderCollider => {
-                        match (aabb1.collision_behavior, aabb2.collision_behavior) {
-                            (CollisionBehavior::Player, CollisionBehavior::Static) => {
-                                let displacement = aabb1.shallow_axis_displace(aabb2);
-                                dbg!(&displacement);
-                                transform_q
-                                    .get_component_mut::<Transform>(*ent1)
-                                    .unwrap()
-                                    .translation += displacement.extend(0.0);
-                                gtransform_q
-                                    .get_component_mut::<GlobalTransform>(*ent1)
-                                    .unwrap()
-                                    .translation += displacement.extend(0.0);
-                            }
-                            (CollisionBehavior::Static, CollisionBehavior::Player) => {
-                                let displacement = aabb2.shallow_axis_displace(aabb1);
-                                dbg!(&displacement, ent1, ent2);
-                                transform_q
-                                    .get_component_mut::<Transform>(*ent2)
-                                    .unwrap()
-                                    .translation += displacement.extend(0.0);
-                                gtransform_q
-                                    .get_component_mut::<GlobalTransform>(*ent2)
-                                    .unwrap()
-                                    .translation += displacement.extend(0.0);
-                            }
-                            (CollisionBehavior::None, CollisionBehavior::None) => { /* do nothing */
-                            }
-                            (CollisionBehavior::None, CollisionBehavior::Static) => todo!(),
-                            (CollisionBehavior::None, CollisionBehavior::Npc) => todo!(),
-                            (CollisionBehavior::None, CollisionBehavior::Player) => todo!(),
-                            (CollisionBehavior::None, CollisionBehavior::Movable) => todo!(),
-                            (CollisionBehavior::Static, CollisionBehavior::None) => todo!(),
-                            (CollisionBehavior::Static, CollisionBehavior::Static) => todo!(),
-                            (CollisionBehavior::Static, CollisionBehavior::Npc) => todo!(),
-                            (CollisionBehavior::Static, CollisionBehavior::Movable) => todo!(),
-                            (CollisionBehavior::Npc, CollisionBehavior::None) => todo!(),
-                            (CollisionBehavior::Npc, CollisionBehavior::Static) => todo!(),
-                            (CollisionBehavior::Npc, CollisionBehavior::Npc) => todo!(),
-                            (CollisionBehavior::Npc, CollisionBehavior::Player) => todo!(),
-                            (CollisionBehavior::Npc, CollisionBehavior::Movable) => todo!(),
-                            (CollisionBehavior::Player, CollisionBehavior::None) => todo!(),
-                            (CollisionBehavior::Player, CollisionBehavior::Npc) => todo!(),
-                            (CollisionBehavior::Player, CollisionBehavior::Player) => todo!(),
-                            (CollisionBehavior::Player, CollisionBehavior::Movable) => todo!(),
-                            (CollisionBehavior::Movable, CollisionBehavior::None) => todo!(),
-                            (CollisionBehavior::Movable, CollisionBehavior::Static) => todo!(),
-                            (CollisionBehavior::Movable, CollisionBehavior::Npc) => todo!(),
-                            (CollisionBehavior::Movable, CollisionBehavior::Player) => todo!(),
-                            (CollisionBehavior::Movable, CollisionBehavior::Movable) => todo!(),
-                        }
-                    }
-                    CollisionKind::SensorCollider => {}
-                    CollisionKind::SensorSensor => {}
-                }
-            }
+    for (mut visible, added) in query.iter_mut() {
+        if added || debug_render.is_changed() {
+            visible.is_visible = debug_render.0;
         }
     }
 }
@@ -0,0 +1,425 @@
+use bevy::prelude::*;
+
+use crate::{
+    collision::Velocity,
+    editor::{Editor, PaletteEntry},
+    frame_budget::TimedExt,
+    localization::next_language,
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    save::{SaveManager, SlotAction, SlotRequest},
+    set_state,
+    settings::{Action, Settings},
+    AppState, DebugRender,
+};
+
+const VOLUME_STEP: f32 = 0.1;
+const TEXT_SCALE_STEP: f32 = 0.25;
+const TEXT_SCALE_RANGE: (f32, f32) = (0.5, 2.);
+const SCREEN_SHAKE_STEP: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsEntry {
+    Volume,
+    DebugRender,
+    WindowMode,
+    Language,
+    TextScale,
+    ScreenShake,
+    HighContrastDebug,
+    TimedMode,
+    Bind(Action),
+}
+
+impl SettingsEntry {
+    fn all() -> Vec<SettingsEntry> {
+        let mut entries = vec![
+            SettingsEntry::Volume,
+            SettingsEntry::DebugRender,
+            SettingsEntry::WindowMode,
+            SettingsEntry::Language,
+            SettingsEntry::TextScale,
+            SettingsEntry::ScreenShake,
+            SettingsEntry::HighContrastDebug,
+            SettingsEntry::TimedMode,
+        ];
+        entries.extend(
+            Action::ALL
+                .iter()
+                .map(|action| SettingsEntry::Bind(*action)),
+        );
+        entries
+    }
+}
+
+#[derive(Default)]
+struct SettingsMenu {
+    selected: usize,
+    /// Set while waiting for the key to bind to this action.
+    rebinding: Option<Action>,
+}
+
+#[derive(Default)]
+struct SaveSlotsMenu {
+    selected: usize,
+}
+
+#[derive(Component)]
+struct MenuTextTag;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MenuSystem {
+    Input,
+}
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsMenu>()
+            .init_resource::<SaveSlotsMenu>()
+            .add_startup_system(setup_menu)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(menu_input.timed().label(MenuSystem::Input)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(render_menu.after(MenuSystem::Input)),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(halt_velocities))
+            .add_system_set(SystemSet::on_enter(AppState::Victory).with_system(halt_velocities))
+            .add_system_set(SystemSet::on_exit(AppState::Settings).with_system(save_settings));
+    }
+}
+
+fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(80.),
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 30.,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(MenuTextTag);
+}
+
+pub fn halt_velocities(mut velocity_q: Query<&mut Velocity>) {
+    for mut velocity in velocity_q.iter_mut() {
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
+
+fn menu_input(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut slots_menu: ResMut<SaveSlotsMenu>,
+    mut settings: ResMut<Settings>,
+    mut debug_render: ResMut<DebugRender>,
+    mut slot_requests: EventWriter<SlotRequest>,
+) {
+    match *state.current() {
+        AppState::Playing => {
+            if keys.just_pressed(KeyCode::P) {
+                set_state(&mut state, AppState::Paused);
+            }
+        }
+        AppState::Paused => {
+            if keys.just_pressed(KeyCode::P) {
+                set_state(&mut state, AppState::Playing);
+            } else if keys.just_pressed(KeyCode::O) {
+                *menu = SettingsMenu::default();
+                set_state(&mut state, AppState::Settings);
+            } else if keys.just_pressed(KeyCode::L) {
+                set_state(&mut state, AppState::SaveSlots);
+            } else if keys.just_pressed(KeyCode::K) {
+                set_state(&mut state, AppState::Stats);
+            }
+        }
+        AppState::Stats => {
+            if keys.just_pressed(KeyCode::P) {
+                set_state(&mut state, AppState::Paused);
+            }
+        }
+        AppState::Victory => {
+            if keys.just_pressed(KeyCode::P) {
+                set_state(&mut state, AppState::Playing);
+            }
+        }
+        AppState::Loading
+        | AppState::Editor
+        | AppState::Dialogue
+        | AppState::PhotoMode
+        | AppState::Milking
+        | AppState::Shop => {}
+        AppState::SaveSlots => {
+            let slot = slots_menu.selected;
+            let slot_count = SaveManager::SLOT_COUNT + SaveManager::AUTOSAVE_COUNT;
+            if keys.just_pressed(KeyCode::P) {
+                set_state(&mut state, AppState::Paused);
+            } else if keys.just_pressed(KeyCode::Up) {
+                slots_menu.selected = (slot + slot_count - 1) % slot_count;
+            } else if keys.just_pressed(KeyCode::Down) {
+                slots_menu.selected = (slot + 1) % slot_count;
+            } else if keys.just_pressed(KeyCode::F5) && !SaveManager::is_autosave(slot) {
+                slot_requests.send(SlotRequest {
+                    slot,
+                    action: SlotAction::Save,
+                });
+            } else if keys.just_pressed(KeyCode::F9) {
+                if set_state(&mut state, AppState::Playing) {
+                    slot_requests.send(SlotRequest {
+                        slot,
+                        action: SlotAction::Load,
+                    });
+                }
+            } else if keys.just_pressed(KeyCode::Delete) {
+                slot_requests.send(SlotRequest {
+                    slot,
+                    action: SlotAction::Delete,
+                });
+            }
+        }
+        AppState::Settings => {
+            if let Some(action) = menu.rebinding {
+                if let Some(key) = keys.get_just_pressed().next().copied() {
+                    *settings.key_bindings.key_mut(action) = key;
+                    menu.rebinding = None;
+                    // Don't let the newly bound key also act as a menu key
+                    keys.clear();
+                }
+                return;
+            }
+
+            let entries = SettingsEntry::all();
+            if keys.just_pressed(KeyCode::P) {
+                set_state(&mut state, AppState::Paused);
+            } else if keys.just_pressed(KeyCode::Up) {
+                menu.selected = (menu.selected + entries.len() - 1) % entries.len();
+            } else if keys.just_pressed(KeyCode::Down) {
+                menu.selected = (menu.selected + 1) % entries.len();
+            } else if keys.just_pressed(KeyCode::Left) || keys.just_pressed(KeyCode::Right) {
+                let sign = if keys.just_pressed(KeyCode::Left) {
+                    -1.
+                } else {
+                    1.
+                };
+                let accessibility = &mut settings.accessibility;
+                match entries[menu.selected] {
+                    SettingsEntry::Volume => {
+                        settings.volume = (settings.volume + sign * VOLUME_STEP).clamp(0., 1.);
+                    }
+                    SettingsEntry::TextScale => {
+                        let (min, max) = TEXT_SCALE_RANGE;
+                        accessibility.text_scale =
+                            (accessibility.text_scale + sign * TEXT_SCALE_STEP).clamp(min, max);
+                    }
+                    SettingsEntry::ScreenShake => {
+                        accessibility.screen_shake =
+                            (accessibility.screen_shake + sign * SCREEN_SHAKE_STEP).clamp(0., 1.);
+                    }
+                    SettingsEntry::HighContrastDebug => {
+                        accessibility.high_contrast_debug = !accessibility.high_contrast_debug;
+                    }
+                    SettingsEntry::TimedMode => {
+                        settings.timed_mode = !settings.timed_mode;
+                    }
+                    SettingsEntry::DebugRender => {
+                        settings.debug_render = !settings.debug_render;
+                        debug_render.0 = settings.debug_render;
+                    }
+                    SettingsEntry::WindowMode => {
+                        settings.window_mode = settings.window_mode.next();
+                    }
+                    SettingsEntry::Language => {
+                        settings.language = next_language(&settings.language);
+                    }
+                    SettingsEntry::Bind(_) => {}
+                }
+            } else if keys.just_pressed(KeyCode::Return) {
+                if let SettingsEntry::Bind(action) = entries[menu.selected] {
+                    menu.rebinding = Some(action);
+                }
+            }
+        }
+    }
+}
+
+fn render_menu(
+    state: Res<State<AppState>>,
+    menu: Res<SettingsMenu>,
+    slots_menu: Res<SaveSlotsMenu>,
+    save_manager: Res<SaveManager>,
+    settings: Res<Settings>,
+    editor: Res<Editor>,
+    stats: Res<PlayerStats>,
+    mut text_q: Query<&mut Text, With<MenuTextTag>>,
+) {
+    let value = match state.current() {
+        AppState::Loading
+        | AppState::Playing
+        | AppState::Dialogue
+        | AppState::PhotoMode
+        | AppState::Milking
+        | AppState::Shop => String::new(),
+        AppState::Paused => String::from(
+            "Paused\n\n[P] Resume\n[O] Settings\n[L] Save slots\n[K] Stats\n[Esc] Quit",
+        ),
+        AppState::Editor => {
+            let mut lines = vec![String::from("Editor"), String::new()];
+            for (i, entry) in PaletteEntry::all().into_iter().enumerate() {
+                let cursor = if i == editor.selected { "> " } else { "  " };
+                lines.push(format!("{}{}", cursor, entry.label()));
+            }
+            lines.push(String::new());
+            lines.push(String::from(
+                "[Tab] Palette  [LMB] Place/Move  [RMB] Delete  [WASD] Pan  [Ctrl+S] Export  [F2] Play",
+            ));
+            lines.join("\n")
+        }
+        AppState::SaveSlots => {
+            let mut lines = vec![String::from("Save slots"), String::new()];
+            for (i, slot) in save_manager.slots.iter().enumerate() {
+                let line = match slot {
+                    Some(metadata) => format!(
+                        "{} - {} - {}",
+                        metadata.name,
+                        format_play_time(metadata.playtime_secs),
+                        metadata.quest_summary
+                    ),
+                    None if SaveManager::is_autosave(i) => {
+                        format!("Autosave {} - Empty", i - SaveManager::SLOT_COUNT + 1)
+                    }
+                    None => format!("Slot {} - Empty", i + 1),
+                };
+                let cursor = if i == slots_menu.selected { "> " } else { "  " };
+                lines.push(format!("{}{}", cursor, line));
+            }
+            lines.push(String::new());
+            lines.push(String::from(
+                "[Up/Down] Select  [F5] Save  [F9] Load  [Delete] Delete  [P] Back",
+            ));
+            lines.join("\n")
+        }
+        AppState::Stats => [
+            String::from("Stats"),
+            String::new(),
+            format!("Play time: {}", format_play_time(stats.play_time_secs)),
+            format!("Distance walked: {:.0}px", stats.distance_walked),
+            format!("Cows talked to: {}", stats.cows_talked_to),
+            format!("Items collected: {}", stats.items_collected),
+            format!("Coins: {}", stats.coins),
+        ]
+        .into_iter()
+        .chain(best_time_lines(&stats))
+        .chain([String::new(), String::from("[P] Back")])
+        .collect::<Vec<_>>()
+        .join("\n"),
+        AppState::Victory => [
+            String::from("All the cows are penned!"),
+            String::new(),
+            format!("Time: {}", format_play_time(stats.play_time_secs)),
+            format!("Distance walked: {:.0}px", stats.distance_walked),
+            format!("Cows talked to: {}", stats.cows_talked_to),
+            format!("Items collected: {}", stats.items_collected),
+            format!("Coins: {}", stats.coins),
+            String::new(),
+            String::from("[P] Keep playing  [Esc] Quit"),
+        ]
+        .join("\n"),
+        AppState::Settings => {
+            let mut lines = vec![String::from("Settings"), String::new()];
+            for (i, entry) in SettingsEntry::all().into_iter().enumerate() {
+                let line = match entry {
+                    SettingsEntry::Volume => {
+                        format!("Volume: {:.0}%", settings.volume * 100.)
+                    }
+                    SettingsEntry::DebugRender => {
+                        format!("Debug render: {}", settings.debug_render)
+                    }
+                    SettingsEntry::WindowMode => {
+                        format!("Window mode: {:?}", settings.window_mode)
+                    }
+                    SettingsEntry::Language => format!("Language: {}", settings.language),
+                    SettingsEntry::TextScale => format!(
+                        "Text size: {:.0}%",
+                        settings.accessibility.text_scale * 100.
+                    ),
+                    SettingsEntry::ScreenShake => format!(
+                        "Screen shake: {:.0}%",
+                        settings.accessibility.screen_shake * 100.
+                    ),
+                    SettingsEntry::HighContrastDebug => format!(
+                        "High contrast debug colors: {}",
+                        settings.accessibility.high_contrast_debug
+                    ),
+                    SettingsEntry::TimedMode => format!("Timed mode: {}", settings.timed_mode),
+                    SettingsEntry::Bind(action) if menu.rebinding == Some(action) => {
+                        format!("{:?}: press a key...", action)
+                    }
+                    SettingsEntry::Bind(action) => {
+                        format!("{:?}: {:?}", action, settings.key_bindings.key(action))
+                    }
+                };
+                let cursor = if i == menu.selected { "> " } else { "  " };
+                lines.push(format!("{}{}", cursor, line));
+            }
+            lines.push(String::new());
+            lines.push(String::from(
+                "[Up/Down] Select  [Left/Right] Change  [Enter] Rebind  [P] Back",
+            ));
+            lines.join("\n")
+        }
+    };
+
+    for mut text in text_q.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn format_play_time(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Stats screen lines for the best times of timed mode, if there are any.
+fn best_time_lines(stats: &PlayerStats) -> Vec<String> {
+    if stats.best_quest_times.is_empty() {
+        return Vec::new();
+    }
+    let mut lines = vec![String::new(), String::from("Best times:")];
+    for (quest, secs) in stats.best_quest_times.iter() {
+        lines.push(format!(
+            "  {}: {}:{:04.1}",
+            quest,
+            (*secs / 60.) as u32,
+            secs % 60.
+        ));
+    }
+    lines
+}
@@ -0,0 +1,254 @@
+use bevy::prelude::*;
+
+use crate::{
+    dialogue::Dialogue,
+    feeding::Fed,
+    floating_text::SpawnFloatingText,
+    frame_budget::TimedExt,
+    interaction::{InteractEvent, InteractionSystem},
+    inventory::Inventory,
+    localization::Localization,
+    menu::halt_velocities,
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    script::Script,
+    set_state,
+    settings::Settings,
+    shop::Merchant,
+    AppState, CowTag, PlayerTag,
+};
+
+static MILK_ITEM: &str = "Milk";
+const MILK_COUNT: u32 = 1;
+const BAR_SIZE: Vec2 = Vec2::new(300., 20.);
+const MARKER_WIDTH: f32 = 4.;
+/// Bar lengths per second the marker sweeps.
+const MARKER_SPEED: f32 = 1.2;
+/// Fraction of the bar the highlighted window covers.
+const WINDOW_WIDTH: f32 = 0.15;
+const WINDOW_COLOR: Color = Color::rgb(0.3, 0.8, 0.35);
+
+/// The milking in progress, present while in `AppState::Milking`.
+///
+/// A marker sweeps back and forth over a bar, pressing interact while it's
+/// inside the highlighted window yields milk. Either way the cow has to be
+/// fed again before it can be milked again.
+pub struct MilkingGame {
+    pub cow: Entity,
+    /// Marker position along the bar, from 0 to 1.
+    marker: f32,
+    /// 1 or -1, the direction the marker is sweeping in.
+    direction: f32,
+    /// Where the window starts along the bar.
+    window_start: f32,
+}
+
+impl MilkingGame {
+    fn in_window(&self) -> bool {
+        (self.window_start..=self.window_start + WINDOW_WIDTH).contains(&self.marker)
+    }
+}
+
+#[derive(Component)]
+struct MilkingUiTag;
+
+#[derive(Component)]
+struct MilkingMarkerTag;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MilkingSystem {
+    Input,
+}
+
+pub struct MilkingPlugin;
+
+impl Plugin for MilkingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(start_milking.after(InteractionSystem::Interact)),
+        )
+        .add_system_set(
+            SystemSet::on_enter(AppState::Milking)
+                .with_system(halt_velocities)
+                .with_system(setup_milking_ui),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Milking)
+                .in_phase(Phase::Input)
+                .with_system(milking_input.timed().label(MilkingSystem::Input)),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Milking)
+                .in_phase(Phase::Ui)
+                .with_system(update_milking_marker.after(MilkingSystem::Input)),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Milking).with_system(despawn_milking_ui));
+    }
+}
+
+/// Interacting with a fed cow starts milking it. Cows that talk, trade or
+/// run a script have their own interactions, and are left alone.
+fn start_milking(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut interact_events: EventReader<InteractEvent>,
+    cow_q: Query<
+        (),
+        (
+            With<CowTag>,
+            With<Fed>,
+            Without<Dialogue>,
+            Without<Merchant>,
+            Without<Script>,
+        ),
+    >,
+) {
+    for event in interact_events.iter() {
+        if cow_q.get(event.target).is_err() {
+            continue;
+        }
+        if !set_state(&mut state, AppState::Milking) {
+            return;
+        }
+        // Move the window around so it can't be hit from memory
+        let window_start = 0.2 + (time.seconds_since_startup() as f32 * 0.37).fract() * 0.6;
+        commands.insert_resource(MilkingGame {
+            cow: event.target,
+            marker: 0.,
+            direction: 1.,
+            window_start: window_start.min(1. - WINDOW_WIDTH),
+        });
+        // The interact press that started milking shouldn't also stop the marker
+        keys.clear();
+        return;
+    }
+}
+
+fn setup_milking_ui(mut commands: Commands, game: Option<Res<MilkingGame>>) {
+    let game = match game {
+        Some(game) => game,
+        None => return,
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(120.),
+                    left: Val::Percent(50.),
+                    ..Default::default()
+                },
+                margin: Rect {
+                    left: Val::Px(-BAR_SIZE.x / 2.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(BAR_SIZE.x), Val::Px(BAR_SIZE.y)),
+                ..Default::default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..Default::default()
+        })
+        .insert(MilkingUiTag)
+        .with_children(|parent| {
+            parent.spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Percent(game.window_start * 100.),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Percent(WINDOW_WIDTH * 100.), Val::Percent(100.)),
+                    ..Default::default()
+                },
+                color: WINDOW_COLOR.into(),
+                ..Default::default()
+            });
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        size: Size::new(Val::Px(MARKER_WIDTH), Val::Percent(100.)),
+                        ..Default::default()
+                    },
+                    color: Color::WHITE.into(),
+                    ..Default::default()
+                })
+                .insert(MilkingMarkerTag);
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn milking_input(
+    mut commands: Commands,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    mut state: ResMut<State<AppState>>,
+    mut inventory: ResMut<Inventory>,
+    mut stats: ResMut<PlayerStats>,
+    mut floating_texts: EventWriter<SpawnFloatingText>,
+    game: Option<ResMut<MilkingGame>>,
+    player_q: Query<Entity, With<PlayerTag>>,
+) {
+    let mut game = match game {
+        Some(game) => game,
+        None => return,
+    };
+
+    if keys.just_pressed(settings.key_bindings.interact) {
+        let (text, color) = if game.in_window() {
+            inventory.add(MILK_ITEM, MILK_COUNT);
+            stats.items_collected += MILK_COUNT;
+            (
+                format!("+{} {}", MILK_COUNT, localization.get(MILK_ITEM)),
+                Color::WHITE,
+            )
+        } else {
+            (localization.get("milking.missed"), Color::GRAY)
+        };
+        floating_texts.send(SpawnFloatingText {
+            anchor: player_q.single(),
+            text,
+            color,
+        });
+        commands.entity(game.cow).remove::<Fed>();
+        commands.remove_resource::<MilkingGame>();
+        // A transition already queued ends the milking just as well
+        set_state(&mut state, AppState::Playing);
+        return;
+    }
+
+    // Bounce off the ends of the bar
+    game.marker += game.direction * MARKER_SPEED * time.delta_seconds();
+    if game.marker >= 1. {
+        game.marker = 2. - game.marker;
+        game.direction = -1.;
+    } else if game.marker <= 0. {
+        game.marker = -game.marker;
+        game.direction = 1.;
+    }
+}
+
+fn update_milking_marker(
+    game: Option<Res<MilkingGame>>,
+    mut marker_q: Query<&mut Style, With<MilkingMarkerTag>>,
+) {
+    let game = match game {
+        Some(game) => game,
+        None => return,
+    };
+    for mut style in marker_q.iter_mut() {
+        style.position.left = Val::Percent(game.marker * 100.);
+    }
+}
+
+fn despawn_milking_ui(mut commands: Commands, ui_q: Query<Entity, With<MilkingUiTag>>) {
+    for entity in ui_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
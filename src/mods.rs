@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+
+static ASSETS_DIR: &str = "assets";
+/// Each subdirectory is a mod, holding files that override the ones of the
+/// same name in `ASSETS_DIR`, e.g. `mods/hard_mode/quests.ron`.
+static MODS_DIR: &str = "mods";
+/// Optional list of mod directory names, e.g. `["base_tweaks", "hard_mode"]`.
+/// Later mods win. Without it, mods load in alphabetical order.
+static LOAD_ORDER_FILE: &str = "load_order.ron";
+
+/// Mods in load order. Mods missing from a load order file are left out,
+/// so it doubles as a way to turn mods off.
+pub fn load_order() -> Vec<String> {
+    let dir = Path::new(MODS_DIR);
+    let order_path = dir.join(LOAD_ORDER_FILE);
+    if let Ok(source) = std::fs::read_to_string(&order_path) {
+        match ron::from_str::<Vec<String>>(&source) {
+            Ok(order) => return order,
+            Err(err) => warn!("Ignoring malformed {}: {}", order_path.display(), err),
+        }
+    }
+
+    let mut mods: Vec<String> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    mods.sort();
+    mods
+}
+
+/// Path of the file `name`, relative to `assets/`, in the last mod of the
+/// load order that has it, or else in `assets/`.
+///
+/// Resolved on every call, so mods can be added while the game is running
+/// and files that reload on change pick them up.
+pub fn resolve(name: &str) -> PathBuf {
+    load_order()
+        .iter()
+        .rev()
+        .map(|mod_name| Path::new(MODS_DIR).join(mod_name).join(name))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| Path::new(ASSETS_DIR).join(name))
+}
+
+/// Reads and parses the RON file `name` like `resolve` finds it, except that
+/// a mod's copy that fails to parse is skipped with a warning for the one
+/// below it in the load order, and finally the one in `assets/`, so a typo in
+/// a mod doesn't take the game down. Errs if none of them loads.
+pub fn load_ron<T: DeserializeOwned>(name: &str) -> Result<T, String> {
+    let mod_paths = load_order()
+        .iter()
+        .rev()
+        .map(|mod_name| Path::new(MODS_DIR).join(mod_name).join(name))
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    for path in mod_paths {
+        match read_ron(&path) {
+            Ok(value) => return Ok(value),
+            Err(err) => warn!("Ignoring {}", err),
+        }
+    }
+    read_ron(&Path::new(ASSETS_DIR).join(name))
+}
+
+fn read_ron<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("unreadable {}: {}", path.display(), err))?;
+    ron::from_str(&source).map_err(|err| format!("malformed {}: {}", path.display(), err))
+}
+
+pub struct ModsPlugin;
+
+impl Plugin for ModsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(log_mods);
+    }
+}
+
+fn log_mods() {
+    let mods = load_order();
+    if !mods.is_empty() {
+        info!("Mods, in load order: {}", mods.join(", "));
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine},
+    collision::{
+        AabbKind, ColliderEnabled, CollisionBehavior, CollisionPair, ContactFilterAppExt, Velocity,
+    },
+    dialogue::Dialogue,
+    follow::FollowTarget,
+    interaction::{InteractEvent, InteractionSystem},
+    npc::{AffectionTier, Relationship},
+    phase::{Phase, PhaseExt},
+    settings::Settings,
+    AppState, Controlled, PlayerSystem, PlayerTag, PLAYER_SPEED,
+};
+
+const MOUNT_SPEED: f32 = 120.;
+/// Where the rider sits relative to the mount's origin.
+const RIDE_OFFSET: Vec3 = Vec3::new(0., 10., 0.1);
+/// Where the rider lands relative to the mount's origin when getting off.
+const DISMOUNT_OFFSET: Vec2 = Vec2::new(0., -28.);
+
+/// The player is sitting on `mount`, which is `Controlled` in their stead.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Riding {
+    pub mount: Entity,
+}
+
+pub struct MountPlugin;
+
+impl Plugin for MountPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_contact_filter(rider_passes_mount).add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(toggle_mount.after(InteractionSystem::Interact))
+                .with_system(face_with_mount.after(PlayerSystem::Input)),
+        );
+    }
+}
+
+/// Riders sit inside their mount's AABBs, and shouldn't push it or set off
+/// its sensors.
+fn rider_passes_mount(world: &World, pair: &CollisionPair) -> bool {
+    let rides = |rider: Entity, mount: Entity| {
+        world
+            .get::<Riding>(rider)
+            .map_or(false, |riding| riding.mount == mount)
+    };
+    !rides(pair.ent1, pair.ent2) && !rides(pair.ent2, pair.ent1)
+}
+
+/// Cows following the player that like them enough, see
+/// `AffectionTier::Companion`, can be ridden by interacting with them.
+/// Interacting again gets off. While riding, only the mount's collider is
+/// active and it collides like the player's.
+#[allow(clippy::too_many_arguments)]
+fn toggle_mount(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut interact_events: EventReader<InteractEvent>,
+    mut player_q: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            Option<&Riding>,
+            &Children,
+        ),
+        With<PlayerTag>,
+    >,
+    mut mount_q: Query<
+        (&GlobalTransform, &mut Velocity, &Children),
+        (With<FollowTarget>, Without<Dialogue>, Without<PlayerTag>),
+    >,
+    relationship_q: Query<&Relationship>,
+    aabb_q: Query<&AabbKind>,
+) {
+    let (player, mut player_trans, mut player_vel, riding, player_children) = player_q.single_mut();
+    let colliders = |children: &Children| -> Vec<Entity> {
+        children
+            .iter()
+            .copied()
+            .filter(|child| matches!(aabb_q.get(*child), Ok(AabbKind::Collider)))
+            .collect()
+    };
+
+    if let Some(riding) = riding {
+        // Consume the events for this press, so it doesn't mount right away
+        interact_events.iter().for_each(drop);
+        if !keys.just_pressed(settings.key_bindings.interact) {
+            return;
+        }
+        let (mount_trans, mut mount_vel, mount_children) = match mount_q.get_mut(riding.mount) {
+            Ok(mount) => mount,
+            Err(_) => return,
+        };
+        mount_vel.0 = Vec2::ZERO;
+        player_trans.translation = (mount_trans.translation.xy() + DISMOUNT_OFFSET).extend(0.);
+        commands.entity(riding.mount).remove_children(&[player]);
+        commands.entity(riding.mount).remove::<Controlled>();
+        for collider in colliders(mount_children) {
+            commands.entity(collider).insert(CollisionBehavior::Npc);
+        }
+        commands
+            .entity(player)
+            .remove::<Riding>()
+            .insert(Controlled {
+                speed: PLAYER_SPEED,
+            });
+        for collider in colliders(player_children) {
+            commands.entity(collider).insert(ColliderEnabled(true));
+        }
+        return;
+    }
+
+    for event in interact_events.iter() {
+        let mount_children = match mount_q.get(event.target) {
+            Ok((_, _, children)) => children,
+            Err(_) => continue,
+        };
+        let tier = relationship_q
+            .get(event.target)
+            .map_or(AffectionTier::Stranger, Relationship::tier);
+        if tier < AffectionTier::Companion {
+            continue;
+        }
+        player_vel.0 = Vec2::ZERO;
+        player_trans.translation = RIDE_OFFSET;
+        commands.entity(event.target).push_children(&[player]);
+        commands
+            .entity(event.target)
+            .insert(Controlled { speed: MOUNT_SPEED });
+        for collider in colliders(mount_children) {
+            commands.entity(collider).insert(CollisionBehavior::Player);
+        }
+        commands
+            .entity(player)
+            .remove::<Controlled>()
+            .insert(Riding {
+                mount: event.target,
+            });
+        for collider in colliders(player_children) {
+            commands.entity(collider).insert(ColliderEnabled(false));
+        }
+        return;
+    }
+}
+
+/// Riders sit still, looking where the mount goes.
+fn face_with_mount(
+    mut rider_q: Query<(&Riding, &mut AnimStateMachine)>,
+    mount_q: Query<&AnimStateMachine, Without<Riding>>,
+) {
+    for (riding, mut machine) in rider_q.iter_mut() {
+        if let Ok(mount_machine) = mount_q.get(riding.mount) {
+            machine.face(mount_machine.facing().direction());
+            machine.set_state(AnimState::Idle);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    collections::VecDeque,
+    net::{SocketAddr, UdpSocket},
+};
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_spicy_aseprite::{AsepriteAnimation, AsepriteBundle};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine, Facing},
+    frame_budget::TimedExt,
+    origin::WorldOrigin,
+    phase::{Phase, PhaseExt},
+    sprites, PlayerTag,
+};
+
+/// Prefix of every packet, so stray traffic on the port is ignored.
+const MAGIC: &[u8; 4] = b"MX1P";
+const PACKET_LEN: usize = 18;
+const SEND_SECS: f32 = 0.05;
+/// Remote players are shown this far in the past, so there are usually two
+/// states to interpolate between.
+const INTERP_DELAY_SECS: f64 = 0.1;
+/// Remote players that go quiet for this long are despawned.
+const TIMEOUT_SECS: f64 = 5.;
+/// Received states kept for interpolation.
+const MAX_SAMPLES: usize = 16;
+
+/// One player's state as sent over the wire.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlayerPacket {
+    seq: u32,
+    position: Vec2,
+    state: AnimState,
+    facing: Facing,
+}
+
+impl PlayerPacket {
+    fn encode(&self) -> [u8; PACKET_LEN] {
+        let mut bytes = [0; PACKET_LEN];
+        bytes[0..4].copy_from_slice(MAGIC);
+        bytes[4..8].copy_from_slice(&self.seq.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.position.x.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.position.y.to_le_bytes());
+        bytes[16] = match self.state {
+            AnimState::Idle => 0,
+            AnimState::Walk => 1,
+            AnimState::Sleep => 2,
+            AnimState::Attack => 3,
+            AnimState::Eat => 4,
+            AnimState::Pet => 5,
+        };
+        bytes[17] = match self.facing {
+            Facing::North => 0,
+            Facing::East => 1,
+            Facing::South => 2,
+            Facing::West => 3,
+        };
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PACKET_LEN || &bytes[0..4] != MAGIC {
+            return None;
+        }
+        let f32_at = |i: usize| f32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        Some(Self {
+            seq: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            position: Vec2::new(f32_at(8), f32_at(12)),
+            state: match bytes[16] {
+                0 => AnimState::Idle,
+                1 => AnimState::Walk,
+                2 => AnimState::Sleep,
+                3 => AnimState::Attack,
+                4 => AnimState::Eat,
+                5 => AnimState::Pet,
+                _ => return None,
+            },
+            facing: match bytes[17] {
+                0 => Facing::North,
+                1 => Facing::East,
+                2 => Facing::South,
+                3 => Facing::West,
+                _ => return None,
+            },
+        })
+    }
+}
+
+/// Experimental peer to peer session with one other player on the LAN.
+///
+/// Both sides bind a UDP socket and send their player's position and
+/// animation state to the other; there is no server and no reliability.
+pub struct NetSession {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    send_timer: Timer,
+    next_seq: u32,
+    last_received_seq: Option<u32>,
+    last_received_at: f64,
+}
+
+impl NetSession {
+    /// Started with `--net <bind address> <peer address>` on the command
+    /// line, e.g. `--net 0.0.0.0:7777 192.168.1.20:7777`.
+    fn from_args(args: &[String]) -> Option<Self> {
+        let (bind, peer) = match args {
+            [flag, bind, peer, ..] if flag == "--net" => (bind, peer),
+            [flag, ..] if flag == "--net" => {
+                warn!("Usage: --net <bind address> <peer address>");
+                return None;
+            }
+            _ => return None,
+        };
+        let result = peer
+            .parse::<SocketAddr>()
+            .map_err(|err| err.to_string())
+            .and_then(|peer| {
+                let socket = UdpSocket::bind(bind).map_err(|err| err.to_string())?;
+                socket
+                    .set_nonblocking(true)
+                    .map_err(|err| err.to_string())?;
+                Ok((socket, peer))
+            });
+        match result {
+            Ok((socket, peer)) => Some(Self {
+                socket,
+                peer,
+                send_timer: Timer::from_seconds(SEND_SECS, true),
+                next_seq: 0,
+                last_received_seq: None,
+                last_received_at: 0.,
+            }),
+            Err(err) => {
+                warn!("Failed to start networking: {}", err);
+                None
+            }
+        }
+    }
+}
+
+/// The other player, driven by packets instead of input.
+#[derive(Component, Default)]
+pub struct RemotePlayer {
+    /// (receive time, state), oldest first.
+    samples: VecDeque<(f64, PlayerPacket)>,
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NetSystem {
+    Receive,
+}
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(start_networking).add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Gameplay)
+                .with_system(send_player_state)
+                .with_system(receive_player_state.timed().label(NetSystem::Receive))
+                .with_system(interpolate_remote_players.after(NetSystem::Receive)),
+        );
+    }
+}
+
+fn start_networking(mut commands: Commands) {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(session) = NetSession::from_args(&args) {
+        info!("Networking with {}", session.peer);
+        commands.insert_resource(session);
+    }
+}
+
+fn send_player_state(
+    time: Res<Time>,
+    session: Option<ResMut<NetSession>>,
+    origin: Res<WorldOrigin>,
+    player_q: Query<(&GlobalTransform, &AnimStateMachine), With<PlayerTag>>,
+) {
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+    if !session.send_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let (trans, machine) = match player_q.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let packet = PlayerPacket {
+        seq: session.next_seq,
+        position: origin.to_world(trans.translation.xy()),
+        state: machine.state(),
+        facing: machine.facing(),
+    };
+    session.next_seq = session.next_seq.wrapping_add(1);
+    // Dropped packets are fine, the next one supersedes them anyway
+    let _ = session.socket.send_to(&packet.encode(), session.peer);
+}
+
+fn receive_player_state(
+    mut commands: Commands,
+    time: Res<Time>,
+    session: Option<ResMut<NetSession>>,
+    origin: Res<WorldOrigin>,
+    mut remote_q: Query<(Entity, &mut RemotePlayer)>,
+) {
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+    let now = time.seconds_since_startup();
+    let mut received = Vec::new();
+    let mut buf = [0; 64];
+    while let Ok((len, from)) = session.socket.recv_from(&mut buf) {
+        if from != session.peer {
+            continue;
+        }
+        let packet = match PlayerPacket::decode(&buf[..len]) {
+            Some(packet) => packet,
+            None => continue,
+        };
+        // Drop packets that arrive out of order
+        let newer = session
+            .last_received_seq
+            .map_or(true, |last| packet.seq.wrapping_sub(last) as i32 > 0);
+        if newer {
+            session.last_received_seq = Some(packet.seq);
+            session.last_received_at = now;
+            received.push(packet);
+        }
+    }
+
+    match remote_q.get_single_mut() {
+        Ok((remote, mut remote_player)) => {
+            if received.is_empty() && now - session.last_received_at > TIMEOUT_SECS {
+                info!("Remote player timed out");
+                commands.entity(remote).despawn_recursive();
+                session.last_received_seq = None;
+                return;
+            }
+            for packet in received {
+                remote_player.samples.push_back((now, packet));
+            }
+            while remote_player.samples.len() > MAX_SAMPLES {
+                remote_player.samples.pop_front();
+            }
+        }
+        Err(_) => {
+            let first = match received.last() {
+                Some(packet) => *packet,
+                None => return,
+            };
+            info!("Remote player joined from {}", session.peer);
+            let mut remote_player = RemotePlayer::default();
+            remote_player.samples.push_back((now, first));
+            commands
+                .spawn_bundle(AsepriteBundle {
+                    aseprite: sprites::Player::sprite(),
+                    animation: AsepriteAnimation::from(sprites::Player::tags::EAST_IDLE),
+                    transform: Transform::from_translation(
+                        origin.to_local(first.position).extend(0.),
+                    ),
+                    ..Default::default()
+                })
+                .insert(Name::new("Remote Player"))
+                .insert(AnimStateMachine::player())
+                .insert(remote_player);
+        }
+    }
+}
+
+/// Moves remote players between the two received states around
+/// `INTERP_DELAY_SECS` ago, or to the latest one if there's nothing newer.
+fn interpolate_remote_players(
+    time: Res<Time>,
+    origin: Res<WorldOrigin>,
+    mut remote_q: Query<(&RemotePlayer, &mut Transform, &mut AnimStateMachine)>,
+) {
+    let render_time = time.seconds_since_startup() - INTERP_DELAY_SECS;
+    for (remote_player, mut trans, mut machine) in remote_q.iter_mut() {
+        let samples = &remote_player.samples;
+        let next = samples
+            .iter()
+            .position(|(received_at, _)| *received_at > render_time);
+        let (position, packet) = match next {
+            Some(i) if i > 0 => {
+                let (t0, from) = samples[i - 1];
+                let (t1, to) = samples[i];
+                let alpha = if t1 > t0 {
+                    ((render_time - t0) / (t1 - t0)) as f32
+                } else {
+                    1.
+                };
+                (from.position.lerp(to.position, alpha), from)
+            }
+            Some(i) => (samples[i].1.position, samples[i].1),
+            None => match samples.back() {
+                Some((_, latest)) => (latest.position, *latest),
+                None => continue,
+            },
+        };
+        let position = origin.to_local(position);
+        trans.translation.x = position.x;
+        trans.translation.y = position.y;
+        machine.face(packet.facing.direction());
+        machine.set_state(packet.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet() -> PlayerPacket {
+        PlayerPacket {
+            seq: 0x0102_0304,
+            position: Vec2::new(-12.5, 4096.25),
+            state: AnimState::Pet,
+            facing: Facing::West,
+        }
+    }
+
+    #[test]
+    fn packet_round_trips() {
+        for state in [
+            AnimState::Idle,
+            AnimState::Walk,
+            AnimState::Sleep,
+            AnimState::Attack,
+            AnimState::Eat,
+            AnimState::Pet,
+        ] {
+            for facing in [Facing::North, Facing::East, Facing::South, Facing::West] {
+                let sent = PlayerPacket {
+                    state,
+                    facing,
+                    ..packet()
+                };
+                assert_eq!(PlayerPacket::decode(&sent.encode()), Some(sent));
+            }
+        }
+    }
+
+    #[test]
+    fn packet_of_wrong_length_is_rejected() {
+        let bytes = packet().encode();
+        assert_eq!(PlayerPacket::decode(&bytes[..PACKET_LEN - 1]), None);
+        let mut longer = bytes.to_vec();
+        longer.push(0);
+        assert_eq!(PlayerPacket::decode(&longer), None);
+        assert_eq!(PlayerPacket::decode(&[]), None);
+    }
+
+    #[test]
+    fn packet_with_wrong_magic_is_rejected() {
+        let mut bytes = packet().encode();
+        bytes[0] ^= 0xff;
+        assert_eq!(PlayerPacket::decode(&bytes), None);
+    }
+
+    #[test]
+    fn packet_with_unknown_state_or_facing_is_rejected() {
+        let mut bytes = packet().encode();
+        bytes[16] = 6;
+        assert_eq!(PlayerPacket::decode(&bytes), None);
+
+        let mut bytes = packet().encode();
+        bytes[17] = 4;
+        assert_eq!(PlayerPacket::decode(&bytes), None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    frame_budget::TimedExt,
+    phase::{Phase, PhaseExt},
+};
+
+const DEFAULT_DURATION: f32 = 3.;
+/// Toasts shown at once, later ones wait for a free spot.
+const MAX_VISIBLE: usize = 4;
+const TOAST_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.6);
+
+/// Shows `text` as a toast in the bottom right corner, above the clock, for
+/// `duration` seconds.
+#[derive(Debug, Clone)]
+pub struct Notify {
+    pub text: String,
+    pub duration: f32,
+}
+
+impl Notify {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            duration: DEFAULT_DURATION,
+        }
+    }
+
+    pub fn with_duration(self, duration: f32) -> Self {
+        Self { duration, ..self }
+    }
+}
+
+#[derive(Component)]
+struct Toast {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct ToastStackTag;
+
+/// Notifications waiting for a free spot on screen.
+#[derive(Default)]
+struct ToastQueue(VecDeque<Notify>);
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NotifySystem {
+    Queue,
+}
+
+pub struct NotifyPlugin;
+
+impl Plugin for NotifyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Notify>()
+            .init_resource::<ToastQueue>()
+            .add_startup_system(setup_toast_stack)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(queue_notifications.timed().label(NotifySystem::Queue))
+                    .with_system(update_toasts.after(NotifySystem::Queue)),
+            );
+    }
+}
+
+fn setup_toast_stack(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(70.),
+                    right: Val::Px(20.),
+                    ..Default::default()
+                },
+                // UI columns grow upwards, newer toasts stack on top
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .insert(ToastStackTag);
+}
+
+fn queue_notifications(mut notifications: EventReader<Notify>, mut queue: ResMut<ToastQueue>) {
+    queue.0.extend(notifications.iter().cloned());
+}
+
+fn update_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut queue: ResMut<ToastQueue>,
+    stack_q: Query<Entity, With<ToastStackTag>>,
+    mut toast_q: Query<(Entity, &mut Toast)>,
+) {
+    let mut visible = 0;
+    for (entity, mut toast) in toast_q.iter_mut() {
+        if toast.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            visible += 1;
+        }
+    }
+
+    let stack = stack_q.single();
+    while visible < MAX_VISIBLE {
+        let notify = match queue.0.pop_front() {
+            Some(notify) => notify,
+            None => break,
+        };
+        let toast = commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    padding: Rect::all(Val::Px(6.)),
+                    margin: Rect {
+                        top: Val::Px(6.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                color: UiColor(TOAST_BACKGROUND),
+                ..Default::default()
+            })
+            .insert(Toast {
+                timer: Timer::from_seconds(notify.duration, false),
+            })
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        notify.text,
+                        TextStyle {
+                            font: asset_server.load("Share-Regular.ttf"),
+                            font_size: 22.,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+            })
+            .id();
+        commands.entity(stack).push_children(&[toast]);
+        visible += 1;
+    }
+}
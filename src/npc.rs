@@ -0,0 +1,350 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine},
+    collision::{SensorEvent, Velocity},
+    cooldown::{Cooldown, TimerAppExt},
+    dialogue::Dialogue,
+    emote::spawn_heart,
+    feeding::Fed,
+    floating_text::SpawnFloatingText,
+    follow::FollowTarget,
+    frame_budget::TimedExt,
+    herd::Herd,
+    hitbox::HitEvent,
+    interaction::{InteractEvent, InteractionSystem},
+    localization::Localization,
+    mount::Riding,
+    patrol::PatrolPath,
+    persist::PersistAppExt,
+    phase::{Phase, PhaseExt},
+    script::Script,
+    shop::Merchant,
+    AppState, CowTag, PlayerTag, Sprinting,
+};
+
+const COW_FLEE_SPEED: f32 = 90.;
+
+#[derive(Component, Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect_value(Component, PartialEq, Serialize, Deserialize)]
+pub enum NpcState {
+    Sleeping,
+    Idle,
+    /// Player is inside the sensor, keep facing them.
+    Watching,
+    /// Player barged in while sprinting, run away until they leave the sensor.
+    Fleeing,
+}
+
+impl Default for NpcState {
+    fn default() -> Self {
+        NpcState::Idle
+    }
+}
+
+/// Affection gained by petting an NPC, at most once every
+/// `PET_COOLDOWN_SECS` per NPC.
+const PET_AFFECTION: u32 = 1;
+const PET_COOLDOWN_SECS: f32 = 5.;
+/// Affection gained by feeding an NPC.
+pub const FEED_AFFECTION: u32 = 2;
+
+/// Steps of a `Relationship`, each unlocking more of the NPC.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AffectionTier {
+    Stranger,
+    Acquaintance,
+    /// Follows the player around, see `follow::recruit_companions`.
+    Friend,
+    /// Lets the player ride it, see `mount::toggle_mount`.
+    Companion,
+}
+
+impl AffectionTier {
+    const ALL: [AffectionTier; 4] = [
+        AffectionTier::Stranger,
+        AffectionTier::Acquaintance,
+        AffectionTier::Friend,
+        AffectionTier::Companion,
+    ];
+
+    /// Affection needed to reach the tier.
+    fn threshold(self) -> u32 {
+        match self {
+            AffectionTier::Stranger => 0,
+            AffectionTier::Acquaintance => 2,
+            AffectionTier::Friend => 5,
+            AffectionTier::Companion => 10,
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            AffectionTier::Stranger => "relationship.stranger",
+            AffectionTier::Acquaintance => "relationship.acquaintance",
+            AffectionTier::Friend => "relationship.friend",
+            AffectionTier::Companion => "relationship.companion",
+        }
+    }
+}
+
+/// How much an NPC likes the player, raised by petting and feeding it.
+#[derive(Component, Reflect, Debug, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct Relationship {
+    pub affection: u32,
+}
+
+impl Relationship {
+    pub fn tier(&self) -> AffectionTier {
+        AffectionTier::ALL
+            .into_iter()
+            .rev()
+            .find(|tier| self.affection >= tier.threshold())
+            .unwrap_or(AffectionTier::Stranger)
+    }
+
+    /// Raises the affection, returning the tier reached if it went up one.
+    pub fn raise(&mut self, amount: u32) -> Option<AffectionTier> {
+        let before = self.tier();
+        self.affection += amount;
+        Some(self.tier()).filter(|tier| *tier != before)
+    }
+}
+
+/// Shows a tier an NPC's `Relationship` just reached above its head.
+pub fn announce_tier(
+    floating_texts: &mut EventWriter<SpawnFloatingText>,
+    localization: &Localization,
+    npc: Entity,
+    tier: AffectionTier,
+) {
+    floating_texts.send(SpawnFloatingText {
+        anchor: npc,
+        text: localization.get(tier.key()),
+        color: Color::PINK,
+    });
+}
+
+/// Label of the systems changing NPC states in reaction to the player.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NpcSystem {
+    Reactions,
+}
+
+/// Tells apart the `Cooldown` of petting an NPC, inserted the first time
+/// it's petted.
+struct Petting;
+
+pub struct NpcPlugin;
+
+impl Plugin for NpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.persist_component::<Relationship>()
+            .persist_component::<NpcState>()
+            .add_cooldown::<Petting>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Ai)
+                    .with_system(npc_sensor_reactions.timed().label(NpcSystem::Reactions))
+                    .with_system(
+                        pet_npcs
+                            .timed()
+                            .label(NpcSystem::Reactions)
+                            .after(InteractionSystem::Interact),
+                    )
+                    .with_system(flee_from_hits.timed().label(NpcSystem::Reactions))
+                    .with_system(cow_steering.after(NpcSystem::Reactions)),
+            );
+    }
+}
+
+fn npc_sensor_reactions(
+    mut sensor_events: EventReader<SensorEvent>,
+    player_q: Query<(&GlobalTransform, &Velocity, &Sprinting), With<PlayerTag>>,
+    mut npc_q: Query<(&GlobalTransform, &mut NpcState)>,
+) {
+    let _span = info_span!("ai").entered();
+    for event in sensor_events.iter() {
+        match *event {
+            SensorEvent::Entered { sensor, other } => {
+                let (player_trans, player_vel, sprinting) = match player_q.get(other) {
+                    Ok(player) => player,
+                    Err(_) => continue,
+                };
+                if let Ok((npc_trans, mut state)) = npc_q.get_mut(sensor) {
+                    let to_npc = npc_trans.translation.xy() - player_trans.translation.xy();
+                    let approaching = player_vel.0.dot(to_npc) > 0.;
+                    *state = if sprinting.0 && approaching {
+                        NpcState::Fleeing
+                    } else {
+                        NpcState::Watching
+                    };
+                    debug!(npc = ?sensor, state = ?*state, "Player nearby");
+                }
+            }
+            SensorEvent::Exited { sensor, other } => {
+                if player_q.get(other).is_err() {
+                    continue;
+                }
+                if let Ok((_, mut state)) = npc_q.get_mut(sensor) {
+                    if matches!(*state, NpcState::Watching | NpcState::Fleeing) {
+                        *state = NpcState::Idle;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Interacting with an NPC pets it, unless the interaction does something
+/// else: talking, trading, running a script, milking a fed cow or getting on
+/// or off a mount.
+fn pet_npcs(
+    mut commands: Commands,
+    localization: Res<Localization>,
+    mut interact_events: EventReader<InteractEvent>,
+    mut floating_texts: EventWriter<SpawnFloatingText>,
+    mut player_q: Query<
+        (&GlobalTransform, &mut AnimStateMachine, Option<&Riding>),
+        With<PlayerTag>,
+    >,
+    mut npc_q: Query<
+        (
+            &GlobalTransform,
+            &mut Relationship,
+            &mut NpcState,
+            Option<&FollowTarget>,
+            Option<&mut Cooldown<Petting>>,
+        ),
+        (
+            Without<PlayerTag>,
+            Without<Dialogue>,
+            Without<Merchant>,
+            Without<Script>,
+            Without<Fed>,
+        ),
+    >,
+) {
+    let (player_trans, mut machine, riding) = player_q.single_mut();
+    // The press gets off the mount, see `mount::toggle_mount`
+    if riding.is_some() {
+        interact_events.iter().for_each(drop);
+        return;
+    }
+    for event in interact_events.iter() {
+        let (npc_trans, mut relationship, mut state, follow, cooldown) =
+            match npc_q.get_mut(event.target) {
+                Ok(npc) => npc,
+                Err(_) => continue,
+            };
+        // The press gets on it instead
+        if follow.is_some() && relationship.tier() >= AffectionTier::Companion {
+            continue;
+        }
+        // Petting again right away is still petting, but doesn't count for
+        // more affection
+        let affectionate = match cooldown {
+            Some(mut cooldown) => {
+                let ready = cooldown.is_ready();
+                if ready {
+                    cooldown.reset();
+                }
+                ready
+            }
+            None => {
+                commands
+                    .entity(event.target)
+                    .insert(Cooldown::<Petting>::started(PET_COOLDOWN_SECS));
+                true
+            }
+        };
+        if affectionate {
+            if let Some(tier) = relationship.raise(PET_AFFECTION) {
+                announce_tier(&mut floating_texts, &localization, event.target, tier);
+            }
+        }
+        if *state == NpcState::Sleeping {
+            *state = NpcState::Watching;
+        }
+        spawn_heart(&mut commands, event.target);
+
+        // Face the cow being petted
+        machine.face(npc_trans.translation.xy() - player_trans.translation.xy());
+        machine.set_state(AnimState::Pet);
+    }
+}
+
+/// Keeps a fed cow put until it's done eating, then lets it go back to idle.
+/// Returns whether it's still eating, in which case steering leaves it be.
+pub fn still_eating(machine: &mut AnimStateMachine, velocity: &mut Velocity) -> bool {
+    if machine.state() == AnimState::Eat && !machine.set_state(AnimState::Idle) {
+        velocity.0 = Vec2::ZERO;
+        return true;
+    }
+    false
+}
+
+fn flee_from_hits(mut hits: EventReader<HitEvent>, mut npc_q: Query<&mut NpcState>) {
+    let _span = info_span!("ai").entered();
+    for hit in hits.iter() {
+        if let Ok(mut state) = npc_q.get_mut(hit.target) {
+            *state = NpcState::Fleeing;
+            debug!(npc = ?hit.target, "Fleeing from a hit");
+        }
+    }
+}
+
+fn cow_steering(
+    player_q: Query<&GlobalTransform, With<PlayerTag>>,
+    mut cow_q: Query<
+        (
+            &GlobalTransform,
+            &NpcState,
+            Option<&Herd>,
+            Option<&PatrolPath>,
+            &mut Velocity,
+            &mut AnimStateMachine,
+        ),
+        (With<CowTag>, Without<FollowTarget>, Without<Script>),
+    >,
+) {
+    let player_trans = player_q.single();
+    for (cow_trans, state, herd, patrol, mut velocity, mut machine) in cow_q.iter_mut() {
+        if still_eating(&mut machine, &mut velocity) {
+            continue;
+        }
+        // Idle herd members graze together, see `herd::flock`, and idle
+        // patrollers walk their path, see `patrol::patrol`
+        if *state == NpcState::Idle && (herd.is_some() || patrol.is_some()) {
+            continue;
+        }
+
+        let to_player = player_trans.translation.xy() - cow_trans.translation.xy();
+        match state {
+            NpcState::Sleeping => {
+                velocity.0 = Vec2::ZERO;
+                machine.set_state(AnimState::Sleep);
+            }
+            NpcState::Idle => {
+                velocity.0 = Vec2::ZERO;
+                // Keep facing the same way, unless just woken up
+                if machine.state() == AnimState::Sleep {
+                    machine.face(to_player);
+                }
+                machine.set_state(AnimState::Idle);
+            }
+            NpcState::Watching => {
+                velocity.0 = Vec2::ZERO;
+                machine.face(to_player);
+                machine.set_state(AnimState::Idle);
+            }
+            NpcState::Fleeing => {
+                velocity.0 = -to_player.normalize_or_zero() * COW_FLEE_SPEED;
+                machine.face(velocity.0);
+                machine.set_state(AnimState::Walk);
+            }
+        }
+    }
+}
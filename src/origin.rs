@@ -0,0 +1,127 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    chunk::CHUNK_SIZE,
+    collision::{CollisionWorld, CollisionWorlds},
+    follow::FollowPath,
+    herd::Herd,
+    patrol::{Patrol, PatrolPath},
+    rollback::RollbackBuffer,
+    AppState, PlayerTag,
+};
+
+/// How far the player can get from the origin before the world is shifted
+/// back around them. f32 positions start losing sub-pixel precision a few
+/// thousand pixels out.
+const SHIFT_DISTANCE: f32 = CHUNK_SIZE * 16.;
+
+/// World position of the origin of the transforms, moved along by
+/// `shift_origin` on large maps so the player stays close to it.
+///
+/// Transforms and the collision world are relative to the origin. Anything
+/// in world coordinates, like level layouts, saves and network packets, goes
+/// through `to_local` and `to_world`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WorldOrigin(pub Vec2);
+
+impl WorldOrigin {
+    pub fn to_local(&self, world: Vec2) -> Vec2 {
+        world - self.0
+    }
+
+    pub fn to_world(&self, local: Vec2) -> Vec2 {
+        local + self.0
+    }
+}
+
+pub struct OriginPlugin;
+
+impl Plugin for OriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldOrigin>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(recenter_origin.exclusive_system().at_start()),
+            )
+            // The editor works in world coordinates
+            .add_system_set(
+                SystemSet::on_enter(AppState::Editor)
+                    .with_system(reset_origin.exclusive_system().at_start()),
+            );
+    }
+}
+
+/// Moves everything by `-offset`, so what was at `offset` is at the origin.
+///
+/// Shifts root transforms and every global transform, so the world is
+/// consistent before transforms are propagated again, as well as the
+/// collision worlds and components holding positions, like herd homes and
+/// patrol and follow waypoints. Rollback snapshots are dropped rather than
+/// shifted.
+pub fn shift_origin(world: &mut World, offset: Vec2) {
+    if offset == Vec2::ZERO {
+        return;
+    }
+    let offset3 = offset.extend(0.);
+    let mut root_q = world.query_filtered::<&mut Transform, (Without<Parent>, Without<Node>)>();
+    for mut trans in root_q.iter_mut(world) {
+        trans.translation -= offset3;
+    }
+    let mut global_q = world.query_filtered::<&mut GlobalTransform, Without<Node>>();
+    for mut trans in global_q.iter_mut(world) {
+        trans.translation -= offset3;
+    }
+    for mut herd in world.query::<&mut Herd>().iter_mut(world) {
+        herd.home -= offset;
+    }
+    for mut path in world.query::<&mut PatrolPath>().iter_mut(world) {
+        for point in path.points.iter_mut() {
+            *point -= offset;
+        }
+    }
+    for mut path in world.query::<&mut FollowPath>().iter_mut(world) {
+        path.shift(offset);
+    }
+    for mut patrol in world.query::<&mut Patrol>().iter_mut(world) {
+        patrol.shift(offset);
+    }
+    world
+        .get_resource_mut::<CollisionWorld>()
+        .unwrap()
+        .shift(offset);
+    world
+        .get_resource_mut::<CollisionWorlds>()
+        .unwrap()
+        .shift_inactive(offset);
+    world.get_resource_mut::<RollbackBuffer>().unwrap().clear();
+
+    let mut origin = world.get_resource_mut::<WorldOrigin>().unwrap();
+    origin.0 += offset;
+    info!(
+        "World origin shifted to ({:.0}, {:.0})",
+        origin.0.x, origin.0.y
+    );
+}
+
+/// Shifts the world so that `origin` is its origin.
+pub fn move_origin_to(world: &mut World, origin: Vec2) {
+    let current = world.get_resource::<WorldOrigin>().unwrap().0;
+    shift_origin(world, origin - current);
+}
+
+/// Shifts by whole chunks, so chunk borders stay where they are in local
+/// coordinates too.
+fn recenter_origin(world: &mut World) {
+    let mut player_q = world.query_filtered::<&GlobalTransform, With<PlayerTag>>();
+    let position = match player_q.iter(world).next() {
+        Some(trans) => trans.translation.xy(),
+        None => return,
+    };
+    if position.abs().max_element() > SHIFT_DISTANCE {
+        shift_origin(world, (position / CHUNK_SIZE).round() * CHUNK_SIZE);
+    }
+}
+
+fn reset_origin(world: &mut World) {
+    move_origin_to(world, Vec2::ZERO);
+}
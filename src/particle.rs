@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder},
+    shapes,
+};
+
+use crate::{
+    collision::{ImpactEvent, Velocity},
+    frame_budget::TimedExt,
+    layer::RenderLayerGroup,
+    lifetime::{Lifetime, OnExpire},
+    phase::{Phase, PhaseExt},
+    pool::EntityPool,
+    time_scale::TimeScale,
+    PlayerTag,
+};
+
+const DUST_COLOR: Color = Color::rgba(0.8, 0.7, 0.55, 0.8);
+/// Offset from the player's origin to their feet, in sprite pixels.
+const FEET_OFFSET: f32 = 14.;
+
+/// Request to emit `count` particles spreading out from `position`.
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleBurst {
+    pub position: Vec2,
+    /// Particles spread in a fan of `spread` radians around this direction.
+    /// A zero direction spreads them all around.
+    pub direction: Vec2,
+    pub spread: f32,
+    pub count: u32,
+    pub speed: f32,
+    pub lifetime: f32,
+    pub color: Color,
+}
+
+impl ParticleBurst {
+    pub fn dust(position: Vec2, direction: Vec2, count: u32) -> Self {
+        Self {
+            position,
+            direction,
+            spread: std::f32::consts::FRAC_PI_2,
+            count,
+            speed: 12.,
+            lifetime: 0.4,
+            color: DUST_COLOR,
+        }
+    }
+}
+
+/// Pooled, released once its `Lifetime` is up.
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    color: Color,
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ParticleSystem {
+    Emit,
+    Spawn,
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityPool<Particle>>()
+            .add_event::<ParticleBurst>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(footstep_dust.timed().label(ParticleSystem::Emit))
+                    .with_system(impact_dust.timed().label(ParticleSystem::Emit))
+                    .with_system(
+                        spawn_particles
+                            .timed()
+                            .label(ParticleSystem::Spawn)
+                            .after(ParticleSystem::Emit),
+                    )
+                    .with_system(update_particles.after(ParticleSystem::Spawn)),
+            );
+    }
+}
+
+fn footstep_dust(
+    mut bursts: EventWriter<ParticleBurst>,
+    player_q: Query<
+        (&GlobalTransform, &TextureAtlasSprite, &Velocity),
+        (With<PlayerTag>, Changed<TextureAtlasSprite>),
+    >,
+) {
+    for (trans, sprite, velocity) in player_q.iter() {
+        // A puff every other frame of the walk cycle, i.e. once per step
+        if velocity.0 == Vec2::ZERO || sprite.index % 2 != 0 {
+            continue;
+        }
+        let feet = trans.translation.truncate() - Vec2::new(0., FEET_OFFSET);
+        bursts.send(ParticleBurst::dust(feet, -velocity.0, 3));
+    }
+}
+
+fn impact_dust(mut impacts: EventReader<ImpactEvent>, mut bursts: EventWriter<ParticleBurst>) {
+    for impact in impacts.iter() {
+        bursts.send(ParticleBurst::dust(impact.position, impact.normal, 6));
+    }
+}
+
+fn spawn_particles(
+    mut commands: Commands,
+    mut bursts: EventReader<ParticleBurst>,
+    mut pool: ResMut<EntityPool<Particle>>,
+) {
+    for burst in bursts.iter() {
+        let base_angle = burst.direction.y.atan2(burst.direction.x);
+        let spread = if burst.direction == Vec2::ZERO {
+            std::f32::consts::TAU
+        } else {
+            burst.spread
+        };
+        for i in 0..burst.count {
+            // Spread evenly across the fan
+            let t = (i as f32 + 0.5) / burst.count as f32 - 0.5;
+            let angle = base_angle + t * spread;
+            let particle = Particle {
+                velocity: Vec2::new(angle.cos(), angle.sin()) * burst.speed,
+                color: burst.color,
+            };
+            pool.acquire(
+                &mut commands,
+                (
+                    particle,
+                    Lifetime::from_seconds(burst.lifetime).keep(),
+                    Transform::from_translation(
+                        burst.position.extend(RenderLayerGroup::UiWorld.z()),
+                    ),
+                    DrawMode::Fill(FillMode::color(burst.color)),
+                ),
+                |commands| {
+                    commands
+                        .spawn_bundle(GeometryBuilder::build_as(
+                            &shapes::Circle {
+                                radius: 1.,
+                                center: Vec2::ZERO,
+                            },
+                            DrawMode::Fill(FillMode::color(burst.color)),
+                            Transform::default(),
+                        ))
+                        .id()
+                },
+            );
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut pool: ResMut<EntityPool<Particle>>,
+    mut expired: EventReader<OnExpire>,
+    mut particle_q: Query<(&Particle, &Lifetime, &mut Transform, &mut DrawMode)>,
+) {
+    for OnExpire { entity } in expired.iter() {
+        if particle_q.get(*entity).is_ok() {
+            pool.release(&mut commands, *entity);
+        }
+    }
+
+    for (particle, lifetime, mut transform, mut draw_mode) in particle_q.iter_mut() {
+        if lifetime.finished() {
+            continue;
+        }
+
+        transform.translation += (particle.velocity * time_scale.delta_seconds(&time)).extend(0.);
+        let mut color = particle.color;
+        color.set_a(color.a() * (1. - lifetime.percent()));
+        *draw_mode = DrawMode::Fill(FillMode::color(color));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use bevy::prelude::*;
+
+use crate::collision::{CollisionBehavior, CollisionWorld, QueryMask};
+
+/// Side length of a grid cell, in world pixels.
+const CELL_SIZE: f32 = 16.;
+/// Gives up on paths needing more cells to be expanded than this.
+const MAX_EXPANDED: usize = 2000;
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+fn cell_of(point: Vec2) -> IVec2 {
+    (point / CELL_SIZE).round().as_ivec2()
+}
+
+fn cell_center(cell: IVec2) -> Vec2 {
+    cell.as_vec2() * CELL_SIZE
+}
+
+/// Octile distance, admissible for 8-way movement with the costs above.
+fn heuristic(from: IVec2, to: IVec2) -> u32 {
+    let delta = (to - from).abs();
+    let (long, short) = (delta.max_element() as u32, delta.min_element() as u32);
+    STRAIGHT_COST * (long - short) + DIAGONAL_COST * short
+}
+
+/// A* over a grid laid on the static colliders in `collision_world`, for a
+/// box with the given half extents. Returns the waypoints after `start` up
+/// to and including `goal`, or `None` if the goal can't be reached.
+pub fn find_path(
+    collision_world: &CollisionWorld,
+    start: Vec2,
+    goal: Vec2,
+    half_extents: Vec2,
+) -> Option<Vec<Vec2>> {
+    let is_blocked = |cell: IVec2| {
+        let center = cell_center(cell);
+        !collision_world
+            .query_region(
+                center - half_extents,
+                center + half_extents,
+                QueryMask::COLLIDERS.with_behavior(CollisionBehavior::Static),
+            )
+            .is_empty()
+    };
+
+    let (start_cell, goal_cell) = (cell_of(start), cell_of(goal));
+    if is_blocked(goal_cell) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<IVec2, IVec2> = HashMap::new();
+    let mut costs: HashMap<IVec2, u32> = HashMap::new();
+    open.push(Reverse((
+        heuristic(start_cell, goal_cell),
+        start_cell.x,
+        start_cell.y,
+    )));
+    costs.insert(start_cell, 0);
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, x, y))) = open.pop() {
+        let cell = IVec2::new(x, y);
+        if cell == goal_cell {
+            let mut waypoints = vec![goal];
+            let mut current = cell;
+            while let Some(previous) = came_from.get(&current) {
+                if *previous != start_cell {
+                    waypoints.push(cell_center(*previous));
+                }
+                current = *previous;
+            }
+            waypoints.reverse();
+            return Some(waypoints);
+        }
+
+        expanded += 1;
+        if expanded > MAX_EXPANDED {
+            return None;
+        }
+
+        let cost = costs[&cell];
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let next = cell + IVec2::new(dx, dy);
+                // Don't cut corners diagonally past a blocked cell
+                let diagonal = dx != 0 && dy != 0;
+                if is_blocked(next)
+                    || (diagonal
+                        && (is_blocked(cell + IVec2::new(dx, 0))
+                            || is_blocked(cell + IVec2::new(0, dy))))
+                {
+                    continue;
+                }
+                let next_cost = cost
+                    + if diagonal {
+                        DIAGONAL_COST
+                    } else {
+                        STRAIGHT_COST
+                    };
+                if costs.get(&next).map_or(true, |known| next_cost < *known) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    let estimate = next_cost + heuristic(next, goal_cell);
+                    open.push(Reverse((estimate, next.x, next.y)));
+                }
+            }
+        }
+    }
+    None
+}
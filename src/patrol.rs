@@ -0,0 +1,263 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, StrokeMode},
+    shapes,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine},
+    collision::{CollisionBehavior, CollisionWorld, QueryMask, Velocity},
+    follow::FollowTarget,
+    frame_budget::TimedExt,
+    layer::RenderLayerGroup,
+    level::COW_COLLIDER_SIZE,
+    npc::{still_eating, NpcState, NpcSystem},
+    pathfinding::find_path,
+    phase::{Phase, PhaseExt},
+    script::Script,
+    time_scale::TimeScale,
+    AppState, Controlled, DebugRenderTag,
+};
+
+const PATROL_SPEED: f32 = 30.;
+const REPATH_SECS: f32 = 1.;
+/// Patrol points and path waypoints closer than this count as reached.
+const POINT_RADIUS: f32 = 4.;
+const PATH_COLOR: Color = Color::CYAN;
+/// Depth in the ground band, above footprints.
+const PATH_DEPTH: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatrolMode {
+    /// Back to the first point after the last.
+    Loop,
+    /// Back and forth along the points.
+    PingPong,
+}
+
+/// Walks an idle NPC along `points`, in world coordinates, pathfinding around
+/// static colliders between them.
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PatrolPath {
+    pub points: Vec<Vec2>,
+    pub mode: PatrolMode,
+}
+
+impl PatrolPath {
+    /// Index of the point to head to after `current`, and the direction to
+    /// keep going in for `PingPong`.
+    fn next(&self, current: usize, forward: bool) -> (usize, bool) {
+        let last = self.points.len().saturating_sub(1);
+        match self.mode {
+            PatrolMode::Loop => ((current + 1) % self.points.len().max(1), true),
+            PatrolMode::PingPong if last == 0 => (0, true),
+            PatrolMode::PingPong => {
+                let forward = if current == last {
+                    false
+                } else if current == 0 {
+                    true
+                } else {
+                    forward
+                };
+                (if forward { current + 1 } else { current - 1 }, forward)
+            }
+        }
+    }
+}
+
+/// Progress along a `PatrolPath`.
+#[derive(Component)]
+pub struct Patrol {
+    target: usize,
+    forward: bool,
+    /// Path to the target point, used while it isn't in direct line of sight.
+    waypoints: Vec<Vec2>,
+    repath: Timer,
+}
+
+impl Patrol {
+    /// Moves the waypoints by `-offset` along with the world origin, see
+    /// `origin::shift_origin`.
+    pub fn shift(&mut self, offset: Vec2) {
+        for waypoint in self.waypoints.iter_mut() {
+            *waypoint -= offset;
+        }
+    }
+}
+
+impl Default for Patrol {
+    fn default() -> Self {
+        Self {
+            target: 0,
+            forward: true,
+            waypoints: Vec::new(),
+            repath: Timer::from_seconds(REPATH_SECS, true),
+        }
+    }
+}
+
+/// Debug drawing of a `PatrolPath`, a child of the patrolling NPC.
+#[derive(Component)]
+struct PatrolPathShape;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PatrolSystem {
+    SpawnPathShapes,
+}
+
+pub struct PatrolPlugin;
+
+impl Plugin for PatrolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Ai)
+                .with_system(patrol.after(NpcSystem::Reactions)),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(
+                    spawn_path_shapes
+                        .timed()
+                        .label(PatrolSystem::SpawnPathShapes),
+                )
+                .with_system(pin_path_shapes.after(PatrolSystem::SpawnPathShapes)),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn patrol(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    collision_world: Res<CollisionWorld>,
+    mut patroller_q: Query<
+        (
+            Entity,
+            &PatrolPath,
+            &NpcState,
+            &GlobalTransform,
+            &mut Velocity,
+            &mut AnimStateMachine,
+            Option<&mut Patrol>,
+        ),
+        (Without<FollowTarget>, Without<Controlled>, Without<Script>),
+    >,
+) {
+    for (entity, path, state, trans, mut velocity, mut machine, patrol) in patroller_q.iter_mut() {
+        let mut patrol = match patrol {
+            Some(patrol) => patrol,
+            None => {
+                commands.entity(entity).insert(Patrol::default());
+                continue;
+            }
+        };
+        // Watching, fleeing and sleeping are up to `npc::cow_steering`
+        if *state != NpcState::Idle || path.points.is_empty() {
+            continue;
+        }
+        if still_eating(&mut machine, &mut velocity) {
+            continue;
+        }
+
+        let position = trans.translation.xy();
+        patrol.target = patrol.target.min(path.points.len() - 1);
+        if path.points[patrol.target].distance(position) < POINT_RADIUS {
+            let (target, forward) = path.next(patrol.target, patrol.forward);
+            patrol.target = target;
+            patrol.forward = forward;
+            patrol.waypoints.clear();
+        }
+        let target_pos = path.points[patrol.target];
+        let to_target = target_pos - position;
+
+        let in_sight = collision_world
+            .aabb_cast(
+                position,
+                COW_COLLIDER_SIZE / 2.,
+                to_target,
+                to_target.length(),
+                QueryMask {
+                    exclude: Some(entity),
+                    ..QueryMask::COLLIDERS.with_behavior(CollisionBehavior::Static)
+                },
+            )
+            .is_none();
+        let heading = if in_sight {
+            patrol.waypoints.clear();
+            to_target
+        } else {
+            patrol.repath.tick(time_scale.delta(&time));
+            if patrol.waypoints.is_empty() || patrol.repath.just_finished() {
+                patrol.waypoints = find_path(
+                    &collision_world,
+                    position,
+                    target_pos,
+                    COW_COLLIDER_SIZE / 2.,
+                )
+                .unwrap_or_default();
+            }
+            while patrol
+                .waypoints
+                .first()
+                .map_or(false, |waypoint| waypoint.distance(position) < POINT_RADIUS)
+            {
+                patrol.waypoints.remove(0);
+            }
+            patrol
+                .waypoints
+                .first()
+                .map_or(Vec2::ZERO, |waypoint| *waypoint - position)
+        };
+
+        velocity.0 = heading.normalize_or_zero() * PATROL_SPEED;
+        machine.face(velocity.0);
+        machine.set_state(if velocity.0 == Vec2::ZERO {
+            AnimState::Idle
+        } else {
+            AnimState::Walk
+        });
+    }
+}
+
+fn spawn_path_shapes(
+    mut commands: Commands,
+    path_q: Query<(Entity, &PatrolPath), Added<PatrolPath>>,
+) {
+    for (entity, path) in path_q.iter() {
+        let shape = commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Polygon {
+                    points: path.points.clone(),
+                    closed: path.mode == PatrolMode::Loop,
+                },
+                DrawMode::Outlined {
+                    fill_mode: FillMode::color(Color::NONE),
+                    outline_mode: StrokeMode::color(PATH_COLOR),
+                },
+                Transform::default(),
+            ))
+            .insert(PatrolPathShape)
+            .insert(DebugRenderTag)
+            .id();
+        commands.entity(entity).push_children(&[shape]);
+    }
+}
+
+/// Path points are in world coordinates, so undo the NPC's movement.
+fn pin_path_shapes(
+    patroller_q: Query<(&Transform, &Children), With<PatrolPath>>,
+    mut shape_q: Query<&mut Transform, (With<PatrolPathShape>, Without<PatrolPath>)>,
+) {
+    for (trans, children) in patroller_q.iter() {
+        for child in children.iter() {
+            if let Ok(mut shape_trans) = shape_q.get_mut(*child) {
+                shape_trans.translation = (-trans.translation.xy())
+                    .extend(RenderLayerGroup::Ground.z() + PATH_DEPTH - trans.translation.z);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin, StrokeMode},
+    shapes,
+};
+
+use crate::{
+    collision::{AabbBundle, AabbKind, CollisionBehavior, SensorEvent},
+    layer::RenderLayerGroup,
+    phase::{Phase, PhaseExt},
+    quest::{Objective, QuestCompleted, QuestLog, QuestSystem},
+    set_state, AppState, CowTag,
+};
+
+const PEN_FILL: Color = Color::rgba(0.55, 0.75, 0.35, 0.25);
+const PEN_OUTLINE: Color = Color::rgba(0.45, 0.6, 0.25, 0.6);
+
+/// Region cows are herded into for `Objective::PenCows`. Cows count as
+/// penned while their collider is in its sensor.
+#[derive(Component, Debug, Default)]
+pub struct PenZone {
+    penned: HashSet<Entity>,
+}
+
+pub struct PenPlugin;
+
+impl Plugin for PenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(count_penned_cows.before(QuestSystem::Progress))
+                .with_system(enter_victory.after(QuestSystem::Progress)),
+        );
+    }
+}
+
+pub fn spawn_pen(commands: &mut Commands, position: Vec2, size: Vec2) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: size,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(PEN_FILL),
+                outline_mode: StrokeMode::color(PEN_OUTLINE),
+            },
+            Transform::from_translation(position.extend(RenderLayerGroup::Ground.z())),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                size,
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::OLIVE,
+            ));
+        })
+        .insert(PenZone::default())
+        .id()
+}
+
+/// Keeps `QuestLog::penned` up to date. Cows in several overlapping pens only
+/// count once.
+fn count_penned_cows(
+    mut sensor_events: EventReader<SensorEvent>,
+    mut quest_log: ResMut<QuestLog>,
+    mut pen_q: Query<&mut PenZone>,
+    cow_q: Query<(), With<CowTag>>,
+) {
+    for event in sensor_events.iter() {
+        let (sensor, other, entered) = match *event {
+            SensorEvent::Entered { sensor, other } => (sensor, other, true),
+            SensorEvent::Exited { sensor, other } => (sensor, other, false),
+        };
+        if cow_q.get(other).is_err() {
+            continue;
+        }
+        if let Ok(mut pen) = pen_q.get_mut(sensor) {
+            if entered {
+                pen.penned.insert(other);
+            } else {
+                pen.penned.remove(&other);
+            }
+        }
+    }
+
+    let penned: HashSet<Entity> = pen_q
+        .iter()
+        .flat_map(|pen| pen.penned.iter().copied())
+        .filter(|cow| cow_q.get(*cow).is_ok())
+        .collect();
+    let penned = penned.len() as u32;
+    if quest_log.penned != penned {
+        quest_log.penned = penned;
+    }
+}
+
+/// Penning the cows is what the game is won by. If another transition was
+/// already queued the same frame, e.g. the shop opening, that one goes first
+/// and victory follows once back to playing.
+fn enter_victory(
+    mut quest_completed: EventReader<QuestCompleted>,
+    mut state: ResMut<State<AppState>>,
+    quest_log: Res<QuestLog>,
+    mut pending: Local<bool>,
+) {
+    for completed in quest_completed.iter() {
+        let pen_quest = quest_log.quests.get(&completed.id).map_or(false, |quest| {
+            matches!(quest.objective, Objective::PenCows { .. })
+        });
+        if pen_quest {
+            *pending = true;
+        }
+    }
+    if *pending && set_state(&mut state, AppState::Victory) {
+        *pending = false;
+    }
+}
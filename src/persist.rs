@@ -0,0 +1,140 @@
+use std::any::TypeId;
+
+use bevy::{
+    prelude::*,
+    reflect::{GetTypeRegistration, TypeRegistry},
+    scene::{serde::SceneDeserializer, DynamicEntity, DynamicScene},
+};
+use serde::de::DeserializeSeed;
+
+/// Marks an entity whose persisted components are saved, keyed by a name that
+/// stays the same across runs so the saved values find their entity again.
+#[derive(Component, Reflect, Debug, Default, Clone)]
+#[reflect(Component)]
+pub struct Persist(pub String);
+
+impl Persist {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+}
+
+/// Component types saved for `Persist` entities, see `PersistAppExt`.
+#[derive(Default)]
+struct PersistedComponents(Vec<TypeId>);
+
+pub trait PersistAppExt {
+    /// Registers `T` for reflection and saves it on every `Persist` entity
+    /// that has it, with no save code needed for the component itself.
+    fn persist_component<T>(&mut self) -> &mut Self
+    where
+        T: Component + GetTypeRegistration;
+}
+
+impl PersistAppExt for App {
+    fn persist_component<T>(&mut self) -> &mut Self
+    where
+        T: Component + GetTypeRegistration,
+    {
+        self.register_type::<T>();
+        self.world
+            .get_resource_or_insert_with(PersistedComponents::default)
+            .0
+            .push(TypeId::of::<T>());
+        self
+    }
+}
+
+pub struct PersistPlugin;
+
+impl Plugin for PersistPlugin {
+    fn build(&self, app: &mut App) {
+        app.persist_component::<Persist>()
+            .persist_component::<Transform>();
+    }
+}
+
+/// Serializes the persisted components of every `Persist` entity.
+pub fn capture_scene(world: &mut World) -> Result<String, ron::Error> {
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, With<Persist>>()
+        .iter(world)
+        .collect();
+    let registry = world.get_resource::<TypeRegistry>().unwrap().clone();
+    let persisted = world.get_resource::<PersistedComponents>().unwrap();
+
+    let mut scene = DynamicScene::default();
+    {
+        let registry = registry.read();
+        let reflect_components: Vec<&ReflectComponent> = persisted
+            .0
+            .iter()
+            .filter_map(|type_id| registry.get(*type_id)?.data::<ReflectComponent>())
+            .collect();
+        for entity in entities {
+            scene.entities.push(DynamicEntity {
+                entity: entity.id(),
+                components: reflect_components
+                    .iter()
+                    .filter_map(|reflect| reflect.reflect_component(world, entity))
+                    .map(|component| component.clone_value())
+                    .collect(),
+            });
+        }
+    }
+    scene.serialize_ron(&registry)
+}
+
+/// Applies a scene from `capture_scene` onto the `Persist` entities with the
+/// same keys, adding saved components they're missing. Saved entities that
+/// no longer exist are skipped.
+pub fn apply_scene(world: &mut World, source: &str) -> Result<(), ron::Error> {
+    let registry = world.get_resource::<TypeRegistry>().unwrap().clone();
+    let registry = registry.read();
+    let mut deserializer = ron::de::Deserializer::from_str(source)?;
+    let scene = SceneDeserializer {
+        type_registry: &registry,
+    }
+    .deserialize(&mut deserializer)?;
+
+    let entities: Vec<(Entity, String)> = world
+        .query::<(Entity, &Persist)>()
+        .iter(world)
+        .map(|(entity, persist)| (entity, persist.0.clone()))
+        .collect();
+    for saved in scene.entities.iter() {
+        let mut key = Persist::default();
+        if let Some(component) = saved
+            .components
+            .iter()
+            .find(|component| component.type_name() == std::any::type_name::<Persist>())
+        {
+            key.apply(&**component);
+        }
+        let entity = match entities.iter().find(|(_, other)| *other == key.0) {
+            Some((entity, _)) => *entity,
+            None => {
+                warn!("No entity to load saved {:?} into", key);
+                continue;
+            }
+        };
+        for component in saved.components.iter() {
+            let reflect = match registry
+                .get_with_name(component.type_name())
+                .and_then(|registration| registration.data::<ReflectComponent>())
+            {
+                Some(reflect) => reflect,
+                None => {
+                    warn!("Saved component {} isn't registered", component.type_name());
+                    continue;
+                }
+            };
+            if reflect.reflect_component(world, entity).is_some() {
+                reflect.apply_component(world, entity, &**component);
+            } else {
+                reflect.add_component(world, entity, &**component);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+/// Coarse order of the gameplay systems in `CoreStage::Update`, so systems
+/// can order against everything that feeds them without naming each system:
+///
+/// - `Input` turns key presses into velocities, events and state changes.
+/// - `Ai` steers NPCs and runs scripts.
+/// - `Gameplay` reacts to what happened, e.g. quests, items and hits.
+/// - `Ui` shows the results.
+///
+/// Physics runs in its own stages after `CoreStage::PostUpdate`, see
+/// `PhysicsStage`, rather than between `Ai` and `Gameplay`: its substeps run a
+/// stage several times per frame, which a label within `CoreStage::Update`
+/// can't do. So `Gameplay` systems reading `SensorEvent`s and `ImpactEvent`s
+/// see the ones of the previous frame's physics, one frame late.
+///
+/// Systems within a phase order against each other with their module's typed
+/// labels, e.g. `InteractionSystem::Interact`.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Input,
+    Ai,
+    Gameplay,
+    Ui,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [Phase::Input, Phase::Ai, Phase::Gameplay, Phase::Ui];
+}
+
+pub trait PhaseExt {
+    /// Puts every system of the set in `phase`, after all earlier phases.
+    fn in_phase(self, phase: Phase) -> Self;
+}
+
+impl PhaseExt for SystemSet {
+    fn in_phase(self, phase: Phase) -> Self {
+        Phase::ALL
+            .iter()
+            .take_while(|earlier| **earlier != phase)
+            .fold(self.label(phase), |set, earlier| set.after(*earlier))
+    }
+}
+
+#[derive(StageLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicsStage {
+    /// Once per frame, before the substeps.
+    Begin,
+    /// Runs `PhysicsSettings::substeps` times per frame.
+    Substep,
+    /// Once per frame, after the substeps.
+    End,
+}
+
+/// Systems of the physics stages, in the order they run.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicsSystem {
+    Rollback,
+    Aabb,
+    Narrowphase,
+    FilterContacts,
+    DropVetoed,
+    SensorEvents,
+    Collision,
+    Integrate,
+    Propagate,
+}
@@ -0,0 +1,144 @@
+use bevy::{input::mouse::MouseWheel, prelude::*, ui::Node};
+
+use crate::{
+    camera::{PixelCameraSettings, PixelCameraTag},
+    menu::halt_velocities,
+    phase::{Phase, PhaseExt},
+    set_state, AppState,
+};
+
+/// In screen pixels per second, so panning feels the same at any zoom.
+const CAMERA_PAN_SPEED: f32 = 600.;
+/// Zoom change per scroll wheel line.
+const ZOOM_STEP: f32 = 0.25;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 16.;
+
+/// Camera and HUD state from before entering photo mode, restored on leaving.
+#[derive(Default)]
+struct PhotoMode {
+    camera_translation: Vec3,
+    zoom: f32,
+    integer_scaling: bool,
+    hidden: Vec<Entity>,
+}
+
+pub struct PhotoModePlugin;
+
+impl Plugin for PhotoModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhotoMode>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(toggle_photo_mode),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::PhotoMode)
+                    .with_system(halt_velocities)
+                    .with_system(enter_photo_mode),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::PhotoMode).with_system(leave_photo_mode))
+            .add_system_set(
+                SystemSet::on_update(AppState::PhotoMode)
+                    .in_phase(Phase::Input)
+                    .with_system(photo_camera),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::PhotoMode)
+                    .in_phase(Phase::Ui)
+                    .with_system(hide_hud),
+            );
+    }
+}
+
+fn toggle_photo_mode(keys: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if !keys.just_pressed(KeyCode::F3) {
+        return;
+    }
+    match *state.current() {
+        AppState::Playing => {
+            set_state(&mut state, AppState::PhotoMode);
+        }
+        AppState::PhotoMode => {
+            set_state(&mut state, AppState::Playing);
+        }
+        _ => {}
+    }
+}
+
+fn enter_photo_mode(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut camera_settings: ResMut<PixelCameraSettings>,
+    camera_q: Query<&Transform, With<PixelCameraTag>>,
+) {
+    photo_mode.camera_translation = camera_q.single().translation;
+    photo_mode.zoom = camera_settings.zoom;
+    photo_mode.integer_scaling = camera_settings.integer_scaling;
+    // Smooth zooming makes framing shots easier than whole steps
+    camera_settings.zoom = camera_settings.effective_zoom();
+    camera_settings.integer_scaling = false;
+}
+
+fn leave_photo_mode(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut camera_settings: ResMut<PixelCameraSettings>,
+    mut camera_q: Query<&mut Transform, With<PixelCameraTag>>,
+    mut visibility_q: Query<&mut Visibility>,
+) {
+    camera_q.single_mut().translation = photo_mode.camera_translation;
+    camera_settings.zoom = photo_mode.zoom;
+    camera_settings.integer_scaling = photo_mode.integer_scaling;
+    for entity in photo_mode.hidden.drain(..) {
+        if let Ok(mut visibility) = visibility_q.get_mut(entity) {
+            visibility.is_visible = true;
+        }
+    }
+}
+
+/// Hides every visible UI node, including ones spawned while in photo mode,
+/// e.g. toasts.
+fn hide_hud(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut node_q: Query<(Entity, &mut Visibility), With<Node>>,
+) {
+    for (entity, mut visibility) in node_q.iter_mut() {
+        if visibility.is_visible {
+            visibility.is_visible = false;
+            photo_mode.hidden.push(entity);
+        }
+    }
+}
+
+fn photo_camera(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut camera_settings: ResMut<PixelCameraSettings>,
+    mut camera_q: Query<&mut Transform, With<PixelCameraTag>>,
+) {
+    let mut pan = Vec2::ZERO;
+    if keys.pressed(KeyCode::A) || keys.pressed(KeyCode::Left) {
+        pan.x -= 1.;
+    }
+    if keys.pressed(KeyCode::D) || keys.pressed(KeyCode::Right) {
+        pan.x += 1.;
+    }
+    if keys.pressed(KeyCode::W) || keys.pressed(KeyCode::Up) {
+        pan.y += 1.;
+    }
+    if keys.pressed(KeyCode::S) || keys.pressed(KeyCode::Down) {
+        pan.y -= 1.;
+    }
+    let speed = CAMERA_PAN_SPEED / camera_settings.effective_zoom();
+    for mut camera_trans in camera_q.iter_mut() {
+        camera_trans.translation +=
+            (pan.normalize_or_zero() * speed * time.delta_seconds()).extend(0.);
+    }
+
+    let scroll: f32 = wheel_events.iter().map(|event| event.y).sum();
+    if scroll != 0. {
+        camera_settings.zoom =
+            (camera_settings.zoom + scroll * ZOOM_STEP).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{
+        AabbBundle, AabbKind, ColliderEnabled, CollisionBehavior, CollisionMatrix, CollisionWorld,
+        CollisionWorlds, Mass, Velocity,
+    },
+    origin::WorldOrigin,
+};
+
+pub const DEFAULT_DUMP_PATH: &str = "physics_dump.ron";
+
+/// One AABB of the collision world, with the state of its owner the physics
+/// stage needs to resolve it. Entities are stored by index.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DumpedAabb {
+    pub entity: u32,
+    pub owner: u32,
+    pub kind: AabbKind,
+    pub behavior: CollisionBehavior,
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+    pub enabled: bool,
+    pub velocity: Option<(f32, f32)>,
+    pub mass: Option<f32>,
+}
+
+/// Snapshot of the active `CollisionWorld`, written by the `dump_physics`
+/// console command to attach to collision bug reports, and loaded back by
+/// `--replay-physics` to step the same scenario headlessly.
+///
+/// Positions are local to `origin`, like the collision world's.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PhysicsDump {
+    pub world: String,
+    pub origin: (f32, f32),
+    pub matrix: CollisionMatrix,
+    pub aabbs: Vec<DumpedAabb>,
+    /// (owner, owner) pairs overlapping in the last physics tick.
+    pub pairs: Vec<(u32, u32)>,
+    /// (sensor owner, other owner) pairs.
+    pub sensor_overlaps: Vec<(u32, u32)>,
+    /// Collider AABB pairs in contact.
+    pub contacts: Vec<(u32, u32)>,
+}
+
+impl PhysicsDump {
+    pub fn capture(world: &World) -> Self {
+        let collision_world = world.get_resource::<CollisionWorld>().unwrap();
+        let origin = world
+            .get_resource::<WorldOrigin>()
+            .map_or(Vec2::ZERO, |origin| origin.0);
+        let aabbs = collision_world
+            .aabbs
+            .iter()
+            .map(|entry| (entry, true))
+            .chain(collision_world.disabled.iter().map(|entry| (entry, false)))
+            .map(|((entity, (owner, aabb)), enabled)| DumpedAabb {
+                entity: entity.id(),
+                owner: owner.id(),
+                kind: aabb.aabb_kind,
+                behavior: aabb.collision_behavior,
+                min: (aabb.min.x, aabb.min.y),
+                max: (aabb.max.x, aabb.max.y),
+                enabled,
+                velocity: world
+                    .get::<Velocity>(*owner)
+                    .map(|velocity| (velocity.0.x, velocity.0.y)),
+                mass: world.get::<Mass>(*owner).map(|mass| mass.0),
+            })
+            .collect();
+        Self {
+            world: world
+                .get_resource::<CollisionWorlds>()
+                .map_or_else(String::new, |worlds| worlds.active().to_string()),
+            origin: (origin.x, origin.y),
+            matrix: world.get_resource::<CollisionMatrix>().unwrap().clone(),
+            aabbs,
+            pairs: collision_world
+                .pairs
+                .iter()
+                .map(|pair| (pair.ent1.id(), pair.ent2.id()))
+                .collect(),
+            sensor_overlaps: collision_world
+                .sensor_overlaps
+                .iter()
+                .map(|(sensor, other)| (sensor.id(), other.id()))
+                .collect(),
+            contacts: collision_world
+                .contacts
+                .iter()
+                .map(|(a, b)| (a.id(), b.id()))
+                .collect(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        std::fs::write(path, source).map_err(|err| err.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&source).map_err(|err| err.to_string())
+    }
+
+    /// Spawns the dumped owners, each with its AABBs as children, and
+    /// inserts the collision matrix. The owner sits at the center of its
+    /// first AABB, the others are offset from it. Returns the spawned owner
+    /// of each dumped owner index.
+    pub fn spawn(&self, world: &mut World) -> HashMap<u32, Entity> {
+        world.insert_resource(self.matrix.clone());
+        let mut owners = HashMap::default();
+        for dumped in self.aabbs.iter() {
+            let min = Vec2::new(dumped.min.0, dumped.min.1);
+            let max = Vec2::new(dumped.max.0, dumped.max.1);
+            let center = (min + max) / 2.;
+            let owner = *owners.entry(dumped.owner).or_insert_with(|| {
+                let mut owner = world.spawn();
+                owner
+                    .insert(Transform::from_translation(center.extend(0.)))
+                    .insert(GlobalTransform::from_translation(center.extend(0.)));
+                if let Some((x, y)) = dumped.velocity {
+                    owner.insert(Velocity(Vec2::new(x, y)));
+                }
+                if let Some(mass) = dumped.mass {
+                    owner.insert(Mass(mass));
+                }
+                owner.id()
+            });
+            let owner_position = world
+                .get::<Transform>(owner)
+                .unwrap()
+                .translation
+                .truncate();
+            let color = match dumped.kind {
+                AabbKind::Collider => Color::GREEN,
+                AabbKind::Sensor => Color::YELLOW,
+            };
+            world.entity_mut(owner).with_children(|parent| {
+                let mut aabb = parent.spawn_bundle(AabbBundle::with_offset(
+                    max - min,
+                    center - owner_position,
+                    dumped.kind,
+                    dumped.behavior,
+                    color,
+                ));
+                if !dumped.enabled {
+                    aabb.insert(ColliderEnabled(false));
+                }
+            });
+        }
+        owners
+    }
+}
+
+/// Writes the active collision world to `path`, see `PhysicsDump`.
+pub fn dump_physics(world: &World, path: &str) -> Result<usize, String> {
+    let dump = PhysicsDump::capture(world);
+    dump.save(path)?;
+    Ok(dump.aabbs.len())
+}
@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder},
+    shapes,
+};
+
+use crate::{
+    clock::WorldClock,
+    collision::{AabbBundle, AabbKind, CollisionBehavior},
+    floating_text::SpawnFloatingText,
+    frame_budget::TimedExt,
+    interaction::{InteractEvent, Interactable, InteractionSystem},
+    inventory::Inventory,
+    layer::RenderLayerGroup,
+    localization::Localization,
+    persist::PersistAppExt,
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    AppState, PlayerTag,
+};
+
+const PLANT_RADIUS: f32 = 6.;
+/// Reach of the plant's sensor, for harvesting it.
+const PLANT_REACH: f32 = 24.;
+/// Items a ripe plant yields.
+const HARVEST_COUNT: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PlantStage {
+    Seed,
+    Sprout,
+    Growing,
+    Ripe,
+}
+
+impl PlantStage {
+    /// In-game hours of growth each stage starts at, in order.
+    const STARTS: [(PlantStage, f32); 4] = [
+        (PlantStage::Seed, 0.),
+        (PlantStage::Sprout, 2.),
+        (PlantStage::Growing, 4.),
+        (PlantStage::Ripe, 6.),
+    ];
+
+    pub fn for_hours(hours: f32) -> Self {
+        Self::STARTS
+            .iter()
+            .rev()
+            .find(|(_, start)| hours >= *start)
+            .map_or(PlantStage::Seed, |(stage, _)| *stage)
+    }
+
+    /// There are no plant sprites yet, stages are told apart by size and color.
+    fn scale(self) -> f32 {
+        match self {
+            PlantStage::Seed => 0.4,
+            PlantStage::Sprout => 0.6,
+            PlantStage::Growing => 0.85,
+            PlantStage::Ripe => 1.,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            PlantStage::Seed => Color::rgb(0.45, 0.32, 0.2),
+            PlantStage::Sprout => Color::rgb(0.5, 0.8, 0.35),
+            PlantStage::Growing => Color::rgb(0.3, 0.65, 0.25),
+            PlantStage::Ripe => Color::rgb(0.9, 0.8, 0.3),
+        }
+    }
+}
+
+/// Grows with the `WorldClock` and can be harvested for `crop` once ripe,
+/// starting over from a seed.
+#[derive(Component, Reflect, Debug, Default, Clone)]
+#[reflect(Component)]
+pub struct Plant {
+    pub crop: String,
+    /// In-game hours since planted.
+    pub grown_hours: f32,
+}
+
+impl Plant {
+    pub fn new(crop: impl Into<String>) -> Self {
+        Self {
+            crop: crop.into(),
+            grown_hours: 0.,
+        }
+    }
+
+    pub fn stage(&self) -> PlantStage {
+        PlantStage::for_hours(self.grown_hours)
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PlantSystem {
+    Grow,
+}
+
+pub struct PlantPlugin;
+
+impl Plugin for PlantPlugin {
+    fn build(&self, app: &mut App) {
+        app.persist_component::<Plant>().add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(grow_plants.timed().label(PlantSystem::Grow))
+                .with_system(
+                    harvest_plants
+                        .after(InteractionSystem::Interact)
+                        .after(PlantSystem::Grow),
+                )
+                .with_system(update_plant_shapes.after(PlantSystem::Grow)),
+        );
+    }
+}
+
+pub fn spawn_plant(commands: &mut Commands, position: Vec2, crop: &str) -> Entity {
+    let stage = PlantStage::Seed;
+    let mut transform = Transform::from_translation(RenderLayerGroup::Actors.at(position));
+    transform.scale = Vec3::splat(stage.scale());
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: PLANT_RADIUS,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Fill(FillMode::color(stage.color())),
+            transform,
+        ))
+        .with_children(|parent| {
+            // No collider, plants can be walked over
+            parent.spawn_bundle(AabbBundle::new(
+                Vec2::splat(PLANT_REACH),
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::PURPLE,
+            ));
+        })
+        .insert(Plant::new(crop))
+        .insert(Interactable)
+        .insert(RenderLayerGroup::Actors)
+        .id()
+}
+
+fn grow_plants(
+    clock: Res<WorldClock>,
+    mut last_hours: Local<Option<f32>>,
+    mut plant_q: Query<&mut Plant>,
+) {
+    let elapsed = match *last_hours {
+        Some(last) => (clock.hours - last).rem_euclid(24.),
+        None => 0.,
+    };
+    *last_hours = Some(clock.hours);
+    if elapsed == 0. {
+        return;
+    }
+    for mut plant in plant_q.iter_mut() {
+        plant.grown_hours += elapsed;
+    }
+}
+
+fn harvest_plants(
+    mut interact_events: EventReader<InteractEvent>,
+    mut inventory: ResMut<Inventory>,
+    mut stats: ResMut<PlayerStats>,
+    mut floating_texts: EventWriter<SpawnFloatingText>,
+    localization: Res<Localization>,
+    player_q: Query<Entity, With<PlayerTag>>,
+    mut plant_q: Query<&mut Plant>,
+) {
+    for event in interact_events.iter() {
+        let mut plant = match plant_q.get_mut(event.target) {
+            Ok(plant) => plant,
+            Err(_) => continue,
+        };
+        if plant.stage() != PlantStage::Ripe {
+            floating_texts.send(SpawnFloatingText {
+                anchor: event.target,
+                text: localization.get("plant.not_ripe"),
+                color: Color::WHITE,
+            });
+            continue;
+        }
+
+        inventory.add(&plant.crop, HARVEST_COUNT);
+        stats.items_collected += HARVEST_COUNT;
+        floating_texts.send(SpawnFloatingText {
+            anchor: player_q.single(),
+            text: format!("+{} {}", HARVEST_COUNT, localization.get(&plant.crop)),
+            color: Color::WHITE,
+        });
+        plant.grown_hours = 0.;
+    }
+}
+
+fn update_plant_shapes(
+    mut plant_q: Query<(&Plant, &mut Transform, &mut DrawMode), Changed<Plant>>,
+) {
+    for (plant, mut trans, mut draw_mode) in plant_q.iter_mut() {
+        let stage = plant.stage();
+        let scale = Vec3::splat(stage.scale());
+        // Growth changes the plant every frame, only redraw on a new stage
+        if trans.scale != scale {
+            trans.scale = scale;
+            *draw_mode = DrawMode::Fill(FillMode::color(stage.color()));
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    phase::{Phase, PhaseExt},
+    AppState, PlayerTag,
+};
+
+/// Per frame movement above this is a teleport, e.g. loading a save, and
+/// doesn't count as walking.
+const MAX_WALK_STEP: f32 = 32.;
+
+/// Lifetime stats of the current run, shown in the pause menu and saved
+/// along with it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlayerStats {
+    pub distance_walked: f32,
+    pub cows_talked_to: u32,
+    pub items_collected: u32,
+    /// Time spent in the `Playing` state.
+    pub play_time_secs: f64,
+    /// Currency for trading with merchants, see `shop::Merchant`.
+    #[serde(default)]
+    pub coins: u32,
+    /// Fastest completion of each quest in timed mode, in seconds of game
+    /// time.
+    #[serde(default)]
+    pub best_quest_times: BTreeMap<String, f32>,
+}
+
+pub struct PlayerStatsPlugin;
+
+impl Plugin for PlayerStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerStats>().add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(tick_play_time)
+                .with_system(track_distance_walked),
+        );
+    }
+}
+
+fn tick_play_time(time: Res<Time>, mut stats: ResMut<PlayerStats>) {
+    stats.play_time_secs += time.delta_seconds_f64();
+}
+
+fn track_distance_walked(
+    mut last_position: Local<Option<Vec2>>,
+    mut stats: ResMut<PlayerStats>,
+    player_q: Query<&GlobalTransform, With<PlayerTag>>,
+) {
+    let position = player_q.single().translation.xy();
+    if let Some(last) = *last_position {
+        let step = position.distance(last);
+        if step <= MAX_WALK_STEP {
+            stats.distance_walked += step;
+        }
+    }
+    *last_position = Some(position);
+}
@@ -0,0 +1,52 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+/// Recycles entities of one kind instead of despawning and respawning them,
+/// so hot spawners don't keep moving entities between archetypes.
+///
+/// `T` only distinguishes pools from one another, usually it's the component
+/// that identifies the pooled entities. Released entities are hidden and keep
+/// all of their components; systems iterating them should skip dead ones.
+pub struct EntityPool<T> {
+    free: Vec<Entity>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EntityPool<T> {
+    fn default() -> Self {
+        Self {
+            free: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> EntityPool<T> {
+    /// Reuses a free entity, or creates one with `spawn` if there is none, and
+    /// (re)sets its state by inserting `reset`.
+    ///
+    /// `spawn` should add the expensive parts (meshes, text, ...) that survive
+    /// recycling, while `reset` holds everything that changes per use.
+    pub fn acquire<B: Bundle>(
+        &mut self,
+        commands: &mut Commands,
+        reset: B,
+        spawn: impl FnOnce(&mut Commands) -> Entity,
+    ) -> Entity {
+        let entity = self.free.pop().unwrap_or_else(|| spawn(commands));
+        commands
+            .entity(entity)
+            .insert_bundle(reset)
+            .insert(Visibility { is_visible: true });
+        entity
+    }
+
+    /// Hides `entity` and makes it available to `acquire`.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        commands
+            .entity(entity)
+            .insert(Visibility { is_visible: false });
+        self.free.push(entity);
+    }
+}
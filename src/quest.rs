@@ -0,0 +1,449 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::Deserialize;
+
+use crate::{
+    clock::WorldClock,
+    dialogue::DialogueChoice,
+    feeding::FedEvent,
+    floating_text::SpawnFloatingText,
+    frame_budget::TimedExt,
+    interaction::{InteractEvent, InteractionSystem},
+    inventory::Inventory,
+    localization::{Localization, LocalizationSystem},
+    mods,
+    notify::Notify,
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    PlayerTag,
+};
+
+static QUESTS_FILE: &str = "quests.ron";
+/// Quest updates stay up a bit longer than other notifications.
+const QUEST_NOTIFY_SECS: f32 = 5.;
+
+/// On-disk format of `assets/quests.ron`, or a mod's override of it.
+#[derive(Deserialize, Debug, Default)]
+pub struct QuestChain {
+    pub start: String,
+    pub quests: Vec<Quest>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Quest {
+    pub id: String,
+    pub objective: Objective,
+    /// Hours of the day the quest can be progressed.
+    #[serde(default)]
+    pub available: Option<Availability>,
+    pub reward: Reward,
+    pub next: Option<String>,
+    #[serde(default)]
+    pub marker: QuestMarker,
+    /// Seconds to complete the quest in, in timed mode, see `challenge`.
+    #[serde(default)]
+    pub time_limit: Option<f32>,
+}
+
+/// What the quest marker points to while the quest is active, see
+/// `quest_marker`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum QuestMarker {
+    /// The NPC the objective is completed at, if any.
+    Target,
+    /// A spot in the world.
+    Position(f32, f32),
+    /// No marker, e.g. for objectives that are meant to be searched for.
+    Hidden,
+}
+
+impl Default for QuestMarker {
+    fn default() -> Self {
+        QuestMarker::Target
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum Objective {
+    TalkTo(String),
+    Deliver {
+        item: String,
+        count: u32,
+        to: String,
+    },
+    /// Herd `count` cows into `pen::PenZone`s.
+    PenCows {
+        count: u32,
+    },
+}
+
+/// `[from, to)` in hours of the `WorldClock`, wrapping past midnight if `to`
+/// is before `from`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Availability {
+    pub from: f32,
+    pub to: f32,
+}
+
+impl Objective {
+    /// Name of the NPC the objective is completed at, if it's completed at
+    /// one.
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            Objective::TalkTo(npc) => Some(npc),
+            Objective::Deliver { to, .. } => Some(to),
+            Objective::PenCows { .. } => None,
+        }
+    }
+
+    /// Text for the three sections of the quest HUD; the middle one is highlighted.
+    fn hud_sections(&self, localization: &Localization, quest_log: &QuestLog) -> [String; 3] {
+        match self {
+            Objective::TalkTo(npc) => [
+                localization.get("quest.talk_to"),
+                localization.get(npc),
+                ".".into(),
+            ],
+            Objective::Deliver { item, count, to } => [
+                localization.format(
+                    "quest.deliver",
+                    &[
+                        (
+                            "count",
+                            count
+                                .saturating_sub(quest_log.delivered)
+                                .to_string()
+                                .as_str(),
+                        ),
+                        ("item", localization.get(item).as_str()),
+                    ],
+                ),
+                localization.get(to),
+                ".".into(),
+            ],
+            Objective::PenCows { count } => [
+                localization.get("quest.pen_cows"),
+                format!("{}/{}", quest_log.penned.min(*count), count),
+                ".".into(),
+            ],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Reward {
+    pub items: Vec<(String, u32)>,
+    pub flags: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuestCompleted {
+    pub id: String,
+}
+
+/// Sent when the time limit of the active quest runs out in timed mode. The
+/// quest stays active, starting over.
+#[derive(Debug, Clone)]
+pub struct QuestFailed {
+    pub id: String,
+}
+
+#[derive(Default)]
+pub struct QuestLog {
+    pub quests: HashMap<String, Quest>,
+    pub active: Option<String>,
+    pub completed: Vec<String>,
+    pub flags: HashSet<String>,
+    /// Items fed to the target of the active `Objective::Deliver` so far.
+    pub delivered: u32,
+    /// Cows in `pen::PenZone`s right now, for `Objective::PenCows`.
+    pub penned: u32,
+}
+
+impl QuestLog {
+    /// Starts at `chain.start`, or with no active quest if the chain has
+    /// none or names a quest it doesn't have.
+    pub fn from_chain(chain: QuestChain) -> Self {
+        let quests: HashMap<String, Quest> = chain
+            .quests
+            .into_iter()
+            .map(|quest| (quest.id.clone(), quest))
+            .collect();
+        let active = if chain.start.is_empty() {
+            None
+        } else if quests.contains_key(&chain.start) {
+            Some(chain.start)
+        } else {
+            warn!("Unknown starting quest {:?}", chain.start);
+            None
+        };
+        Self {
+            quests,
+            active,
+            ..Default::default()
+        }
+    }
+
+    pub fn active_quest(&self) -> Option<&Quest> {
+        self.active.as_ref().and_then(|id| self.quests.get(id))
+    }
+
+    /// False if the active quest involves `npc` but is outside its
+    /// availability window.
+    pub fn is_available_for(&self, npc: &str, clock: &WorldClock) -> bool {
+        match self.active_quest() {
+            Some(quest) if quest.objective.target() == Some(npc) => quest
+                .available
+                .map_or(true, |window| clock.is_between(window.from, window.to)),
+            _ => true,
+        }
+    }
+
+    fn hud_sections(&self, localization: &Localization) -> [String; 3] {
+        match self.active_quest() {
+            Some(quest) => quest.objective.hud_sections(localization, self),
+            // Without any quests there's nothing to complete
+            None if self.completed.is_empty() => Default::default(),
+            None => [
+                localization.get("quest.all_complete"),
+                String::new(),
+                String::new(),
+            ],
+        }
+    }
+
+    /// One line description of the current progress, e.g. for save slots.
+    pub fn summary(&self, localization: &Localization) -> String {
+        self.hud_sections(localization).concat()
+    }
+}
+
+#[derive(Component)]
+pub struct QuestHudTag;
+
+/// Label of `progress_quests`, which completes objectives and starts the
+/// next quest.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuestSystem {
+    Progress,
+}
+
+pub struct QuestPlugin;
+
+impl Plugin for QuestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuestLog>()
+            .init_resource::<Inventory>()
+            .add_event::<QuestCompleted>()
+            .add_event::<QuestFailed>()
+            .add_startup_system(load_quests)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Gameplay)
+                    .with_system(
+                        progress_quests
+                            .timed()
+                            .label(QuestSystem::Progress)
+                            .after(InteractionSystem::Interact),
+                    )
+                    .with_system(record_dialogue_choices)
+                    .with_system(notify_quest_completed.after(QuestSystem::Progress)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_quest_hud.after(LocalizationSystem::SwitchLanguage)),
+            );
+    }
+}
+
+/// A missing or malformed quests file leaves the game without quests.
+fn load_quests(mut quest_log: ResMut<QuestLog>) {
+    let chain = mods::load_ron(QUESTS_FILE).unwrap_or_else(|err| {
+        warn!("No quests, {}", err);
+        QuestChain::default()
+    });
+    *quest_log = QuestLog::from_chain(chain);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn progress_quests(
+    mut interact_events: EventReader<InteractEvent>,
+    mut fed_events: EventReader<FedEvent>,
+    mut quest_completed: EventWriter<QuestCompleted>,
+    mut quest_log: ResMut<QuestLog>,
+    mut inventory: ResMut<Inventory>,
+    mut stats: ResMut<PlayerStats>,
+    mut floating_texts: EventWriter<SpawnFloatingText>,
+    localization: Res<Localization>,
+    clock: Res<WorldClock>,
+    name_q: Query<&Name>,
+    player_q: Query<Entity, With<PlayerTag>>,
+) {
+    let _span = info_span!("quest").entered();
+    let player = player_q.single();
+    // NPCs talked to, or fed the given item
+    let visits: Vec<(Entity, Option<String>)> = interact_events
+        .iter()
+        .map(|event| (event.target, None))
+        .chain(
+            fed_events
+                .iter()
+                .map(|event| (event.npc, Some(event.item.clone()))),
+        )
+        .collect();
+    for (npc, fed) in visits {
+        let quest = match quest_log.active_quest() {
+            Some(quest) => quest.clone(),
+            None => break,
+        };
+        let target = match name_q.get(npc) {
+            Ok(name) => name.as_str(),
+            Err(_) => continue,
+        };
+
+        if !quest_log.is_available_for(target, &clock) {
+            continue;
+        }
+
+        let done = match &quest.objective {
+            Objective::TalkTo(npc) => npc == target && fed.is_none(),
+            Objective::Deliver { item, count, to } if to == target => {
+                if fed.as_ref() == Some(item) {
+                    quest_log.delivered += 1;
+                }
+                let missing = count.saturating_sub(quest_log.delivered);
+                missing == 0 || (fed.is_none() && inventory.take(item, missing))
+            }
+            Objective::Deliver { .. } | Objective::PenCows { .. } => false,
+        };
+        if done {
+            complete_quest(
+                quest,
+                npc,
+                player,
+                &mut quest_log,
+                &mut inventory,
+                &mut stats,
+                &mut floating_texts,
+                &localization,
+                &mut quest_completed,
+            );
+        }
+    }
+
+    // Checked after the visits, which may have just made it the active quest
+    let penned = match quest_log.active_quest() {
+        Some(quest) => match quest.objective {
+            Objective::PenCows { count } if quest_log.penned >= count => Some(quest.clone()),
+            _ => None,
+        },
+        None => None,
+    };
+    if let Some(quest) = penned {
+        complete_quest(
+            quest,
+            player,
+            player,
+            &mut quest_log,
+            &mut inventory,
+            &mut stats,
+            &mut floating_texts,
+            &localization,
+            &mut quest_completed,
+        );
+    }
+}
+
+/// Hands out the rewards of `quest` and moves on to the next one. `anchor` is
+/// what the completion is shown above.
+#[allow(clippy::too_many_arguments)]
+fn complete_quest(
+    quest: Quest,
+    anchor: Entity,
+    player: Entity,
+    quest_log: &mut QuestLog,
+    inventory: &mut Inventory,
+    stats: &mut PlayerStats,
+    floating_texts: &mut EventWriter<SpawnFloatingText>,
+    localization: &Localization,
+    quest_completed: &mut EventWriter<QuestCompleted>,
+) {
+    floating_texts.send(SpawnFloatingText {
+        anchor,
+        text: localization.get("quest.complete"),
+        color: Color::LIME_GREEN,
+    });
+    for (item, count) in quest.reward.items.iter() {
+        inventory.add(item, *count);
+        stats.items_collected += count;
+        floating_texts.send(SpawnFloatingText {
+            anchor: player,
+            text: format!("+{} {}", count, localization.get(item)),
+            color: Color::WHITE,
+        });
+    }
+    quest_log.flags.extend(quest.reward.flags.iter().cloned());
+    quest_log.completed.push(quest.id.clone());
+    quest_log.active = quest.next.clone();
+    quest_log.delivered = 0;
+    info!(quest = %quest.id, "Quest completed");
+    quest_completed.send(QuestCompleted { id: quest.id });
+}
+
+fn notify_quest_completed(
+    mut quest_completed: EventReader<QuestCompleted>,
+    quest_log: Res<QuestLog>,
+    localization: Res<Localization>,
+    mut notifications: EventWriter<Notify>,
+) {
+    for completed in quest_completed.iter() {
+        notifications.send(
+            Notify::new(localization.get("notify.quest_updated")).with_duration(QUEST_NOTIFY_SECS),
+        );
+        let reward = match quest_log.quests.get(&completed.id) {
+            Some(quest) => &quest.reward,
+            None => continue,
+        };
+        for (item, count) in reward.items.iter() {
+            notifications.send(Notify::new(localization.format(
+                "notify.item_acquired",
+                &[
+                    ("count", count.to_string().as_str()),
+                    ("item", localization.get(item).as_str()),
+                ],
+            )));
+        }
+    }
+}
+
+/// Choices made in dialogue set quest flags, for quests to branch on.
+fn record_dialogue_choices(
+    mut choices: EventReader<DialogueChoice>,
+    mut quest_log: ResMut<QuestLog>,
+) {
+    for choice in choices.iter() {
+        if let Some(flag) = &choice.flag {
+            quest_log.flags.insert(flag.clone());
+        }
+    }
+}
+
+fn update_quest_hud(
+    quest_log: Res<QuestLog>,
+    localization: Res<Localization>,
+    mut hud_q: Query<&mut Text, With<QuestHudTag>>,
+) {
+    if !quest_log.is_changed() && !localization.is_changed() {
+        return;
+    }
+
+    let sections = quest_log.hud_sections(&localization);
+    for mut text in hud_q.iter_mut() {
+        for (section, value) in text.sections.iter_mut().zip(sections.iter()) {
+            section.value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, StrokeMode},
+    shapes,
+};
+
+use crate::{
+    camera::PixelCameraTag,
+    layer::RenderLayerGroup,
+    origin::WorldOrigin,
+    phase::{Phase, PhaseExt},
+    quest::{QuestLog, QuestMarker},
+    AppState,
+};
+
+const MARKER_COLOR: Color = Color::GOLD;
+const MARKER_OUTLINE: Color = Color::rgb(0.35, 0.25, 0.05);
+/// From the arrow's center to its tip.
+const MARKER_SIZE: f32 = 5.;
+/// Above the target's origin, so the arrow doesn't cover it.
+const TARGET_HEIGHT: f32 = 26.;
+/// Kept between the arrow and the screen edge while the target is off-screen.
+const EDGE_INSET: f32 = 12.;
+/// Up and down movement of the arrow over an on-screen target.
+const BOB_HEIGHT: f32 = 2.;
+/// Bobs per second.
+const BOB_SPEED: f32 = 1.5;
+/// Above floating text.
+const MARKER_DEPTH: f32 = 2.;
+
+/// Arrow hovering over the active quest's objective, or at the edge of the
+/// screen pointing towards it while it's off-screen.
+#[derive(Component)]
+struct QuestMarkerArrow;
+
+pub struct QuestMarkerPlugin;
+
+impl Plugin for QuestMarkerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_marker).add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(update_marker),
+        );
+    }
+}
+
+/// Drawn pointing right, `update_marker` turns it.
+fn spawn_marker(mut commands: Commands) {
+    let back = -MARKER_SIZE * 0.6;
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Polygon {
+                points: vec![
+                    Vec2::new(MARKER_SIZE, 0.),
+                    Vec2::new(back, MARKER_SIZE * 0.7),
+                    Vec2::new(back, -MARKER_SIZE * 0.7),
+                ],
+                closed: true,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(MARKER_COLOR),
+                outline_mode: StrokeMode::new(MARKER_OUTLINE, 1.),
+            },
+            Transform::default(),
+        ))
+        .insert(Visibility { is_visible: false })
+        .insert(QuestMarkerArrow);
+}
+
+/// Where the active quest's marker points to, if anywhere.
+fn marker_target(
+    quest_log: &QuestLog,
+    origin: &WorldOrigin,
+    target_q: &Query<(&Name, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let quest = quest_log.active_quest()?;
+    match quest.marker {
+        QuestMarker::Target => target_q
+            .iter()
+            .find(|(name, _)| Some(name.as_str()) == quest.objective.target())
+            .map(|(_, trans)| trans.translation.xy() + Vec2::new(0., TARGET_HEIGHT)),
+        QuestMarker::Position(x, y) => Some(origin.to_local(Vec2::new(x, y))),
+        QuestMarker::Hidden => None,
+    }
+}
+
+/// Runs in the UI phase, after the camera and the target have moved.
+fn update_marker(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    quest_log: Res<QuestLog>,
+    origin: Res<WorldOrigin>,
+    target_q: Query<(&Name, &GlobalTransform)>,
+    camera_q: Query<(&Transform, &OrthographicProjection), With<PixelCameraTag>>,
+    mut marker_q: Query<
+        (&mut Transform, &mut Visibility),
+        (With<QuestMarkerArrow>, Without<PixelCameraTag>),
+    >,
+) {
+    let (mut trans, mut visibility) = match marker_q.get_single_mut() {
+        Ok(marker) => marker,
+        Err(_) => return,
+    };
+    let target = match camera_q.get_single() {
+        Ok(camera) if *state.current() == AppState::Playing => {
+            marker_target(&quest_log, &origin, &target_q).map(|target| (camera, target))
+        }
+        _ => None,
+    };
+    if visibility.is_visible != target.is_some() {
+        visibility.is_visible = target.is_some();
+    }
+    let ((camera_trans, projection), target) = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    let center = camera_trans.translation.xy();
+    let inset = Vec2::splat(EDGE_INSET);
+    let min = center + Vec2::new(projection.left, projection.bottom) * projection.scale + inset;
+    let max = center + Vec2::new(projection.right, projection.top) * projection.scale - inset;
+    let (position, angle) = if target.cmpge(min).all() && target.cmple(max).all() {
+        let bob = (time.seconds_since_startup() as f32 * BOB_SPEED * std::f32::consts::TAU).sin();
+        (
+            target + Vec2::new(0., BOB_HEIGHT * bob),
+            -std::f32::consts::FRAC_PI_2,
+        )
+    } else {
+        let to_target = target - center;
+        (target.clamp(min, max), to_target.y.atan2(to_target.x))
+    };
+    trans.translation = position.extend(RenderLayerGroup::UiWorld.z() + MARKER_DEPTH);
+    trans.rotation = Quat::from_rotation_z(angle);
+}
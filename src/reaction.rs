@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder},
+    shapes,
+};
+
+use crate::{
+    collision::{
+        AabbBundle, AabbKind, CollisionBehavior, CollisionWorld, ImpactEvent, SensorEvent,
+    },
+    layer::RenderLayerGroup,
+    localization::Localization,
+    notify::Notify,
+    particle::ParticleBurst,
+    phase::{Phase, PhaseExt},
+    quest::QuestLog,
+    AppState, PlayerTag,
+};
+
+const SPARKLE_COLOR: Color = Color::rgb(1., 0.95, 0.5);
+const PICKUP_COLOR: Color = Color::GOLD;
+const PICKUP_RADIUS: f32 = 3.;
+/// Size of the sensor the player has to touch to pick something up.
+const PICKUP_REACH: f32 = 10.;
+
+/// Something an `OnCollision` entity does when it collides.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollisionReaction {
+    /// Despawns the entity with its children, e.g. a pickup.
+    Despawn,
+    /// Sets a quest flag.
+    SetFlag(String),
+    /// Shows a notification with the given `Localization` key.
+    Notify(String),
+    /// Bursts sparkles from the entity.
+    Sparkle,
+}
+
+/// Reactions of an entity to colliding, run in order by
+/// `dispatch_collision_reactions`, so one-off behaviors don't each need a
+/// system of their own.
+///
+/// The entity collides when a collider enters its sensor, its collider
+/// enters a sensor, or it runs into something static.
+#[derive(Component, Debug, Clone)]
+pub struct OnCollision {
+    pub reactions: Vec<CollisionReaction>,
+    /// Only react to colliding with the player.
+    pub player_only: bool,
+}
+
+impl OnCollision {
+    pub fn new(reactions: Vec<CollisionReaction>) -> Self {
+        Self {
+            reactions,
+            player_only: false,
+        }
+    }
+
+    pub fn player_only(self) -> Self {
+        Self {
+            player_only: true,
+            ..self
+        }
+    }
+}
+
+/// Spawns a pickup that sparkles, runs `reactions` and disappears once the
+/// player touches it.
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    position: Vec2,
+    reactions: Vec<CollisionReaction>,
+) -> Entity {
+    let reactions = std::iter::once(CollisionReaction::Sparkle)
+        .chain(reactions)
+        .chain(std::iter::once(CollisionReaction::Despawn))
+        .collect();
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: PICKUP_RADIUS,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Fill(FillMode::color(PICKUP_COLOR)),
+            Transform::from_translation(RenderLayerGroup::Actors.at(position)),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                Vec2::splat(PICKUP_REACH),
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::PURPLE,
+            ));
+        })
+        .insert(RenderLayerGroup::Actors)
+        .insert(OnCollision::new(reactions).player_only())
+        .id()
+}
+
+pub struct ReactionPlugin;
+
+impl Plugin for ReactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(dispatch_collision_reactions),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn dispatch_collision_reactions(
+    mut commands: Commands,
+    localization: Res<Localization>,
+    mut quest_log: ResMut<QuestLog>,
+    mut collision_world: ResMut<CollisionWorld>,
+    mut sensor_events: EventReader<SensorEvent>,
+    mut impacts: EventReader<ImpactEvent>,
+    mut notifications: EventWriter<Notify>,
+    mut bursts: EventWriter<ParticleBurst>,
+    player_q: Query<(), With<PlayerTag>>,
+    reacting_q: Query<(&OnCollision, &GlobalTransform)>,
+) {
+    // Entities that collided, and what with if it's known
+    let mut collided: Vec<(Entity, Option<Entity>)> = Vec::new();
+    for event in sensor_events.iter() {
+        if let SensorEvent::Entered { sensor, other } = *event {
+            collided.push((sensor, Some(other)));
+            collided.push((other, Some(sensor)));
+        }
+    }
+    collided.extend(impacts.iter().map(|impact| (impact.entity, None)));
+
+    let mut despawned = Vec::new();
+    for (entity, other) in collided {
+        let (on_collision, trans) = match reacting_q.get(entity) {
+            Ok(reacting) => reacting,
+            Err(_) => continue,
+        };
+        let with_player = other.map_or(false, |other| player_q.get(other).is_ok());
+        if (on_collision.player_only && !with_player) || despawned.contains(&entity) {
+            continue;
+        }
+        for reaction in on_collision.reactions.iter() {
+            match reaction {
+                CollisionReaction::Despawn => {
+                    // Gone from queries right away, instead of once the
+                    // physics stage sees the removed AABBs
+                    collision_world.remove_owner(entity);
+                    commands.entity(entity).despawn_recursive();
+                    despawned.push(entity);
+                }
+                CollisionReaction::SetFlag(flag) => {
+                    quest_log.flags.insert(flag.clone());
+                }
+                CollisionReaction::Notify(key) => {
+                    notifications.send(Notify::new(localization.get(key)));
+                }
+                CollisionReaction::Sparkle => bursts.send(ParticleBurst {
+                    color: SPARKLE_COLOR,
+                    ..ParticleBurst::dust(trans.translation.truncate(), Vec2::ZERO, 8)
+                }),
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::{math::Vec3Swizzles, prelude::*, reflect::TypeRegistry};
+
+/// An entity found by `find_with_component`.
+#[derive(Debug, Clone)]
+pub struct FoundEntity {
+    pub entity: Entity,
+    pub name: Option<String>,
+    pub position: Option<Vec2>,
+}
+
+/// Entities with the component registered for reflection under the short
+/// type name `component`, e.g. `NpcState` or `Transform`, ignoring case.
+///
+/// Only components registered with `#[reflect(Component)]` can be found,
+/// which includes every persisted one, see `PersistAppExt`.
+pub fn find_with_component(world: &mut World, component: &str) -> Result<Vec<FoundEntity>, String> {
+    let registry = world.get_resource::<TypeRegistry>().unwrap().clone();
+    let registry = registry.read();
+    let reflect = registry
+        .iter()
+        .filter(|registration| registration.short_name().eq_ignore_ascii_case(component))
+        .find_map(|registration| registration.data::<ReflectComponent>())
+        .ok_or_else(|| format!("No reflected component named {}", component))?;
+
+    let entities: Vec<Entity> = world.query::<Entity>().iter(world).collect();
+    Ok(entities
+        .into_iter()
+        .filter(|entity| reflect.reflect_component(world, *entity).is_some())
+        .map(|entity| FoundEntity {
+            entity,
+            name: world
+                .get::<Name>(entity)
+                .map(|name| name.as_str().to_string()),
+            position: world
+                .get::<GlobalTransform>(entity)
+                .map(|trans| trans.translation.xy()),
+        })
+        .collect())
+}
+
+/// The live entity with index `id`, as shown before the `v` in `{:?}`.
+pub fn entity_by_id(world: &mut World, id: u32) -> Option<Entity> {
+    world
+        .query::<Entity>()
+        .iter(world)
+        .find(|entity| entity.id() == id)
+}
@@ -0,0 +1,206 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{app::AppExit, input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    rng::GameRng,
+    settings::{Action, Settings},
+    time_scale::TimeScale,
+    AppState,
+};
+
+/// Game time per tick while recording or replaying.
+const TICK_SECS: f32 = 1. / 60.;
+/// Recordings are written out this often, so a crash doesn't lose them.
+const SAVE_EVERY_TICKS: usize = 600;
+
+/// On-disk format of a replay: the actions held on each tick, from the first
+/// frame of gameplay on.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Replay {
+    pub tick_secs: f32,
+    /// `GameRng` seed, reseeded with on the first tick. Replays recorded
+    /// before it was saved play with whatever seed the run has.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Bit `i` is set while `Action::ALL[i]` is held.
+    pub ticks: Vec<u8>,
+}
+
+impl Replay {
+    fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&source).map_err(|err| err.to_string())
+    }
+
+    fn save(&self, path: &Path) {
+        let result = ron::to_string(self)
+            .map_err(|err| err.to_string())
+            .and_then(|source| std::fs::write(path, source).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            warn!("Failed to save replay to {:?}: {}", path, err);
+        }
+    }
+}
+
+enum ReplayMode {
+    Off,
+    Recording,
+    Playing,
+}
+
+/// Set up from the command line, see `ReplaySession::from_args`.
+pub struct ReplaySession {
+    mode: ReplayMode,
+    path: PathBuf,
+    replay: Replay,
+    /// Ticks start counting on entering `AppState::Playing`, so time spent
+    /// loading doesn't matter.
+    started: bool,
+    tick: usize,
+}
+
+impl Default for ReplaySession {
+    fn default() -> Self {
+        Self {
+            mode: ReplayMode::Off,
+            path: PathBuf::new(),
+            replay: Replay::default(),
+            started: false,
+            tick: 0,
+        }
+    }
+}
+
+impl ReplaySession {
+    /// `--record <file>` records the session's input to `file`,
+    /// `--replay <file>` plays one back. Anything else leaves replays off.
+    pub fn from_args(flag: Option<&str>, path: Option<String>) -> Self {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => return Self::default(),
+        };
+        match flag {
+            Some("--record") => Self {
+                mode: ReplayMode::Recording,
+                path,
+                replay: Replay {
+                    tick_secs: TICK_SECS,
+                    seed: None,
+                    ticks: Vec::new(),
+                },
+                ..Default::default()
+            },
+            Some("--replay") => match Replay::load(&path) {
+                Ok(replay) => Self {
+                    mode: ReplayMode::Playing,
+                    path,
+                    replay,
+                    ..Default::default()
+                },
+                Err(err) => {
+                    eprintln!("Failed to load replay {:?}: {}", path, err);
+                    Self::default()
+                }
+            },
+            _ => Self::default(),
+        }
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplaySession>()
+            .add_startup_system(fix_time_step)
+            .add_system_to_stage(CoreStage::PreUpdate, replay_input.after(InputSystem))
+            .add_system_to_stage(CoreStage::Last, save_recording_on_exit);
+    }
+}
+
+/// Both recording and playback step time by the replay's tick, so physics
+/// and movement play out the same on replay.
+fn fix_time_step(session: Res<ReplaySession>, mut time_scale: ResMut<TimeScale>) {
+    if !matches!(session.mode, ReplayMode::Off) {
+        time_scale.fixed_delta = Some(session.replay.tick_secs);
+    }
+}
+
+fn replay_input(
+    mut session: ResMut<ReplaySession>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut rng: ResMut<GameRng>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+) {
+    if matches!(session.mode, ReplayMode::Off) {
+        return;
+    }
+    if !session.started {
+        if *state.current() != AppState::Playing {
+            return;
+        }
+        session.started = true;
+        // Both start from the same seed at the same point, whatever the
+        // menus drew before
+        let seed = *session.replay.seed.get_or_insert(rng.seed());
+        rng.reseed(seed);
+    }
+
+    let session = &mut *session;
+    let bindings = &settings.key_bindings;
+    match session.mode {
+        ReplayMode::Off => {}
+        ReplayMode::Recording => {
+            let held = Action::ALL
+                .iter()
+                .enumerate()
+                .filter(|(_, action)| keys.pressed(bindings.key(**action)))
+                .fold(0, |bits, (i, _)| bits | (1 << i));
+            session.replay.ticks.push(held);
+            if session.replay.ticks.len() % SAVE_EVERY_TICKS == 0 {
+                session.replay.save(&session.path);
+            }
+        }
+        ReplayMode::Playing => {
+            let tick = session.tick;
+            let held = match session.replay.ticks.get(tick) {
+                Some(held) => *held,
+                None => {
+                    info!("Replay {:?} finished after {} ticks", session.path, tick);
+                    for action in Action::ALL {
+                        keys.release(bindings.key(action));
+                    }
+                    session.mode = ReplayMode::Off;
+                    return;
+                }
+            };
+            // Replayed actions override the keyboard
+            for (i, action) in Action::ALL.iter().enumerate() {
+                let key = bindings.key(*action);
+                let was_held = keys.pressed(key);
+                if held & (1 << i) != 0 {
+                    if !was_held {
+                        keys.press(key);
+                    }
+                } else if was_held {
+                    keys.release(key);
+                }
+            }
+            session.tick += 1;
+        }
+    }
+}
+
+fn save_recording_on_exit(mut exit_events: EventReader<AppExit>, session: Res<ReplaySession>) {
+    if exit_events.iter().next().is_some() && matches!(session.mode, ReplayMode::Recording) {
+        session.replay.save(&session.path);
+        info!(
+            "Saved replay of {} ticks to {:?}",
+            session.replay.ticks.len(),
+            session.path
+        );
+    }
+}
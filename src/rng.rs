@@ -0,0 +1,108 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::config::GameConfig;
+
+/// One sequence of random numbers out of `GameRng`. SplitMix64, small and
+/// good enough for gameplay, and any seed works.
+#[derive(Debug, Clone)]
+pub struct RngStream(u64);
+
+impl RngStream {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in `[min, max)`.
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    /// Uniform index into something `len` long, which must not be empty.
+    pub fn index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// Source of all gameplay randomness, so a run can be repeated from its seed,
+/// e.g. by a replay.
+///
+/// Each system draws from its own named stream, so the numbers one gets don't
+/// depend on the order systems happen to run in, or on other systems drawing
+/// more or fewer of them.
+///
+/// Seeded from `GameConfig::seed`, or the clock without one, and reseeded by
+/// the `seed` console command and by replays.
+#[derive(Debug)]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<&'static str, RngStream>,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts every stream over from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::from_seed(seed);
+    }
+
+    /// The stream called `name`, started from the seed mixed with the name on
+    /// first use.
+    pub fn stream(&mut self, name: &'static str) -> &mut RngStream {
+        let seed = self.seed;
+        self.streams.entry(name).or_insert_with(|| {
+            // FNV-1a of the name
+            let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+            });
+            let mut stream = RngStream(seed ^ hash);
+            stream.next_u64();
+            stream
+        })
+    }
+}
+
+/// Seed for runs without one set.
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = app
+            .world
+            .get_resource::<GameConfig>()
+            .and_then(|config| config.seed)
+            .unwrap_or_else(clock_seed);
+        app.insert_resource(GameRng::from_seed(seed))
+            .add_startup_system(log_seed);
+    }
+}
+
+fn log_seed(rng: Res<GameRng>) {
+    info!("Random seed {}", rng.seed());
+}
@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::collision::{Aabb, CollisionSnapshot, CollisionWorld, Velocity};
+
+/// Physics ticks kept around to roll back to.
+pub const ROLLBACK_TICKS: usize = 30;
+
+/// Everything the physics stage reads and writes, as of the end of a tick.
+///
+/// Covers moving entities (anything with a `Velocity`) and AABB entities,
+/// whose global transforms the next tick computes AABBs from. Entities
+/// spawned or despawned since the snapshot are left alone on restore.
+#[derive(Debug, Clone)]
+pub struct PhysicsSnapshot {
+    world: CollisionSnapshot,
+    transforms: Vec<(Entity, Transform, GlobalTransform)>,
+    velocities: Vec<(Entity, Velocity)>,
+}
+
+/// The last `ROLLBACK_TICKS` physics ticks, newest last.
+#[derive(Default)]
+pub struct RollbackBuffer {
+    snapshots: VecDeque<PhysicsSnapshot>,
+}
+
+impl RollbackBuffer {
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn push(&mut self, snapshot: PhysicsSnapshot) {
+        if self.snapshots.len() == ROLLBACK_TICKS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Drops the newest `ticks` snapshots and returns the one before them,
+    /// i.e. the state `ticks` ticks ago. Rolling back further than the buffer
+    /// goes returns the oldest snapshot there is.
+    pub fn rewind(&mut self, ticks: usize) -> Option<PhysicsSnapshot> {
+        let keep = self.snapshots.len().saturating_sub(ticks).max(1);
+        self.snapshots.truncate(keep);
+        self.snapshots.back().cloned()
+    }
+}
+
+/// Rolls physics back by `ticks` ticks at the start of the next physics stage.
+#[derive(Debug, Clone, Copy)]
+pub struct RollbackRequest {
+    pub ticks: usize,
+}
+
+type SnapshotFilter = Or<(With<Velocity>, With<Aabb>)>;
+
+pub fn record_physics_snapshot(
+    collision_world: Res<CollisionWorld>,
+    mut buffer: ResMut<RollbackBuffer>,
+    transform_q: Query<(Entity, &Transform, &GlobalTransform), SnapshotFilter>,
+    velocity_q: Query<(Entity, &Velocity)>,
+) {
+    buffer.push(PhysicsSnapshot {
+        world: collision_world.snapshot(),
+        transforms: transform_q
+            .iter()
+            .map(|(entity, trans, gtrans)| (entity, *trans, *gtrans))
+            .collect(),
+        velocities: velocity_q
+            .iter()
+            .map(|(entity, velocity)| (entity, *velocity))
+            .collect(),
+    });
+}
+
+pub fn apply_rollback(
+    mut requests: EventReader<RollbackRequest>,
+    mut collision_world: ResMut<CollisionWorld>,
+    mut buffer: ResMut<RollbackBuffer>,
+    mut transform_q: Query<(&mut Transform, &mut GlobalTransform), SnapshotFilter>,
+    mut velocity_q: Query<&mut Velocity>,
+) {
+    let ticks: usize = requests.iter().map(|request| request.ticks).sum();
+    if ticks == 0 {
+        return;
+    }
+    let snapshot = match buffer.rewind(ticks) {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+
+    collision_world.restore(snapshot.world);
+    for (entity, trans, gtrans) in snapshot.transforms {
+        if let Ok((mut current_trans, mut current_gtrans)) = transform_q.get_mut(entity) {
+            *current_trans = trans;
+            *current_gtrans = gtrans;
+        }
+    }
+    for (entity, velocity) in snapshot.velocities {
+        if let Ok(mut current) = velocity_q.get_mut(entity) {
+            *current = velocity;
+        }
+    }
+}
+
+/// Debug hotkey: F10 rewinds physics as far back as the buffer goes.
+pub fn rollback_hotkey(
+    keys: Res<Input<KeyCode>>,
+    buffer: Res<RollbackBuffer>,
+    mut requests: EventWriter<RollbackRequest>,
+) {
+    if keys.just_pressed(KeyCode::F10) {
+        info!("Rolling physics back {} ticks", buffer.len());
+        requests.send(RollbackRequest {
+            ticks: buffer.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A snapshot telling apart the tick it was taken at by its one velocity.
+    fn snapshot(tick: usize) -> PhysicsSnapshot {
+        PhysicsSnapshot {
+            world: CollisionWorld::default().snapshot(),
+            transforms: Vec::new(),
+            velocities: vec![(Entity::from_raw(0), Velocity(Vec2::new(tick as f32, 0.)))],
+        }
+    }
+
+    fn tick_of(snapshot: &PhysicsSnapshot) -> usize {
+        let (_, velocity) = snapshot.velocities[0];
+        velocity.0.x as usize
+    }
+
+    fn buffer(ticks: usize) -> RollbackBuffer {
+        let mut buffer = RollbackBuffer::default();
+        for tick in 0..ticks {
+            buffer.push(snapshot(tick));
+        }
+        buffer
+    }
+
+    #[test]
+    fn push_drops_the_oldest_snapshot_when_full() {
+        let mut buffer = buffer(ROLLBACK_TICKS + 5);
+        assert_eq!(buffer.len(), ROLLBACK_TICKS);
+        assert_eq!(tick_of(&buffer.rewind(ROLLBACK_TICKS).unwrap()), 5);
+    }
+
+    #[test]
+    fn rewind_returns_the_state_ticks_ago() {
+        let mut buffer = buffer(10);
+        assert_eq!(tick_of(&buffer.rewind(0).unwrap()), 9);
+        assert_eq!(buffer.len(), 10);
+        assert_eq!(tick_of(&buffer.rewind(3).unwrap()), 6);
+        assert_eq!(buffer.len(), 7);
+    }
+
+    #[test]
+    fn rewind_past_the_buffer_returns_the_oldest_snapshot() {
+        let mut buffer = buffer(10);
+        assert_eq!(tick_of(&buffer.rewind(25).unwrap()), 0);
+        assert_eq!(buffer.len(), 1);
+        assert!(RollbackBuffer::default().rewind(1).is_none());
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin},
+    shapes,
+};
+
+use crate::{
+    collision::{AabbBundle, AabbKind, CollisionBehavior, SensorEvent},
+    group::{spawn_group, GroupTag},
+    layer::RenderLayerGroup,
+    phase::{Phase, PhaseExt},
+    time_scale::TimeScale,
+    PlayerTag,
+};
+
+const ROOF_COLOR: Color = Color::rgb(0.55, 0.22, 0.18);
+/// Alpha of roofs the player is under.
+const FADED_ALPHA: f32 = 0.25;
+/// Alpha change per second while fading.
+const FADE_SPEED: f32 = 4.;
+
+/// While the player is inside this entity's sensor, the `Roof`s of its group
+/// fade out so the player stays visible under them.
+#[derive(Component, Debug, Default)]
+pub struct UnderRoof {
+    occupied: bool,
+}
+
+/// Overhead shape faded out while the player is under it, see `UnderRoof`.
+#[derive(Component, Debug)]
+pub struct Roof {
+    color: Color,
+    alpha: f32,
+}
+
+pub struct RoofPlugin;
+
+impl Plugin for RoofPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Gameplay)
+                .with_system(track_under_roof),
+        )
+        .add_system_set(SystemSet::new().in_phase(Phase::Ui).with_system(fade_roofs));
+    }
+}
+
+/// Spawns a roof covering `size` around `position`, with the region under it
+/// in the same group. Returns the group's roots.
+pub fn spawn_roof(
+    commands: &mut Commands,
+    group: &'static str,
+    position: Vec2,
+    size: Vec2,
+) -> Vec<Entity> {
+    spawn_group(commands, group, |commands| {
+        let under = commands
+            .spawn_bundle((
+                Transform::from_translation(position.extend(0.)),
+                GlobalTransform::default(),
+            ))
+            .with_children(|parent| {
+                parent.spawn_bundle(AabbBundle::new(
+                    size,
+                    AabbKind::Sensor,
+                    CollisionBehavior::None,
+                    Color::ORANGE,
+                ));
+            })
+            .insert(UnderRoof::default())
+            .id();
+        let roof = commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Rectangle {
+                    extents: size,
+                    origin: RectangleOrigin::Center,
+                },
+                DrawMode::Fill(FillMode::color(ROOF_COLOR)),
+                Transform::from_translation(RenderLayerGroup::Overhead.at(position)),
+            ))
+            .insert(RenderLayerGroup::Overhead)
+            .insert(Roof {
+                color: ROOF_COLOR,
+                alpha: 1.,
+            })
+            .id();
+        vec![under, roof]
+    })
+}
+
+fn track_under_roof(
+    mut sensor_events: EventReader<SensorEvent>,
+    player_q: Query<(), With<PlayerTag>>,
+    mut under_q: Query<&mut UnderRoof>,
+) {
+    for event in sensor_events.iter() {
+        let (sensor, other, entered) = match *event {
+            SensorEvent::Entered { sensor, other } => (sensor, other, true),
+            SensorEvent::Exited { sensor, other } => (sensor, other, false),
+        };
+        if player_q.get(other).is_err() {
+            continue;
+        }
+        if let Ok(mut under) = under_q.get_mut(sensor) {
+            under.occupied = entered;
+        }
+    }
+}
+
+/// Only redraws roofs while they fade, as changing the `DrawMode` tessellates
+/// them again.
+fn fade_roofs(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    under_q: Query<(&UnderRoof, &GroupTag)>,
+    mut roof_q: Query<(&mut Roof, &mut DrawMode, &GroupTag)>,
+) {
+    let occupied: HashSet<&str> = under_q
+        .iter()
+        .filter(|(under, _)| under.occupied)
+        .map(|(_, group)| group.0)
+        .collect();
+    let step = FADE_SPEED * time_scale.delta_seconds(&time);
+    for (mut roof, mut draw_mode, group) in roof_q.iter_mut() {
+        let target = if occupied.contains(group.0) {
+            FADED_ALPHA
+        } else {
+            1.
+        };
+        if roof.alpha == target {
+            continue;
+        }
+        roof.alpha = if roof.alpha < target {
+            (roof.alpha + step).min(target)
+        } else {
+            (roof.alpha - step).max(target)
+        };
+        let mut color = roof.color;
+        color.set_a(roof.alpha);
+        *draw_mode = DrawMode::Fill(FillMode::color(color));
+    }
+}
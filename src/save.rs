@@ -0,0 +1,406 @@
+use std::{
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::CollisionWorlds,
+    cooldown::{RepeatingTimer, TimerAppExt},
+    inventory::Inventory,
+    localization::Localization,
+    notify::Notify,
+    origin::{move_origin_to, WorldOrigin},
+    persist::{apply_scene, capture_scene},
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    quest::{QuestCompleted, QuestLog},
+    AppState,
+};
+
+static DATA_DIR_NAME: &str = "mini-exp-1";
+/// Minutes of play between timed autosaves.
+const AUTOSAVE_MINUTES: f32 = 5.;
+
+#[derive(Debug)]
+pub enum SaveError {
+    NoDataDir,
+    EmptySlot,
+    Io(std::io::Error),
+    Ron(ron::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::NoDataDir => write!(f, "no data directory available"),
+            SaveError::EmptySlot => write!(f, "slot is empty"),
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::Ron(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(err: ron::Error) -> Self {
+        SaveError::Ron(err)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SlotMetadata {
+    pub name: String,
+    pub playtime_secs: f64,
+    pub quest_summary: String,
+    /// Seconds since the Unix epoch, for replacing the oldest autosave.
+    #[serde(default)]
+    pub saved_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct QuestProgress {
+    pub active: Option<String>,
+    pub completed: Vec<String>,
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub delivered: u32,
+}
+
+/// Resources of a save. Entities are saved next to it as a scene of their
+/// persisted components, see `persist::Persist`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveData {
+    pub metadata: SlotMetadata,
+    pub quest: QuestProgress,
+    pub inventory: Vec<(String, u32)>,
+    #[serde(default)]
+    pub stats: PlayerStats,
+    /// `WorldOrigin` the saved transforms are relative to.
+    #[serde(default)]
+    pub origin: (f32, f32),
+}
+
+pub struct SaveManager {
+    dir: Option<PathBuf>,
+    /// Metadata of every slot, `None` for empty slots.
+    pub slots: Vec<Option<SlotMetadata>>,
+}
+
+impl Default for SaveManager {
+    fn default() -> Self {
+        let mut manager = Self {
+            dir: dirs::data_dir().map(|dir| dir.join(DATA_DIR_NAME).join("saves")),
+            slots: Vec::new(),
+        };
+        manager.refresh();
+        manager
+    }
+}
+
+impl SaveManager {
+    /// Slots saved to from the menu.
+    pub const SLOT_COUNT: usize = 3;
+    /// Slots after the manual ones, taken in turns by autosaves.
+    pub const AUTOSAVE_COUNT: usize = 2;
+
+    pub fn is_autosave(slot: usize) -> bool {
+        slot >= Self::SLOT_COUNT
+    }
+
+    /// File name of a slot, without extension.
+    fn slot_name(slot: usize) -> String {
+        if Self::is_autosave(slot) {
+            format!("autosave_{}", slot - Self::SLOT_COUNT)
+        } else {
+            format!("slot_{}", slot)
+        }
+    }
+
+    fn slot_path(&self, slot: usize) -> Result<PathBuf, SaveError> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.ron", Self::slot_name(slot))))
+            .ok_or(SaveError::NoDataDir)
+    }
+
+    fn scene_path(&self, slot: usize) -> Result<PathBuf, SaveError> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.scn.ron", Self::slot_name(slot))))
+            .ok_or(SaveError::NoDataDir)
+    }
+
+    /// The autosave slot to write next: an empty one, or else the oldest.
+    pub fn next_autosave_slot(&self) -> usize {
+        (Self::SLOT_COUNT..Self::SLOT_COUNT + Self::AUTOSAVE_COUNT)
+            .min_by_key(|slot| {
+                self.slots[*slot]
+                    .as_ref()
+                    .map_or(0, |metadata| metadata.saved_at)
+            })
+            .unwrap()
+    }
+
+    /// Re-reads the metadata of every slot from disk.
+    pub fn refresh(&mut self) {
+        self.slots = (0..Self::SLOT_COUNT + Self::AUTOSAVE_COUNT)
+            .map(|slot| self.load_slot(slot).ok().map(|data| data.metadata))
+            .collect();
+    }
+
+    pub fn save_slot(
+        &mut self,
+        slot: usize,
+        data: &SaveData,
+        scene: &str,
+    ) -> Result<(), SaveError> {
+        let path = self.slot_path(slot)?;
+        let source = ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())?;
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        // The slot file last, it's what makes the slot show up as used
+        write_atomic(&self.scene_path(slot)?, scene)?;
+        write_atomic(&path, &source)?;
+        self.slots[slot] = Some(data.metadata.clone());
+        Ok(())
+    }
+
+    pub fn load_slot(&self, slot: usize) -> Result<SaveData, SaveError> {
+        let path = self.slot_path(slot)?;
+        if !path.exists() {
+            return Err(SaveError::EmptySlot);
+        }
+        let source = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&source)?)
+    }
+
+    /// Saves from before entities were saved as scenes have none.
+    pub fn load_scene(&self, slot: usize) -> Result<Option<String>, SaveError> {
+        let path = self.scene_path(slot)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(std::fs::read_to_string(path)?))
+    }
+
+    pub fn delete_slot(&mut self, slot: usize) -> Result<(), SaveError> {
+        for path in [self.slot_path(slot)?, self.scene_path(slot)?] {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        self.slots[slot] = None;
+        Ok(())
+    }
+}
+
+/// Writes to a temporary file first and renames it over `path`, so a crash
+/// mid-write leaves the previous file intact rather than a truncated one.
+fn write_atomic(path: &Path, contents: &str) -> Result<(), SaveError> {
+    let temp_path = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SlotAction {
+    Save,
+    Load,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SlotRequest {
+    pub slot: usize,
+    pub action: SlotAction,
+}
+
+/// Entity scenes need exclusive world access, so `handle_slot_requests` leaves
+/// them to `finish_slot_requests`.
+#[derive(Default)]
+struct PendingScenes {
+    saves: Vec<(usize, SaveData)>,
+    load: Option<String>,
+    /// Origin of the loaded scene, shifted to before applying it.
+    load_origin: Vec2,
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveManager>()
+            .init_resource::<PendingScenes>()
+            .insert_resource(RepeatingTimer::<Autosave>::new(AUTOSAVE_MINUTES * 60.))
+            .add_repeating_timer::<Autosave>()
+            .add_event::<SlotRequest>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Gameplay)
+                    .with_system(handle_slot_requests),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(autosave),
+            )
+            .add_system(finish_slot_requests.exclusive_system().at_end());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_slot_requests(
+    mut slot_requests: EventReader<SlotRequest>,
+    mut save_manager: ResMut<SaveManager>,
+    mut stats: ResMut<PlayerStats>,
+    mut quest_log: ResMut<QuestLog>,
+    mut inventory: ResMut<Inventory>,
+    origin: Res<WorldOrigin>,
+    localization: Res<Localization>,
+    mut notifications: EventWriter<Notify>,
+    mut pending: ResMut<PendingScenes>,
+) {
+    for request in slot_requests.iter() {
+        let result = match request.action {
+            SlotAction::Save => {
+                let name = if SaveManager::is_autosave(request.slot) {
+                    format!("Autosave {}", request.slot - SaveManager::SLOT_COUNT + 1)
+                } else {
+                    format!("Slot {}", request.slot + 1)
+                };
+                let data = SaveData {
+                    metadata: SlotMetadata {
+                        name,
+                        playtime_secs: stats.play_time_secs,
+                        quest_summary: quest_log.summary(&localization),
+                        saved_at: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |since| since.as_secs()),
+                    },
+                    quest: QuestProgress {
+                        active: quest_log.active.clone(),
+                        completed: quest_log.completed.clone(),
+                        flags: quest_log.flags.iter().cloned().collect(),
+                        delivered: quest_log.delivered,
+                    },
+                    inventory: inventory
+                        .items
+                        .iter()
+                        .map(|(item, count)| (item.clone(), *count))
+                        .collect(),
+                    stats: stats.clone(),
+                    origin: origin.0.into(),
+                };
+                pending.saves.push((request.slot, data));
+                continue;
+            }
+            SlotAction::Load => save_manager.load_slot(request.slot).and_then(|data| {
+                pending.load = save_manager.load_scene(request.slot)?;
+                pending.load_origin = data.origin.into();
+                *stats = PlayerStats {
+                    // Saves from before stats were tracked only have the play time
+                    play_time_secs: data.metadata.playtime_secs,
+                    ..data.stats
+                };
+                quest_log.active = data.quest.active;
+                quest_log.completed = data.quest.completed;
+                quest_log.flags = data.quest.flags.into_iter().collect();
+                quest_log.delivered = data.quest.delivered;
+                inventory.items = data.inventory.into_iter().collect();
+                Ok(())
+            }),
+            SlotAction::Delete => save_manager.delete_slot(request.slot),
+        };
+        let notification = match request.action {
+            SlotAction::Save => "notify.game_saved",
+            SlotAction::Load => "notify.game_loaded",
+            SlotAction::Delete => "notify.slot_deleted",
+        };
+        match result {
+            Ok(()) => notifications.send(Notify::new(localization.get(notification))),
+            Err(err) => warn!(
+                "{:?} of slot {} failed: {}",
+                request.action, request.slot, err
+            ),
+        }
+    }
+}
+
+fn finish_slot_requests(world: &mut World) {
+    let pending = std::mem::take(&mut *world.get_resource_mut::<PendingScenes>().unwrap());
+    if let Some(scene) = pending.load {
+        move_origin_to(world, pending.load_origin);
+        if let Err(err) = apply_scene(world, &scene) {
+            warn!("Loading saved entities failed: {}", err);
+        }
+    }
+    for (slot, data) in pending.saves {
+        let result = capture_scene(world)
+            .map_err(SaveError::from)
+            .and_then(|scene| {
+                world
+                    .get_resource_mut::<SaveManager>()
+                    .unwrap()
+                    .save_slot(slot, &data, &scene)
+            });
+        match result {
+            Ok(()) => {
+                let key = if SaveManager::is_autosave(slot) {
+                    "notify.autosaved"
+                } else {
+                    "notify.game_saved"
+                };
+                let text = world.get_resource::<Localization>().unwrap().get(key);
+                world
+                    .get_resource_mut::<Events<Notify>>()
+                    .unwrap()
+                    .send(Notify::new(text));
+            }
+            Err(err) => warn!("Save of slot {} failed: {}", slot, err),
+        }
+    }
+}
+
+/// Marks the timer of timed autosaves.
+struct Autosave;
+
+/// Autosaves when a quest is completed, when the active collision world
+/// changes (e.g. walking into a building) and every `AUTOSAVE_MINUTES` of
+/// play, taking the autosave slots in turns.
+fn autosave(
+    mut timer: ResMut<RepeatingTimer<Autosave>>,
+    mut last_world: Local<Option<String>>,
+    collision_worlds: Res<CollisionWorlds>,
+    save_manager: Res<SaveManager>,
+    mut quest_completed: EventReader<QuestCompleted>,
+    mut slot_requests: EventWriter<SlotRequest>,
+) {
+    let quest_done = quest_completed.iter().count() > 0;
+    let world = collision_worlds.active();
+    let world_changed = last_world.as_deref() != Some(world);
+    // Entering the first world isn't a transition
+    let transitioned = world_changed && last_world.is_some();
+    if world_changed {
+        *last_world = Some(world.to_string());
+    }
+
+    if quest_done || transitioned || timer.is_ready() {
+        timer.reset();
+        slot_requests.send(SlotRequest {
+            slot: save_manager.next_autosave_slot(),
+            action: SlotAction::Save,
+        });
+    }
+}
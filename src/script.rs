@@ -0,0 +1,404 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST, FLOAT, INT};
+
+use crate::{
+    anim::{AnimState, AnimStateMachine},
+    collision::Velocity,
+    dialogue::StartDialogue,
+    frame_budget::TimedExt,
+    interaction::{InteractEvent, InteractionSystem},
+    phase::{Phase, PhaseExt},
+    quest::QuestLog,
+    time_scale::TimeScale,
+    trigger::{TriggerFired, TriggerSystem},
+    AppState, PlayerTag,
+};
+
+static SCRIPTS_DIR: &str = "assets/scripts";
+const RELOAD_CHECK_SECS: f32 = 1.;
+/// Keeps a runaway loop in a script from freezing the game.
+const MAX_OPERATIONS: u64 = 50_000;
+
+/// Runs `assets/scripts/<path>` for this entity.
+///
+/// Scripts can define `update(me, dt)`, called every frame, `interact(me)`,
+/// called when the player interacts with the entity, and `enter(me)`, called
+/// when the entity's `TriggerZone` fires. `me` is the entity's id as used by
+/// the script API, see `ScriptEngine::new`.
+#[derive(Component, Debug, Clone)]
+pub struct Script {
+    pub path: String,
+}
+
+impl Script {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+/// Requests made by scripts, applied once they've all run.
+#[derive(Debug, Clone)]
+enum ScriptCommand {
+    Move(Entity, Vec2),
+    Animate(Entity, AnimState),
+    Dialogue(Entity, String),
+    SetFlag(String),
+}
+
+/// World state scripts can read, refreshed before they run.
+#[derive(Default)]
+struct ScriptContext {
+    positions: HashMap<Entity, Vec2>,
+    names: HashMap<String, Entity>,
+    player: Option<Entity>,
+    flags: Vec<String>,
+    vars: HashMap<Entity, Map>,
+    commands: Vec<ScriptCommand>,
+}
+
+struct LoadedScript {
+    ast: Option<AST>,
+    modified: Option<SystemTime>,
+}
+
+pub struct ScriptEngine {
+    engine: Engine,
+    context: Arc<Mutex<ScriptContext>>,
+    scripts: HashMap<String, LoadedScript>,
+}
+
+fn script_id(entity: Entity) -> INT {
+    entity.to_bits() as INT
+}
+
+fn script_entity(id: INT) -> Entity {
+    Entity::from_bits(id as u64)
+}
+
+impl ScriptEngine {
+    /// Entities are passed to and from scripts as integer ids, `-1` for none.
+    /// Scripts can't touch the world directly, only through:
+    ///
+    /// - `player()`, `find(name)`: ids of the player or a named entity
+    /// - `position(id)`: `[x, y]`, or `()` for unknown entities
+    /// - `move_entity(id, vx, vy)`: sets the velocity, in floats
+    /// - `play_animation(id, state)`: `"idle"`, `"walk"`, `"sleep"`,
+    ///   `"attack"`, `"eat"` or `"pet"`, facing the way the entity moves
+    /// - `start_dialogue(id, node)`: talk to `id` starting at `node`
+    /// - `set_flag(flag)`, `has_flag(flag)`: quest flags
+    /// - `get_var(id, key)`, `set_var(id, key, value)`: per entity state kept
+    ///   between calls, `()` if unset
+    pub fn new() -> Self {
+        let context = Arc::new(Mutex::new(ScriptContext::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let ctx = context.clone();
+        engine.register_fn("player", move || -> INT {
+            ctx.lock().unwrap().player.map_or(-1, script_id)
+        });
+        let ctx = context.clone();
+        engine.register_fn("find", move |name: &str| -> INT {
+            ctx.lock()
+                .unwrap()
+                .names
+                .get(name)
+                .copied()
+                .map_or(-1, script_id)
+        });
+        let ctx = context.clone();
+        engine.register_fn("position", move |id: INT| -> Dynamic {
+            match ctx.lock().unwrap().positions.get(&script_entity(id)) {
+                Some(position) => {
+                    let xy: Array =
+                        vec![(position.x as FLOAT).into(), (position.y as FLOAT).into()];
+                    xy.into()
+                }
+                None => Dynamic::UNIT,
+            }
+        });
+        let ctx = context.clone();
+        engine.register_fn("move_entity", move |id: INT, vx: FLOAT, vy: FLOAT| {
+            let velocity = Vec2::new(vx as f32, vy as f32);
+            ctx.lock()
+                .unwrap()
+                .commands
+                .push(ScriptCommand::Move(script_entity(id), velocity));
+        });
+        let ctx = context.clone();
+        engine.register_fn("play_animation", move |id: INT, state: &str| {
+            let state = match state {
+                "idle" => AnimState::Idle,
+                "walk" => AnimState::Walk,
+                "sleep" => AnimState::Sleep,
+                "attack" => AnimState::Attack,
+                "eat" => AnimState::Eat,
+                "pet" => AnimState::Pet,
+                _ => {
+                    warn!("Unknown animation state {:?} in script", state);
+                    return;
+                }
+            };
+            ctx.lock()
+                .unwrap()
+                .commands
+                .push(ScriptCommand::Animate(script_entity(id), state));
+        });
+        let ctx = context.clone();
+        engine.register_fn("start_dialogue", move |id: INT, node: &str| {
+            ctx.lock()
+                .unwrap()
+                .commands
+                .push(ScriptCommand::Dialogue(script_entity(id), node.to_string()));
+        });
+        let ctx = context.clone();
+        engine.register_fn("set_flag", move |flag: &str| {
+            let mut ctx = ctx.lock().unwrap();
+            // Visible to `has_flag` right away, not just next frame
+            ctx.flags.push(flag.to_string());
+            ctx.commands.push(ScriptCommand::SetFlag(flag.to_string()));
+        });
+        let ctx = context.clone();
+        engine.register_fn("has_flag", move |flag: &str| -> bool {
+            ctx.lock().unwrap().flags.iter().any(|f| f == flag)
+        });
+        let ctx = context.clone();
+        engine.register_fn("get_var", move |id: INT, key: &str| -> Dynamic {
+            ctx.lock()
+                .unwrap()
+                .vars
+                .get(&script_entity(id))
+                .and_then(|vars| vars.get(key).cloned())
+                .unwrap_or(Dynamic::UNIT)
+        });
+        let ctx = context.clone();
+        engine.register_fn("set_var", move |id: INT, key: &str, value: Dynamic| {
+            ctx.lock()
+                .unwrap()
+                .vars
+                .entry(script_entity(id))
+                .or_default()
+                .insert(key.into(), value);
+        });
+
+        Self {
+            engine,
+            context,
+            scripts: HashMap::default(),
+        }
+    }
+
+    fn script_path(path: &str) -> PathBuf {
+        Path::new(SCRIPTS_DIR).join(path)
+    }
+
+    /// (Re)compiles `path` if it changed on disk since it was last loaded.
+    /// A script that fails to compile keeps running its last good version.
+    fn reload(&mut self, path: &str) {
+        let full_path = Self::script_path(path);
+        let modified = std::fs::metadata(&full_path)
+            .and_then(|meta| meta.modified())
+            .ok();
+        if let Some(loaded) = self.scripts.get(path) {
+            if loaded.modified == modified {
+                return;
+            }
+        }
+
+        let ast = match std::fs::read_to_string(&full_path) {
+            Ok(source) => match self.engine.compile(&source) {
+                Ok(ast) => {
+                    info!("Loaded script {:?}", full_path);
+                    Some(ast)
+                }
+                Err(err) => {
+                    error!("Failed to compile {:?}: {}", full_path, err);
+                    None
+                }
+            },
+            Err(err) => {
+                error!("Failed to read {:?}: {}", full_path, err);
+                None
+            }
+        };
+        let loaded = self
+            .scripts
+            .entry(path.to_string())
+            .or_insert(LoadedScript {
+                ast: None,
+                modified: None,
+            });
+        loaded.modified = modified;
+        if ast.is_some() {
+            loaded.ast = ast;
+        }
+    }
+
+    /// Calls `hook` in the script at `path`, if it defines one.
+    fn call(&self, path: &str, hook: &str, args: impl rhai::FuncArgs) {
+        let ast = match self
+            .scripts
+            .get(path)
+            .and_then(|loaded| loaded.ast.as_ref())
+        {
+            Some(ast) => ast,
+            None => return,
+        };
+        if !ast.iter_functions().any(|f| f.name == hook) {
+            return;
+        }
+        if let Err(err) = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), ast, hook, args)
+        {
+            error!("Error in {}::{}: {}", path, hook, err);
+        }
+    }
+}
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ScriptSystem {
+    Reload,
+    SyncContext,
+    Run,
+}
+
+pub struct ScriptPlugin;
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScriptEngine>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ai)
+                    .with_system(reload_scripts.timed().label(ScriptSystem::Reload)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Ai)
+                    .with_system(sync_script_context.timed().label(ScriptSystem::SyncContext))
+                    .with_system(
+                        run_scripts
+                            .timed()
+                            .label(ScriptSystem::Run)
+                            .after(ScriptSystem::SyncContext)
+                            .after(ScriptSystem::Reload)
+                            .after(InteractionSystem::Interact)
+                            .after(TriggerSystem::Fire),
+                    )
+                    .with_system(apply_script_commands.after(ScriptSystem::Run)),
+            );
+    }
+}
+
+fn reload_scripts(
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+    mut engine: ResMut<ScriptEngine>,
+    new_scripts_q: Query<&Script, Added<Script>>,
+    script_q: Query<&Script>,
+) {
+    // New scripts load right away, changes to loaded ones are polled for
+    for script in new_scripts_q.iter() {
+        engine.reload(&script.path);
+    }
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(RELOAD_CHECK_SECS, true));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    for script in script_q.iter() {
+        engine.reload(&script.path);
+    }
+}
+
+fn sync_script_context(
+    engine: Res<ScriptEngine>,
+    quest_log: Res<QuestLog>,
+    player_q: Query<Entity, With<PlayerTag>>,
+    entity_q: Query<(Entity, &GlobalTransform, Option<&Name>)>,
+    mut removed_scripts: RemovedComponents<Script>,
+) {
+    let mut ctx = engine.context.lock().unwrap();
+    ctx.positions.clear();
+    ctx.names.clear();
+    for (entity, transform, name) in entity_q.iter() {
+        ctx.positions.insert(entity, transform.translation.xy());
+        if let Some(name) = name {
+            ctx.names.insert(name.as_str().to_string(), entity);
+        }
+    }
+    ctx.player = player_q.get_single().ok();
+    ctx.flags = quest_log.flags.iter().cloned().collect();
+    for entity in removed_scripts.iter() {
+        ctx.vars.remove(&entity);
+    }
+}
+
+fn run_scripts(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    engine: Res<ScriptEngine>,
+    mut interact_events: EventReader<InteractEvent>,
+    mut trigger_events: EventReader<TriggerFired>,
+    script_q: Query<(Entity, &Script)>,
+) {
+    let dt = time_scale.delta_seconds(&time) as FLOAT;
+    for event in interact_events.iter() {
+        if let Ok((entity, script)) = script_q.get(event.target) {
+            engine.call(&script.path, "interact", (script_id(entity),));
+        }
+    }
+    for event in trigger_events.iter() {
+        if let Ok((entity, script)) = script_q.get(event.zone) {
+            engine.call(&script.path, "enter", (script_id(entity),));
+        }
+    }
+    for (entity, script) in script_q.iter() {
+        engine.call(&script.path, "update", (script_id(entity), dt));
+    }
+}
+
+fn apply_script_commands(
+    engine: Res<ScriptEngine>,
+    mut quest_log: ResMut<QuestLog>,
+    mut dialogues: EventWriter<StartDialogue>,
+    mut velocity_q: Query<&mut Velocity>,
+    mut machine_q: Query<&mut AnimStateMachine>,
+) {
+    let commands = std::mem::take(&mut engine.context.lock().unwrap().commands);
+    for command in commands {
+        match command {
+            ScriptCommand::Move(entity, velocity) => {
+                if let Ok(mut vel) = velocity_q.get_mut(entity) {
+                    vel.0 = velocity;
+                }
+            }
+            ScriptCommand::Animate(entity, state) => {
+                if let Ok(mut machine) = machine_q.get_mut(entity) {
+                    if let Ok(vel) = velocity_q.get(entity) {
+                        machine.face(vel.0);
+                    }
+                    machine.set_state(state);
+                }
+            }
+            ScriptCommand::Dialogue(speaker, node) => {
+                dialogues.send(StartDialogue { speaker, node });
+            }
+            ScriptCommand::SetFlag(flag) => {
+                quest_log.flags.insert(flag);
+            }
+        }
+    }
+}
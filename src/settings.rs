@@ -0,0 +1,356 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, utils::HashMap, window::WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::DebugRenderStyle,
+    frame_budget::TimedExt,
+    phase::{Phase, PhaseExt},
+};
+
+static CONFIG_DIR_NAME: &str = "mini-exp-1";
+static SETTINGS_FILE_NAME: &str = "settings.ron";
+/// Seconds a press of a buffered action counts for, see `ActionBuffer`.
+const BUFFER_SECS: f32 = 0.12;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowModeSetting {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub fn window_mode(self) -> WindowMode {
+        match self {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            WindowModeSetting::Windowed => WindowModeSetting::Borderless,
+            WindowModeSetting::Borderless => WindowModeSetting::Fullscreen,
+            WindowModeSetting::Fullscreen => WindowModeSetting::Windowed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Left,
+    Right,
+    Up,
+    Down,
+    Sprint,
+    Interact,
+    Dash,
+    UseItem,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::Left,
+        Action::Right,
+        Action::Up,
+        Action::Down,
+        Action::Sprint,
+        Action::Interact,
+        Action::Dash,
+        Action::UseItem,
+    ];
+
+    /// Seconds a press stays in the `ActionBuffer`, zero for actions that
+    /// are held rather than pressed.
+    pub fn buffer_secs(self) -> f32 {
+        match self {
+            Action::Interact | Action::Dash | Action::UseItem => BUFFER_SECS,
+            Action::Left | Action::Right | Action::Up | Action::Down | Action::Sprint => 0.,
+        }
+    }
+}
+
+/// Recent presses of buffered actions, so pressing a little early, e.g. just
+/// before a cooldown ends or during hit-stop, still counts once the action
+/// can happen. Buffered in real time, as hit-stop slows down game time.
+#[derive(Default)]
+pub struct ActionBuffer {
+    /// Seconds left for each buffered press.
+    pressed: HashMap<Action, f32>,
+}
+
+impl ActionBuffer {
+    /// Takes the buffered press of `action`, if there is one.
+    pub fn consume(&mut self, action: Action) -> bool {
+        self.pressed.remove(&action).is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub sprint: KeyCode,
+    pub interact: KeyCode,
+    pub dash: KeyCode,
+    pub use_item: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: KeyCode::A,
+            right: KeyCode::D,
+            up: KeyCode::W,
+            down: KeyCode::S,
+            sprint: KeyCode::LShift,
+            interact: KeyCode::E,
+            dash: KeyCode::Space,
+            use_item: KeyCode::F,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::Left => self.left,
+            Action::Right => self.right,
+            Action::Up => self.up,
+            Action::Down => self.down,
+            Action::Sprint => self.sprint,
+            Action::Interact => self.interact,
+            Action::Dash => self.dash,
+            Action::UseItem => self.use_item,
+        }
+    }
+
+    pub fn key_mut(&mut self, action: Action) -> &mut KeyCode {
+        match action {
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down,
+            Action::Sprint => &mut self.sprint,
+            Action::Interact => &mut self.interact,
+            Action::Dash => &mut self.dash,
+            Action::UseItem => &mut self.use_item,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Accessibility {
+    /// Scale of all UI text, including dialogue.
+    pub text_scale: f32,
+    /// Scale of the screen shake, zero turns it off.
+    pub screen_shake: f32,
+    /// Draws debug colliders in a palette that doesn't rely on telling red
+    /// and green apart, see `DebugRenderStyle::high_contrast`.
+    pub high_contrast_debug: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            text_scale: 1.,
+            screen_shake: 1.,
+            high_contrast_debug: false,
+        }
+    }
+}
+
+/// User settings persisted as RON in the platform config directory.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub volume: f32,
+    pub debug_render: bool,
+    pub window_mode: WindowModeSetting,
+    pub key_bindings: KeyBindings,
+    /// One of `localization::LANGUAGES`.
+    pub language: String,
+    pub accessibility: Accessibility,
+    /// Gives quests with a time limit a countdown, see `challenge`.
+    pub timed_mode: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            debug_render: true,
+            window_mode: WindowModeSetting::Windowed,
+            key_bindings: KeyBindings::default(),
+            language: String::from("en"),
+            accessibility: Accessibility::default(),
+            timed_mode: false,
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(SETTINGS_FILE_NAME))
+    }
+
+    /// Loads the settings file, falling back to defaults if it is missing or
+    /// unreadable. Called before the app is built so the window can be
+    /// created with the right mode.
+    pub fn load() -> Self {
+        let source = match Self::path().and_then(|path| std::fs::read_to_string(path).ok()) {
+            Some(source) => source,
+            None => return Self::default(),
+        };
+        match ron::from_str(&source) {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("Ignoring malformed settings file: {}", err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => {
+                warn!("No config directory, settings will not be saved");
+                return;
+            }
+        };
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|source| {
+                std::fs::create_dir_all(path.parent().unwrap())
+                    .and_then(|_| std::fs::write(&path, source))
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            warn!("Failed to save settings to {:?}: {}", path, err);
+        }
+    }
+}
+
+/// Label of `buffer_actions`, which fills the `ActionBuffer` from the
+/// bound keys.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SettingsSystem {
+    BufferActions,
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionBuffer>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(buffer_actions.timed().label(SettingsSystem::BufferActions)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(apply_window_mode)
+                    .with_system(apply_text_scale)
+                    .with_system(apply_debug_palette),
+            );
+    }
+}
+
+fn buffer_actions(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut buffer: ResMut<ActionBuffer>,
+) {
+    let delta = time.delta_seconds();
+    buffer.pressed.retain(|_, secs| {
+        *secs -= delta;
+        *secs > 0.
+    });
+    for action in Action::ALL {
+        let secs = action.buffer_secs();
+        if secs > 0. && keys.just_pressed(settings.key_bindings.key(action)) {
+            buffer.pressed.insert(action, secs);
+        }
+    }
+}
+
+fn apply_window_mode(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = settings.window_mode.window_mode();
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+    }
+}
+
+/// Font sizes UI text was spawned with, before `Accessibility::text_scale`.
+#[derive(Component)]
+struct BaseFontSizes(Vec<f32>);
+
+/// Scales UI text as it's spawned, and all of it when the scale changes.
+fn apply_text_scale(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut new_text_q: Query<(Entity, &mut Text), (With<Node>, Without<BaseFontSizes>)>,
+    mut scaled_text_q: Query<(&BaseFontSizes, &mut Text)>,
+) {
+    let scale = settings.accessibility.text_scale;
+    for (entity, mut text) in new_text_q.iter_mut() {
+        let base: Vec<f32> = text
+            .sections
+            .iter()
+            .map(|section| section.style.font_size)
+            .collect();
+        if scale != 1. {
+            for (section, base) in text.sections.iter_mut().zip(base.iter()) {
+                section.style.font_size = base * scale;
+            }
+        }
+        commands.entity(entity).insert(BaseFontSizes(base));
+    }
+
+    if !settings.is_changed() {
+        return;
+    }
+    for (base, mut text) in scaled_text_q.iter_mut() {
+        let changed = text
+            .sections
+            .iter()
+            .zip(base.0.iter())
+            .any(|(section, base)| section.style.font_size != base * scale);
+        if changed {
+            for (section, base) in text.sections.iter_mut().zip(base.0.iter()) {
+                section.style.font_size = base * scale;
+            }
+        }
+    }
+}
+
+fn apply_debug_palette(settings: Res<Settings>, mut style: ResMut<DebugRenderStyle>) {
+    if !settings.is_changed() {
+        return;
+    }
+    let wanted = if settings.accessibility.high_contrast_debug {
+        DebugRenderStyle::high_contrast()
+    } else {
+        DebugRenderStyle::default()
+    };
+    if *style != wanted {
+        *style = wanted;
+    }
+}
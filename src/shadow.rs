@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder},
+    shapes,
+};
+
+use crate::{
+    collision::{Aabb, AabbKind},
+    npc::NpcState,
+    phase::{Phase, PhaseExt},
+    PlayerTag,
+};
+
+const SHADOW_COLOR: Color = Color::rgba(0., 0., 0., 0.3);
+/// Shadow width relative to the collider width.
+const SHADOW_WIDTH_SCALE: f32 = 0.8;
+/// Shadow height relative to its width.
+const SHADOW_ASPECT: f32 = 0.35;
+/// Local depth below the owner. Being a child, the shadow follows the owner's
+/// z, so it stays under its sprite however that z changes.
+const SHADOW_DEPTH: f32 = -0.5;
+
+/// Marks the blob shadow drawn at the feet of its parent.
+#[derive(Component)]
+pub struct Shadow;
+
+pub struct ShadowPlugin;
+
+impl Plugin for ShadowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .in_phase(Phase::Ui)
+                .with_system(add_shadows),
+        );
+    }
+}
+
+fn add_shadows(
+    mut commands: Commands,
+    owner_q: Query<(Entity, &Children), Or<(Added<PlayerTag>, Added<NpcState>)>>,
+    aabb_q: Query<(&Aabb, &AabbKind)>,
+) {
+    for (owner, children) in owner_q.iter() {
+        let extents = children
+            .iter()
+            .filter_map(|child| aabb_q.get(*child).ok())
+            .find(|(_, kind)| **kind == AabbKind::Collider)
+            .map(|(aabb, _)| aabb.extents);
+        let extents = match extents {
+            Some(extents) => extents,
+            None => continue,
+        };
+
+        let radius_x = extents.x / 2. * SHADOW_WIDTH_SCALE;
+        let shadow = commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Ellipse {
+                    radii: Vec2::new(radius_x, radius_x * SHADOW_ASPECT),
+                    center: Vec2::ZERO,
+                },
+                DrawMode::Fill(FillMode::color(SHADOW_COLOR)),
+                Transform::from_translation(Vec3::new(0., -extents.y / 2., SHADOW_DEPTH)),
+            ))
+            .insert(Shadow)
+            .id();
+        commands.entity(owner).push_children(&[shadow]);
+    }
+}
@@ -0,0 +1,387 @@
+use bevy::prelude::*;
+
+use crate::{
+    floating_text::SpawnFloatingText,
+    frame_budget::TimedExt,
+    interaction::{InteractEvent, InteractionSystem},
+    inventory::Inventory,
+    localization::Localization,
+    menu::halt_velocities,
+    phase::{Phase, PhaseExt},
+    player_stats::PlayerStats,
+    set_state,
+    settings::Settings,
+    AppState, PlayerTag,
+};
+
+const TEXT_TOP: f32 = 80.;
+const TEXT_LEFT: f32 = 20.;
+
+/// An item a `Merchant` sells, and buys back for less.
+#[derive(Debug, Clone)]
+pub struct StockItem {
+    pub item: String,
+    /// Coins the player pays for one.
+    pub price: u32,
+    /// Coins the player gets for selling one.
+    pub buyback: u32,
+    /// How many the merchant has left to sell. Items sold to the merchant
+    /// are put back in stock.
+    pub count: u32,
+}
+
+impl StockItem {
+    pub fn new(item: &str, price: u32, buyback: u32, count: u32) -> Self {
+        Self {
+            item: item.to_string(),
+            price,
+            buyback,
+            count,
+        }
+    }
+}
+
+/// Why a trade didn't go through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeError {
+    SoldOut,
+    NotEnoughCoins,
+    NothingToSell,
+}
+
+impl TradeError {
+    fn key(self) -> &'static str {
+        match self {
+            TradeError::SoldOut => "shop.sold_out",
+            TradeError::NotEnoughCoins => "shop.not_enough_coins",
+            TradeError::NothingToSell => "shop.nothing_to_sell",
+        }
+    }
+}
+
+/// Interacting with a merchant opens their shop, where the player trades the
+/// items in `stock` for `PlayerStats::coins`. Merchants only buy what they
+/// sell.
+#[derive(Component, Debug, Clone)]
+pub struct Merchant {
+    pub stock: Vec<StockItem>,
+}
+
+impl Merchant {
+    /// Sells hay for feeding cows and buys the milk they give.
+    pub fn dairy() -> Self {
+        Self {
+            stock: vec![
+                StockItem::new("Hay", 4, 2, 10),
+                StockItem::new("Milk", 12, 6, 0),
+            ],
+        }
+    }
+
+    /// Indices of the stock the player holds some of, and could sell.
+    fn sellable(&self, inventory: &Inventory) -> Vec<usize> {
+        (0..self.stock.len())
+            .filter(|i| inventory.count(&self.stock[*i].item) > 0)
+            .collect()
+    }
+
+    /// Moves one of `stock[index]` into the player's inventory.
+    pub fn buy(
+        &mut self,
+        index: usize,
+        inventory: &mut Inventory,
+        stats: &mut PlayerStats,
+    ) -> Result<(), TradeError> {
+        let stock = &mut self.stock[index];
+        if stock.count == 0 {
+            return Err(TradeError::SoldOut);
+        }
+        if stats.coins < stock.price {
+            return Err(TradeError::NotEnoughCoins);
+        }
+        stats.coins -= stock.price;
+        stock.count -= 1;
+        inventory.add(&stock.item, 1);
+        Ok(())
+    }
+
+    /// Moves one of `stock[index]` from the player's inventory into stock.
+    pub fn sell(
+        &mut self,
+        index: usize,
+        inventory: &mut Inventory,
+        stats: &mut PlayerStats,
+    ) -> Result<(), TradeError> {
+        let stock = &mut self.stock[index];
+        if !inventory.take(&stock.item, 1) {
+            return Err(TradeError::NothingToSell);
+        }
+        stock.count += 1;
+        stats.coins += stock.buyback;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShopMode {
+    Buy,
+    Sell,
+}
+
+/// The shop being browsed, present while in `AppState::Shop`.
+pub struct ActiveShop {
+    merchant: Entity,
+    mode: ShopMode,
+    /// Index into the listed items, one past the last for leaving.
+    selected: usize,
+}
+
+impl ActiveShop {
+    /// Stock indices listed in the current mode.
+    fn listed(&self, merchant: &Merchant, inventory: &Inventory) -> Vec<usize> {
+        match self.mode {
+            ShopMode::Buy => (0..merchant.stock.len()).collect(),
+            ShopMode::Sell => merchant.sellable(inventory),
+        }
+    }
+}
+
+#[derive(Component)]
+struct ShopTextTag;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ShopSystem {
+    Input,
+}
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .in_phase(Phase::Gameplay)
+                .with_system(open_shop.after(InteractionSystem::Interact)),
+        )
+        .add_system_set(
+            SystemSet::on_enter(AppState::Shop)
+                .with_system(halt_velocities)
+                .with_system(setup_shop_text),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Shop)
+                .in_phase(Phase::Input)
+                .with_system(shop_input.timed().label(ShopSystem::Input)),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Shop)
+                .in_phase(Phase::Ui)
+                .with_system(update_shop_text.after(ShopSystem::Input)),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Shop).with_system(despawn_shop_text));
+    }
+}
+
+fn open_shop(
+    mut commands: Commands,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut interact_events: EventReader<InteractEvent>,
+    merchant_q: Query<(), With<Merchant>>,
+) {
+    for event in interact_events.iter() {
+        if merchant_q.get(event.target).is_err() {
+            continue;
+        }
+        if !set_state(&mut state, AppState::Shop) {
+            return;
+        }
+        commands.insert_resource(ActiveShop {
+            merchant: event.target,
+            mode: ShopMode::Buy,
+            selected: 0,
+        });
+        // The interact press that opened the shop shouldn't also buy something
+        keys.clear();
+        return;
+    }
+}
+
+fn setup_shop_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(TEXT_TOP),
+                    left: Val::Px(TEXT_LEFT),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 24.,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(ShopTextTag);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn shop_input(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    mut state: ResMut<State<AppState>>,
+    mut inventory: ResMut<Inventory>,
+    mut stats: ResMut<PlayerStats>,
+    mut floating_texts: EventWriter<SpawnFloatingText>,
+    active: Option<ResMut<ActiveShop>>,
+    player_q: Query<Entity, With<PlayerTag>>,
+    mut merchant_q: Query<&mut Merchant>,
+) {
+    let mut active = match active {
+        Some(active) => active,
+        None => return,
+    };
+    let mut merchant = match merchant_q.get_mut(active.merchant) {
+        Ok(merchant) => merchant,
+        Err(_) => {
+            commands.remove_resource::<ActiveShop>();
+            set_state(&mut state, AppState::Playing);
+            return;
+        }
+    };
+    let bindings = &settings.key_bindings;
+    let listed = active.listed(&merchant, &inventory);
+    // The listed items and leaving
+    let option_count = listed.len() + 1;
+
+    if keys.just_pressed(bindings.left)
+        || keys.just_pressed(KeyCode::Left)
+        || keys.just_pressed(bindings.right)
+        || keys.just_pressed(KeyCode::Right)
+    {
+        active.mode = match active.mode {
+            ShopMode::Buy => ShopMode::Sell,
+            ShopMode::Sell => ShopMode::Buy,
+        };
+        active.selected = 0;
+    } else if keys.just_pressed(bindings.up) || keys.just_pressed(KeyCode::Up) {
+        active.selected = (active.selected + option_count - 1) % option_count;
+    } else if keys.just_pressed(bindings.down) || keys.just_pressed(KeyCode::Down) {
+        active.selected = (active.selected + 1) % option_count;
+    } else if keys.just_pressed(bindings.interact) {
+        let index = match listed.get(active.selected) {
+            Some(index) => *index,
+            None => {
+                commands.remove_resource::<ActiveShop>();
+                // A transition already queued closes the shop just as well
+                set_state(&mut state, AppState::Playing);
+                return;
+            }
+        };
+        let item = merchant.stock[index].item.clone();
+        let result = match active.mode {
+            ShopMode::Buy => merchant
+                .buy(index, &mut inventory, &mut stats)
+                .map(|()| format!("+1 {}", localization.get(&item))),
+            ShopMode::Sell => merchant
+                .sell(index, &mut inventory, &mut stats)
+                .map(|()| format!("-1 {}", localization.get(&item))),
+        };
+        let (text, color) = match result {
+            Ok(text) => (text, Color::WHITE),
+            Err(err) => (localization.get(err.key()), Color::GRAY),
+        };
+        floating_texts.send(SpawnFloatingText {
+            anchor: player_q.single(),
+            text,
+            color,
+        });
+        // Selling the last of an item takes it off the list
+        let listed = active.listed(&merchant, &inventory).len();
+        active.selected = active.selected.min(listed);
+    }
+}
+
+fn update_shop_text(
+    localization: Res<Localization>,
+    inventory: Res<Inventory>,
+    stats: Res<PlayerStats>,
+    active: Option<Res<ActiveShop>>,
+    merchant_q: Query<(&Merchant, Option<&Name>)>,
+    mut text_q: Query<&mut Text, With<ShopTextTag>>,
+) {
+    let active = match active {
+        Some(active) => active,
+        None => return,
+    };
+    let (merchant, name) = match merchant_q.get(active.merchant) {
+        Ok(merchant) => merchant,
+        Err(_) => return,
+    };
+
+    let tab = |mode: ShopMode, key: &str| {
+        let label = localization.get(key);
+        if mode == active.mode {
+            format!("[{}]", label)
+        } else {
+            format!(" {} ", label)
+        }
+    };
+    let coins = stats.coins.to_string();
+    let mut lines = vec![
+        name.map_or_else(String::new, |name| localization.get(name.as_str())),
+        localization.format("shop.coins", &[("count", &coins)]),
+        format!(
+            "{} {}",
+            tab(ShopMode::Buy, "shop.buy"),
+            tab(ShopMode::Sell, "shop.sell")
+        ),
+        String::new(),
+    ];
+    let listed = active.listed(merchant, &inventory);
+    for (i, index) in listed.iter().enumerate() {
+        let stock = &merchant.stock[*index];
+        let (price, count) = match active.mode {
+            ShopMode::Buy => (stock.price, stock.count),
+            ShopMode::Sell => (stock.buyback, inventory.count(&stock.item)),
+        };
+        let cursor = if i == active.selected { "> " } else { "  " };
+        let price = price.to_string();
+        lines.push(format!(
+            "{}{} x{}  {}",
+            cursor,
+            localization.get(&stock.item),
+            count,
+            localization.format("shop.coins", &[("count", &price)]),
+        ));
+    }
+    let cursor = if active.selected == listed.len() {
+        "> "
+    } else {
+        "  "
+    };
+    lines.push(format!("{}{}", cursor, localization.get("shop.leave")));
+    let value = lines.join("\n");
+
+    for mut text in text_q.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn despawn_shop_text(mut commands: Commands, text_q: Query<Entity, With<ShopTextTag>>) {
+    for entity in text_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Write as _;
+
+use bevy::{
+    diagnostic::{Diagnostics, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    chunk::WorldChunks,
+    collision::{CollisionWorld, PhysicsStats},
+    frame_budget::{SystemTimings, TimedExt},
+    phase::{Phase, PhaseExt},
+};
+
+/// Slowest timed systems listed, see `SystemTimings`.
+const SLOWEST_SYSTEMS: usize = 3;
+
+#[derive(Component)]
+struct StatsTextTag;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StatsSystem {
+    Toggle,
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+            .add_plugin(EntityCountDiagnosticsPlugin::default())
+            .add_startup_system(setup_stats_text)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(toggle_stats.timed().label(StatsSystem::Toggle)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(update_stats_text.after(StatsSystem::Toggle)),
+            );
+    }
+}
+
+fn setup_stats_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.),
+                    right: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("Share-Regular.ttf"),
+                    font_size: 20.,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(StatsTextTag);
+}
+
+fn toggle_stats(keys: Res<Input<KeyCode>>, mut text_q: Query<&mut Visibility, With<StatsTextTag>>) {
+    if keys.just_pressed(KeyCode::F4) {
+        for mut visibility in text_q.iter_mut() {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+}
+
+fn update_stats_text(
+    diagnostics: Res<Diagnostics>,
+    collision_world: Res<CollisionWorld>,
+    stats: Res<PhysicsStats>,
+    world_chunks: Res<WorldChunks>,
+    timings: Res<SystemTimings>,
+    mut text_q: Query<(&mut Text, &Visibility), With<StatsTextTag>>,
+) {
+    let diagnostic = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.average())
+            .unwrap_or(0.)
+    };
+    for (mut text, visibility) in text_q.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+        text.sections[0].value = format!(
+            "FPS: {:.0}\nEntities: {:.0}\nAABBs: {}\nPairs tested: {}\nPairs collided: {}\nPhysics: {:.3} ms\nChunks: {}/{} ({} walls queued)",
+            diagnostic(FrameTimeDiagnosticsPlugin::FPS),
+            diagnostic(EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+            collision_world.aabbs.len(),
+            stats.pairs_tested,
+            stats.pairs_collided,
+            stats.stage_time.as_secs_f64() * 1000.,
+            world_chunks.loaded_count(),
+            world_chunks.chunk_count(),
+            world_chunks.pending_count(),
+        );
+        // Marked with `!` while over the frame budget
+        for (name, time) in timings.last_frame.iter().take(SLOWEST_SYSTEMS) {
+            let _ = write!(
+                text.sections[0].value,
+                "\n{}{}: {:.3} ms",
+                if timings.is_over_budget(*time) {
+                    "!"
+                } else {
+                    ""
+                },
+                name,
+                time.as_secs_f64() * 1000.,
+            );
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, RectangleOrigin},
+    shapes,
+};
+use bevy_spicy_aseprite::AsepriteTag;
+
+use crate::{
+    collision::{AabbBundle, AabbKind, CollisionBehavior, CollisionWorld, Velocity},
+    layer::RenderLayerGroup,
+    weather::Weather,
+};
+
+/// How much more heavy rain slows down already slow terrain.
+const RAIN_SLOWDOWN: f32 = 0.3;
+
+/// Ground that changes how things move while their collider overlaps the
+/// zone's sensor.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct TerrainModifier {
+    pub speed_multiplier: f32,
+    /// West and east walk tags the player uses instead of the regular ones.
+    pub player_walk_tags: Option<[AsepriteTag; 2]>,
+    pub color: Color,
+    /// Color of the footprints walking here leaves, see `LeavesFootprints`.
+    pub footprint_color: Option<Color>,
+}
+
+impl TerrainModifier {
+    pub fn water() -> Self {
+        Self {
+            speed_multiplier: 0.5,
+            // player.ase has no swimming tags yet
+            player_walk_tags: None,
+            color: Color::rgba(0.2, 0.45, 0.8, 0.7),
+            footprint_color: None,
+        }
+    }
+
+    pub fn mud() -> Self {
+        Self {
+            speed_multiplier: 0.7,
+            player_walk_tags: None,
+            color: Color::rgb(0.4, 0.28, 0.16),
+            footprint_color: Some(Color::rgba(0.25, 0.16, 0.08, 0.9)),
+        }
+    }
+
+    pub fn path() -> Self {
+        Self {
+            speed_multiplier: 1.2,
+            player_walk_tags: None,
+            color: Color::rgb(0.8, 0.7, 0.5),
+            footprint_color: Some(Color::rgba(0.6, 0.5, 0.32, 0.6)),
+        }
+    }
+}
+
+/// The terrain an entity is currently on, if any. Kept up to date for
+/// everything with a `Velocity` by `apply_terrain_modifiers`.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct OnTerrain {
+    pub modifier: Option<TerrainModifier>,
+    /// What physics scales the entity's velocity by when integrating it, the
+    /// modifier's multiplier adjusted for the weather, 1 off terrain.
+    pub speed_multiplier: f32,
+}
+
+impl Default for OnTerrain {
+    fn default() -> Self {
+        Self {
+            modifier: None,
+            speed_multiplier: 1.,
+        }
+    }
+}
+
+pub fn spawn_terrain_zone(
+    commands: &mut Commands,
+    position: Vec2,
+    size: Vec2,
+    modifier: TerrainModifier,
+) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: size,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Fill(FillMode::color(modifier.color)),
+            Transform::from_translation(position.extend(RenderLayerGroup::Background.z())),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                size,
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::BLUE,
+            ));
+        })
+        .insert(modifier)
+        .id()
+}
+
+/// Updates the `OnTerrain` of everything that moves, whose velocity physics
+/// then scales by the terrain's multiplier when integrating it. The
+/// `Velocity` itself is left alone, as not every mover sets it every frame.
+///
+/// Where zones overlap, the slowest one wins. Rain makes slow ground slower.
+pub fn apply_terrain_modifiers(
+    mut commands: Commands,
+    collision_world: Res<CollisionWorld>,
+    weather: Res<Weather>,
+    zone_q: Query<&TerrainModifier>,
+    mut mover_q: Query<(Entity, Option<&mut OnTerrain>), With<Velocity>>,
+) {
+    let mut terrain: HashMap<Entity, TerrainModifier> = HashMap::default();
+    for (sensor, other) in collision_world.sensor_overlaps.iter() {
+        let modifier = match zone_q.get(*sensor) {
+            Ok(modifier) => *modifier,
+            Err(_) => continue,
+        };
+        terrain
+            .entry(*other)
+            .and_modify(|current| {
+                if modifier.speed_multiplier < current.speed_multiplier {
+                    *current = modifier;
+                }
+            })
+            .or_insert(modifier);
+    }
+
+    for (entity, on_terrain) in mover_q.iter_mut() {
+        let modifier = terrain.get(&entity).copied();
+        let speed_multiplier = modifier.map_or(1., |modifier| {
+            let mut multiplier = modifier.speed_multiplier;
+            if multiplier < 1. {
+                multiplier *= 1. - RAIN_SLOWDOWN * weather.rain();
+            }
+            multiplier
+        });
+        let updated = OnTerrain {
+            modifier,
+            speed_multiplier,
+        };
+        match on_terrain {
+            Some(mut on_terrain) => {
+                if *on_terrain != updated {
+                    *on_terrain = updated;
+                }
+            }
+            None if modifier.is_some() => {
+                commands.entity(entity).insert(updated);
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::phase::{Phase, PhaseExt};
+
+/// Time scale while a hit-stop is running, relative to `TimeScale::scale`.
+const HIT_STOP_SCALE: f32 = 0.05;
+const SLOW_MOTION_SCALE: f32 = 0.25;
+const FAST_FORWARD_SCALE: f32 = 2.;
+
+/// Game speed for movement, physics and animation systems, which should use
+/// `delta`/`delta_seconds` instead of `Time`'s directly. UI and real time
+/// bookkeeping like play time keep using `Time`.
+pub struct TimeScale {
+    pub scale: f32,
+    /// Steps by this many seconds every frame instead of the frame time, for
+    /// replays to play out the same regardless of frame rate.
+    pub fixed_delta: Option<f32>,
+    hit_stop: Timer,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        let mut hit_stop = Timer::from_seconds(0., false);
+        hit_stop.tick(Duration::ZERO);
+        Self {
+            scale: 1.,
+            fixed_delta: None,
+            hit_stop,
+        }
+    }
+}
+
+impl TimeScale {
+    pub fn get(&self) -> f32 {
+        if self.hit_stop.finished() {
+            self.scale
+        } else {
+            self.scale * HIT_STOP_SCALE
+        }
+    }
+
+    pub fn delta(&self, time: &Time) -> Duration {
+        self.unscaled_delta(time).mul_f32(self.get())
+    }
+
+    pub fn delta_seconds(&self, time: &Time) -> f32 {
+        self.unscaled_delta(time).as_secs_f32() * self.get()
+    }
+
+    /// Frame time, or the fixed step if there is one.
+    pub fn unscaled_delta(&self, time: &Time) -> Duration {
+        self.fixed_delta
+            .map_or(time.delta(), Duration::from_secs_f32)
+    }
+
+    /// Nearly stops time for `secs` real seconds, to give heavy hits weight.
+    pub fn hit_stop(&mut self, secs: f32) {
+        self.hit_stop = Timer::from_seconds(secs, false);
+    }
+}
+
+pub struct TimeScalePlugin;
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeScale>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(time_scale_hotkeys),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Gameplay)
+                    .with_system(tick_hit_stop),
+            );
+    }
+}
+
+/// F6 toggles slow motion, F7 fast forward.
+fn time_scale_hotkeys(keys: Res<Input<KeyCode>>, mut time_scale: ResMut<TimeScale>) {
+    let toggle = |current: f32, scale: f32| if current == scale { 1. } else { scale };
+    if keys.just_pressed(KeyCode::F6) {
+        time_scale.scale = toggle(time_scale.scale, SLOW_MOTION_SCALE);
+        info!("Time scale {}x", time_scale.scale);
+    }
+    if keys.just_pressed(KeyCode::F7) {
+        time_scale.scale = toggle(time_scale.scale, FAST_FORWARD_SCALE);
+        info!("Time scale {}x", time_scale.scale);
+    }
+}
+
+/// Hit-stops last for real time, not scaled time.
+fn tick_hit_stop(time: Res<Time>, mut time_scale: ResMut<TimeScale>) {
+    let delta = time_scale.unscaled_delta(&time);
+    time_scale.hit_stop.tick(delta);
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{AabbBundle, AabbKind, CollisionBehavior, SensorEvent},
+    frame_budget::TimedExt,
+    persist::{Persist, PersistAppExt},
+    phase::{Phase, PhaseExt},
+    time_scale::TimeScale,
+    AppState, PlayerTag,
+};
+
+/// When a `TriggerZone` fires again after it has fired once.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TriggerPolicy {
+    /// Never again, also after loading a save, e.g. for story events.
+    Once,
+    /// On entering once this many seconds have passed since it last fired.
+    Cooldown(f32),
+    EveryEnter,
+}
+
+impl Default for TriggerPolicy {
+    fn default() -> Self {
+        TriggerPolicy::EveryEnter
+    }
+}
+
+/// Fires a `TriggerFired` when the player walks into the zone's sensor, as
+/// often as its policy allows.
+///
+/// Only `fired` is saved, the policy comes from wherever the zone is spawned.
+#[derive(Component, Reflect, Debug, Default, Clone)]
+#[reflect(Component)]
+pub struct TriggerZone {
+    #[reflect(ignore)]
+    pub policy: TriggerPolicy,
+    pub fired: bool,
+    /// Seconds until a `Cooldown` zone can fire again.
+    #[reflect(ignore)]
+    cooldown: f32,
+}
+
+impl TriggerZone {
+    pub fn new(policy: TriggerPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Fires unless the policy forbids it.
+    fn try_fire(&mut self) -> bool {
+        let allowed = match self.policy {
+            TriggerPolicy::Once => !self.fired,
+            TriggerPolicy::Cooldown(_) => self.cooldown <= 0.,
+            TriggerPolicy::EveryEnter => true,
+        };
+        if !allowed {
+            return false;
+        }
+        self.fired = true;
+        if let TriggerPolicy::Cooldown(secs) = self.policy {
+            self.cooldown = secs;
+        }
+        true
+    }
+}
+
+/// Sent when the player enters a `TriggerZone` that was allowed to fire.
+#[derive(Debug, Clone, Copy)]
+pub struct TriggerFired {
+    pub zone: Entity,
+}
+
+/// Label of `fire_triggers`, which sends the events of trigger zones.
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriggerSystem {
+    Fire,
+}
+
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.persist_component::<TriggerZone>()
+            .add_event::<TriggerFired>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Ai)
+                    .with_system(fire_triggers.timed().label(TriggerSystem::Fire)),
+            );
+    }
+}
+
+/// Spawns a trigger zone. `key` identifies it in saves, see `Persist`.
+pub fn spawn_trigger_zone(
+    commands: &mut Commands,
+    key: &str,
+    position: Vec2,
+    size: Vec2,
+    policy: TriggerPolicy,
+) -> Entity {
+    commands
+        .spawn_bundle((
+            Transform::from_translation(position.extend(0.)),
+            GlobalTransform::default(),
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(AabbBundle::new(
+                size,
+                AabbKind::Sensor,
+                CollisionBehavior::None,
+                Color::ORANGE,
+            ));
+        })
+        .insert(TriggerZone::new(policy))
+        .insert(Persist::new(key))
+        .id()
+}
+
+fn fire_triggers(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut sensor_events: EventReader<SensorEvent>,
+    mut fired: EventWriter<TriggerFired>,
+    player_q: Query<(), With<PlayerTag>>,
+    mut zone_q: Query<&mut TriggerZone>,
+) {
+    let delta = time_scale.delta_seconds(&time);
+    for mut zone in zone_q.iter_mut() {
+        if zone.cooldown > 0. {
+            zone.cooldown -= delta;
+        }
+    }
+
+    for event in sensor_events.iter() {
+        let (sensor, other) = match *event {
+            SensorEvent::Entered { sensor, other } => (sensor, other),
+            SensorEvent::Exited { .. } => continue,
+        };
+        if player_q.get(other).is_err() {
+            continue;
+        }
+        if let Ok(mut zone) = zone_q.get_mut(sensor) {
+            if zone.try_fire() {
+                debug!("Trigger {:?} fired", sensor);
+                fired.send(TriggerFired { zone: sensor });
+            }
+        }
+    }
+}
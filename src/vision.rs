@@ -0,0 +1,185 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_prototype_lyon::{
+    prelude::{DrawMode, FillMode, GeometryBuilder, StrokeMode},
+    shapes,
+};
+
+use crate::{
+    anim::AnimStateMachine,
+    collision::{CollisionBehavior, CollisionWorld, QueryMask},
+    frame_budget::TimedExt,
+    phase::{Phase, PhaseExt},
+    AppState, DebugRenderTag, PlayerTag,
+};
+
+/// Segments approximating the cone's arc in the debug render.
+const ARC_SEGMENTS: usize = 8;
+const CONE_COLOR: Color = Color::rgba(1., 1., 0., 0.6);
+const SPOTTED_COLOR: Color = Color::rgba(1., 0.2, 0.2, 0.6);
+
+/// Lets an NPC see the player within `radius` and `half_angle` radians of
+/// where it faces, unless a static collider is in the way.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct VisionCone {
+    pub radius: f32,
+    pub half_angle: f32,
+    pub sees_player: bool,
+}
+
+impl VisionCone {
+    pub fn new(radius: f32, half_angle: f32) -> Self {
+        Self {
+            radius,
+            half_angle,
+            sees_player: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerSpotted {
+    pub npc: Entity,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerLost {
+    pub npc: Entity,
+}
+
+/// Debug render of the cone, a child of the NPC.
+#[derive(Component)]
+struct VisionConeShape;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum VisionSystem {
+    Shapes,
+    Update,
+}
+
+pub struct VisionPlugin;
+
+impl Plugin for VisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerSpotted>()
+            .add_event::<PlayerLost>()
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(spawn_cone_shapes)
+                    .with_system(update_cone_shapes.timed().label(VisionSystem::Shapes)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Ai)
+                    .with_system(
+                        update_vision
+                            .timed()
+                            .label(VisionSystem::Update)
+                            .before(VisionSystem::Shapes),
+                    )
+                    .with_system(log_vision_events.after(VisionSystem::Update)),
+            );
+    }
+}
+
+fn update_vision(
+    collision_world: Res<CollisionWorld>,
+    player_q: Query<&GlobalTransform, With<PlayerTag>>,
+    mut npc_q: Query<(Entity, &GlobalTransform, &AnimStateMachine, &mut VisionCone)>,
+    mut spotted: EventWriter<PlayerSpotted>,
+    mut lost: EventWriter<PlayerLost>,
+) {
+    let player_pos = player_q.single().translation.xy();
+    for (npc, npc_trans, machine, mut cone) in npc_q.iter_mut() {
+        let to_player = player_pos - npc_trans.translation.xy();
+        let distance = to_player.length();
+        let in_cone = distance <= cone.radius
+            && (distance == 0.
+                || machine.facing().direction().angle_between(to_player).abs() <= cone.half_angle);
+        let sees_player = in_cone
+            && collision_world
+                .raycast(
+                    npc_trans.translation.xy(),
+                    to_player,
+                    distance,
+                    QueryMask {
+                        exclude: Some(npc),
+                        ..QueryMask::COLLIDERS.with_behavior(CollisionBehavior::Static)
+                    },
+                )
+                .is_none();
+
+        if sees_player != cone.sees_player {
+            cone.sees_player = sees_player;
+            if sees_player {
+                spotted.send(PlayerSpotted { npc });
+            } else {
+                lost.send(PlayerLost { npc });
+            }
+        }
+    }
+}
+
+fn log_vision_events(mut spotted: EventReader<PlayerSpotted>, mut lost: EventReader<PlayerLost>) {
+    let _span = info_span!("ai").entered();
+    for event in spotted.iter() {
+        debug!("{:?} spotted the player", event.npc);
+    }
+    for event in lost.iter() {
+        debug!("{:?} lost sight of the player", event.npc);
+    }
+}
+
+fn spawn_cone_shapes(
+    mut commands: Commands,
+    cone_q: Query<(Entity, &VisionCone), Added<VisionCone>>,
+) {
+    for (npc, cone) in cone_q.iter() {
+        // Pointing along +x, rotated towards the facing in `update_cone_shapes`
+        let mut points = vec![Vec2::ZERO];
+        points.extend((0..=ARC_SEGMENTS).map(|i| {
+            let t = i as f32 / ARC_SEGMENTS as f32;
+            let angle = -cone.half_angle + t * 2. * cone.half_angle;
+            Vec2::new(angle.cos(), angle.sin()) * cone.radius
+        }));
+        let shape = commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Polygon {
+                    points,
+                    closed: true,
+                },
+                DrawMode::Outlined {
+                    fill_mode: FillMode::color(Color::NONE),
+                    outline_mode: StrokeMode::color(CONE_COLOR),
+                },
+                Transform::default(),
+            ))
+            .insert(VisionConeShape)
+            .insert(DebugRenderTag)
+            .id();
+        commands.entity(npc).push_children(&[shape]);
+    }
+}
+
+fn update_cone_shapes(
+    npc_q: Query<(&AnimStateMachine, &VisionCone, &Children)>,
+    mut shape_q: Query<(&mut Transform, &mut DrawMode), With<VisionConeShape>>,
+) {
+    for (machine, cone, children) in npc_q.iter() {
+        let direction = machine.facing().direction();
+        let color = if cone.sees_player {
+            SPOTTED_COLOR
+        } else {
+            CONE_COLOR
+        };
+        for child in children.iter() {
+            if let Ok((mut trans, mut draw_mode)) = shape_q.get_mut(*child) {
+                trans.rotation = Quat::from_rotation_z(direction.y.atan2(direction.x));
+                *draw_mode = DrawMode::Outlined {
+                    fill_mode: FillMode::color(Color::NONE),
+                    outline_mode: StrokeMode::color(color),
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+use crate::{
+    frame_budget::TimedExt,
+    phase::{Phase, PhaseExt},
+    AppState,
+};
+
+/// Real seconds between weather changes.
+const WEATHER_SECS: f32 = 120.;
+/// Seconds to fade the current weather out, and the next one in.
+const TRANSITION_SECS: f32 = 4.;
+/// Weather cycled through over time.
+const FORECAST: [WeatherKind; 4] = [
+    WeatherKind::Clear,
+    WeatherKind::Rain,
+    WeatherKind::Clear,
+    WeatherKind::Fog,
+];
+const RAIN_DROP_COUNT: usize = 120;
+const RAIN_DROP_SIZE: Vec2 = Vec2::new(2., 14.);
+/// Screen heights per second.
+const RAIN_FALL_SPEED: f32 = 1.4;
+/// Screen widths per second, leaning the rain a bit.
+const RAIN_DRIFT_SPEED: f32 = -0.15;
+const RAIN_COLOR: Color = Color::rgba(0.7, 0.8, 1., 0.5);
+const FOG_COLOR: Color = Color::rgba(0.8, 0.82, 0.85, 0.55);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Fog,
+}
+
+/// The current weather and how strongly it has set in, for effects and
+/// gameplay systems to scale with.
+pub struct Weather {
+    kind: WeatherKind,
+    /// How far `kind` has faded in, in `0..=1`.
+    intensity: f32,
+    /// Weather to switch to once `kind` has faded out.
+    next: Option<WeatherKind>,
+    forecast_index: usize,
+    timer: Timer,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            kind: FORECAST[0],
+            intensity: 1.,
+            next: None,
+            forecast_index: 0,
+            timer: Timer::from_seconds(WEATHER_SECS, true),
+        }
+    }
+}
+
+impl Weather {
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    /// Fades over to `kind`, unless already there.
+    pub fn transition_to(&mut self, kind: WeatherKind) {
+        if kind != self.kind || self.next.is_some() {
+            self.next = Some(kind);
+        }
+    }
+
+    fn intensity_of(&self, kind: WeatherKind) -> f32 {
+        if self.kind == kind {
+            self.intensity
+        } else {
+            0.
+        }
+    }
+
+    /// Rain strength in `0..=1`.
+    pub fn rain(&self) -> f32 {
+        self.intensity_of(WeatherKind::Rain)
+    }
+
+    /// Fog density in `0..=1`.
+    pub fn fog(&self) -> f32 {
+        self.intensity_of(WeatherKind::Fog)
+    }
+}
+
+/// A rain drop, positioned in fractions of the screen size.
+#[derive(Component)]
+struct RainDrop {
+    position: Vec2,
+}
+
+#[derive(Component)]
+struct FogTag;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum WeatherSystem {
+    Hotkeys,
+    Update,
+}
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Weather>()
+            .add_startup_system(setup_weather_layers)
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Input)
+                    .with_system(weather_hotkeys.timed().label(WeatherSystem::Hotkeys)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .in_phase(Phase::Gameplay)
+                    .with_system(
+                        update_weather
+                            .timed()
+                            .label(WeatherSystem::Update)
+                            .after(WeatherSystem::Hotkeys),
+                    )
+                    .with_system(fall_rain.after(WeatherSystem::Update)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .in_phase(Phase::Ui)
+                    .with_system(render_weather.after(WeatherSystem::Update)),
+            );
+    }
+}
+
+fn setup_weather_layers(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .insert(FogTag);
+
+    // Scattered with a fixed LCG, so the drops don't start in lockstep
+    let mut seed: u32 = 0x2545_f491;
+    let mut next = || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (seed >> 8) as f32 / (1 << 24) as f32
+    };
+    for _ in 0..RAIN_DROP_COUNT {
+        let position = Vec2::new(next(), next());
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(RAIN_DROP_SIZE.x), Val::Px(RAIN_DROP_SIZE.y)),
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                color: UiColor(Color::NONE),
+                ..Default::default()
+            })
+            .insert(RainDrop { position });
+    }
+}
+
+/// F8 moves on to the next weather in the forecast.
+fn weather_hotkeys(keys: Res<Input<KeyCode>>, mut weather: ResMut<Weather>) {
+    if keys.just_pressed(KeyCode::F8) {
+        weather.timer.reset();
+        weather.forecast_index = (weather.forecast_index + 1) % FORECAST.len();
+        let kind = FORECAST[weather.forecast_index];
+        weather.transition_to(kind);
+        info!("Weather changing to {:?}", kind);
+    }
+}
+
+fn update_weather(time: Res<Time>, mut weather: ResMut<Weather>) {
+    let fade = time.delta_seconds() / TRANSITION_SECS;
+    if weather.timer.tick(time.delta()).just_finished() {
+        weather.forecast_index = (weather.forecast_index + 1) % FORECAST.len();
+        let kind = FORECAST[weather.forecast_index];
+        weather.transition_to(kind);
+    }
+
+    match weather.next {
+        Some(next) => {
+            weather.intensity = (weather.intensity - fade).max(0.);
+            if weather.intensity == 0. {
+                weather.kind = next;
+                weather.next = None;
+            }
+        }
+        None => weather.intensity = (weather.intensity + fade).min(1.),
+    }
+}
+
+fn fall_rain(time: Res<Time>, weather: Res<Weather>, mut drop_q: Query<&mut RainDrop>) {
+    if weather.rain() == 0. {
+        return;
+    }
+    let step = Vec2::new(RAIN_DRIFT_SPEED, RAIN_FALL_SPEED) * time.delta_seconds();
+    for mut drop in drop_q.iter_mut() {
+        drop.position += step;
+        drop.position.x = drop.position.x.rem_euclid(1.);
+        drop.position.y = drop.position.y.rem_euclid(1.);
+    }
+}
+
+fn render_weather(
+    weather: Res<Weather>,
+    mut drop_q: Query<(&RainDrop, &mut Style, &mut UiColor), Without<FogTag>>,
+    mut fog_q: Query<&mut UiColor, With<FogTag>>,
+) {
+    let rain = weather.rain();
+    for (drop, mut style, mut color) in drop_q.iter_mut() {
+        let mut drop_color = RAIN_COLOR;
+        drop_color.set_a(RAIN_COLOR.a() * rain);
+        color.0 = drop_color;
+        if rain > 0. {
+            style.position = Rect {
+                left: Val::Percent(drop.position.x * 100.),
+                top: Val::Percent(drop.position.y * 100.),
+                ..Default::default()
+            };
+        }
+    }
+    for mut color in fog_q.iter_mut() {
+        let mut fog_color = FOG_COLOR;
+        fog_color.set_a(FOG_COLOR.a() * weather.fog());
+        color.0 = fog_color;
+    }
+}
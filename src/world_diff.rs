@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::{
+    prelude::*,
+    reflect::{serde::ReflectSerializer, TypeRegistry},
+};
+
+use crate::collision::CollisionWorld;
+
+/// Components whose values aren't compared, as they change whenever anything
+/// moves. Added and removed ones still show.
+const UNCOMPARED: [&str; 2] = ["Transform", "GlobalTransform"];
+
+/// An entity as recorded by `WorldSnapshot::capture`.
+#[derive(Debug, Clone, PartialEq)]
+struct EntitySnapshot {
+    name: Option<String>,
+    parent: Option<Entity>,
+    /// Short type names of the components, with the serialized value of
+    /// reflected ones.
+    components: BTreeMap<String, Option<String>>,
+}
+
+/// Entities and their components at one point in time, recorded by the
+/// `snapshot` console command and compared to the live world by `diff`, to
+/// see what an action like loading a room spawned, despawned or changed.
+#[derive(Debug, Default, Clone)]
+pub struct WorldSnapshot {
+    entities: BTreeMap<Entity, EntitySnapshot>,
+}
+
+/// `bevy_transform::components::Transform` to `Transform`, leaving generic
+/// arguments as they are.
+fn short_name(name: &str) -> &str {
+    let head_len = name.find('<').unwrap_or(name.len());
+    let start = name[..head_len].rfind("::").map_or(0, |i| i + 2);
+    &name[start..]
+}
+
+impl WorldSnapshot {
+    pub fn capture(world: &World) -> Self {
+        let registry = world.get_resource::<TypeRegistry>().unwrap().clone();
+        let registry = registry.read();
+        let mut entities = BTreeMap::new();
+        for archetype in world.archetypes().iter() {
+            let infos: Vec<_> = archetype
+                .components()
+                .filter_map(|id| world.components().get_info(id))
+                .collect();
+            for entity in archetype.entities() {
+                let components = infos
+                    .iter()
+                    .map(|info| {
+                        let name = short_name(info.name()).to_string();
+                        let value = info
+                            .type_id()
+                            .filter(|_| !UNCOMPARED.contains(&name.as_str()))
+                            .and_then(|type_id| registry.get(type_id)?.data::<ReflectComponent>())
+                            .and_then(|reflect| reflect.reflect_component(world, *entity))
+                            .and_then(|component| {
+                                ron::to_string(&ReflectSerializer::new(component, &registry)).ok()
+                            });
+                        (name, value)
+                    })
+                    .collect();
+                entities.insert(
+                    *entity,
+                    EntitySnapshot {
+                        name: world
+                            .get::<Name>(*entity)
+                            .map(|name| name.as_str().to_string()),
+                        parent: world.get::<Parent>(*entity).map(|parent| parent.0),
+                        components,
+                    },
+                );
+            }
+        }
+        Self { entities }
+    }
+
+    /// Lines describing how `after` differs from this snapshot: entities
+    /// added and removed, and components added, removed and changed on the
+    /// others. Also lists collision world entries of despawned entities, as
+    /// of `after`.
+    pub fn diff(&self, after: &WorldSnapshot, world: &World) -> Vec<String> {
+        let describe = |entity: &Entity, snapshot: &EntitySnapshot| {
+            let mut line = format!("{:?}", entity);
+            if let Some(name) = &snapshot.name {
+                line.push_str(&format!(" \"{}\"", name));
+            }
+            if let Some(parent) = snapshot.parent {
+                line.push_str(&format!(" under {:?}", parent));
+            }
+            line
+        };
+        let mut lines = Vec::new();
+        for (entity, snapshot) in after.entities.iter() {
+            if !self.entities.contains_key(entity) {
+                let components: Vec<&str> =
+                    snapshot.components.keys().map(String::as_str).collect();
+                lines.push(format!(
+                    "+ {} [{}]",
+                    describe(entity, snapshot),
+                    components.join(", ")
+                ));
+            }
+        }
+        for (entity, snapshot) in self.entities.iter() {
+            if !after.entities.contains_key(entity) {
+                lines.push(format!("- {}", describe(entity, snapshot)));
+            }
+        }
+        for (entity, before) in self.entities.iter() {
+            let after = match after.entities.get(entity) {
+                Some(after) if after != before => after,
+                _ => continue,
+            };
+            let mut changes = Vec::new();
+            if before.parent != after.parent {
+                changes.push(format!("parent {:?} -> {:?}", before.parent, after.parent));
+            }
+            let names: BTreeSet<&String> = before
+                .components
+                .keys()
+                .chain(after.components.keys())
+                .collect();
+            for name in names {
+                match (before.components.get(name), after.components.get(name)) {
+                    (None, Some(_)) => changes.push(format!("+{}", name)),
+                    (Some(_), None) => changes.push(format!("-{}", name)),
+                    (Some(before), Some(after)) if before != after => {
+                        changes.push(format!("~{}", name))
+                    }
+                    _ => {}
+                }
+            }
+            if !changes.is_empty() {
+                lines.push(format!(
+                    "~ {}: {}",
+                    describe(entity, after),
+                    changes.join(", ")
+                ));
+            }
+        }
+
+        let collision_world = world.get_resource::<CollisionWorld>().unwrap();
+        for (aabb, (owner, _)) in collision_world
+            .aabbs
+            .iter()
+            .chain(collision_world.disabled.iter())
+        {
+            if !after.entities.contains_key(aabb) || !after.entities.contains_key(owner) {
+                lines.push(format!(
+                    "! stale collision world entry {:?} of {:?}",
+                    aabb, owner
+                ));
+            }
+        }
+        lines
+    }
+}